@@ -3,15 +3,16 @@ use crate::{
     display_backend::DisplayBackend,
     error_handling_ctx,
     gtk::prelude::{ContainerExt, CssProviderExt, GtkWindowExt, MonitorExt, StyleContextExt, WidgetExt},
+    keybinds::KeybindAction,
     paths::EwwPaths,
     script_var_handler::ScriptVarHandlerHandle,
     state::scope_graph::{ScopeGraph, ScopeIndex},
     widgets::window::Window,
     window_arguments::WindowArguments,
     window_initiator::WindowInitiator,
-    *,
+    window_transition, *,
 };
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use codespan_reporting::files::Files;
 use eww_shared_util::{Span, VarName};
 use gdk::Monitor;
@@ -21,7 +22,7 @@ use itertools::Itertools;
 use once_cell::sync::Lazy;
 use simplexpr::{dynval::DynVal, SimplExpr};
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::{HashMap, HashSet},
     marker::PhantomData,
     rc::Rc,
@@ -31,11 +32,12 @@ use yuck::{
     config::{
         monitor::MonitorIdentifier,
         script_var_definition::ScriptVarDefinition,
+        window_definition::{WindowStacking, WindowTransition},
         window_geometry::{AnchorPoint, WindowGeometry},
     },
     error::DiagError,
     gen_diagnostic,
-    value::Coords,
+    value::{Coords, NumWithUnit},
 };
 
 /// A command for the eww daemon.
@@ -45,13 +47,24 @@ use yuck::{
 pub enum DaemonCommand {
     NoOp,
     UpdateVars(Vec<(VarName, DynVal)>),
+    /// Update the window-local variables of an open window, see `:locals`
+    UpdateWindowVars {
+        instance_id: String,
+        mappings: Vec<(VarName, DynVal)>,
+    },
     PollVars(Vec<VarName>),
-    ReloadConfigAndCss(DaemonResponseSender),
+    ReloadConfigAndCss {
+        /// Keep the current values of variables whose definition didn't change
+        soft: bool,
+        sender: DaemonResponseSender,
+    },
     OpenInspector,
     OpenMany {
         windows: Vec<(String, String)>,
         args: Vec<(String, VarName, DynVal)>,
         should_toggle: bool,
+        /// Close the windows that were opened if any of them fails to open
+        atomic: bool,
         sender: DaemonResponseSender,
     },
     OpenWindow {
@@ -74,16 +87,44 @@ pub enum DaemonCommand {
     CloseAll,
     PrintState {
         all: bool,
+        json: bool,
         sender: DaemonResponseSender,
     },
     GetVar {
-        name: String,
+        names: Vec<String>,
+        json: bool,
+        sender: DaemonResponseSender,
+    },
+    /// A monitor was added or removed, so windows need to be moved to the monitor they target now.
+    MonitorsChanged,
+    SubscribeVars {
+        names: Vec<VarName>,
         sender: DaemonResponseSender,
     },
     PrintDebug(DaemonResponseSender),
     PrintGraph(DaemonResponseSender),
+    InspectWindow {
+        window_id: String,
+        json: bool,
+        sender: DaemonResponseSender,
+    },
     ListWindows(DaemonResponseSender),
-    ListActiveWindows(DaemonResponseSender),
+    ListActiveWindows {
+        json: bool,
+        sender: DaemonResponseSender,
+    },
+    PrintErrors {
+        clear: bool,
+        sender: DaemonResponseSender,
+    },
+    Profile {
+        action: opts::ProfileAction,
+        sender: DaemonResponseSender,
+    },
+    MprisControl {
+        action: opts::MprisAction,
+        sender: DaemonResponseSender,
+    },
 }
 
 /// An opened window.
@@ -93,6 +134,23 @@ pub struct EwwWindow {
     pub scope_index: ScopeIndex,
     pub gtk_window: Window,
     pub destroy_event_handler_id: Option<glib::SignalHandlerId>,
+    /// Handler repositioning this window whenever the window it is anchored to changes, see `:anchor-to`
+    pub anchor_handler: Option<(glib::WeakRef<Window>, glib::SignalHandlerId)>,
+    pub stacking: WindowStacking,
+    /// Order of the window among the windows with the same stacking, see `:z-index`
+    pub z_index: i32,
+    /// Provider of the window's `:stylesheet`, which is registered for the whole screen while the window is open
+    pub css_provider: Option<gtk::CssProvider>,
+    /// Animation shown when closing the window, see `:close-transition`
+    pub close_transition: WindowTransition,
+    pub transition_duration: std::time::Duration,
+    /// Pointer grab held while the window is open, see `:close-on-unfocus`
+    pub unfocus_grab: Option<close_on_unfocus::UnfocusGrab>,
+    /// Names of the window-local variables, which can be changed with `eww update --id`
+    pub local_names: HashSet<VarName>,
+    /// Monitor the window was opened on, and the `:monitor` it was resolved from
+    pub monitor: Monitor,
+    pub monitor_identifier: Option<MonitorIdentifier>,
 }
 
 impl EwwWindow {
@@ -102,11 +160,50 @@ impl EwwWindow {
     /// and that script-vars get cleaned up properly
     pub fn close(self) {
         log::info!("Closing gtk window {}", self.name);
-        self.gtk_window.close();
+        if let Some(unfocus_grab) = &self.unfocus_grab {
+            unfocus_grab.release();
+        }
         if let Some(handler_id) = self.destroy_event_handler_id {
             self.gtk_window.disconnect(handler_id);
         }
+        if let Some((anchor_window, handler_id)) = self.anchor_handler {
+            if let Some(anchor_window) = anchor_window.upgrade() {
+                anchor_window.disconnect(handler_id);
+            }
+        }
+        let css_provider = self.css_provider;
+        window_transition::animate_close(&self.gtk_window, self.close_transition, self.transition_duration, move |gtk_window| {
+            gtk_window.close();
+            if let (Some(css_provider), Some(screen)) = (css_provider, gdk::Screen::default()) {
+                gtk::StyleContext::remove_provider_for_screen(&screen, &css_provider);
+            }
+        });
     }
+
+    /// Describe the window as it is currently shown, for `eww active-windows --json`.
+    pub fn to_json(&self, instance_id: &str) -> serde_json::Value {
+        let monitor = self
+            .gtk_window
+            .window()
+            .and_then(|gdk_window| self.gtk_window.display().monitor_at_window(&gdk_window))
+            .and_then(|monitor| monitor.model())
+            .map(|model| model.to_string());
+        let (x, y) = self.gtk_window.position();
+        let (width, height) = self.gtk_window.size();
+        serde_json::json!({
+            "id": instance_id,
+            "name": self.name,
+            "monitor": monitor,
+            "geometry": { "x": x, "y": y, "width": width, "height": height },
+        })
+    }
+}
+
+/// A client that gets sent the new values of some variables whenever they change.
+#[derive(Debug)]
+pub struct VarSubscription {
+    pub names: Vec<VarName>,
+    pub sender: DaemonResponseSender,
 }
 
 pub struct App<B: DisplayBackend> {
@@ -122,11 +219,18 @@ pub struct App<B: DisplayBackend> {
     /// When reloading the config, these should be opened again.
     pub failed_windows: HashSet<String>,
     pub css_provider: gtk::CssProvider,
+    /// Provider defining a gtk named color for each variable in `css_var_names`
+    pub css_vars_provider: gtk::CssProvider,
+    /// Variables that are referenced from the stylesheets as `var(--name)`
+    pub css_var_names: HashSet<VarName>,
 
     /// Sender to send [`DaemonCommand`]s
     pub app_evt_send: UnboundedSender<DaemonCommand>,
     pub script_var_handler: ScriptVarHandlerHandle,
 
+    /// Clients that listen to changes of variables, i.e. via `eww listen-var`.
+    pub var_subscriptions: Vec<VarSubscription>,
+
     /// Senders that will cancel a windows auto-close timer when started with --duration.
     pub window_close_timer_abort_senders: HashMap<String, futures::channel::oneshot::Sender<()>>,
 
@@ -168,16 +272,34 @@ impl<B: DisplayBackend> App<B> {
                     self.update_global_variable(var_name, new_value);
                 }
             }
+            DaemonCommand::UpdateWindowVars { instance_id, mappings } => {
+                let window = self
+                    .open_windows
+                    .get(&instance_id)
+                    .ok_or_else(|| anyhow!("There is no open window with the id {}", instance_id))?;
+                let mut scope_graph = self.scope_graph.borrow_mut();
+                for (var_name, new_value) in mappings {
+                    // Only update the variables declared in `:locals`, rather than the arguments of the window or global variables
+                    let result = if window.local_names.contains(&var_name) {
+                        scope_graph.update_value(window.scope_index, &var_name, new_value)
+                    } else {
+                        Err(anyhow!("Window {} has no local variable {}", instance_id, var_name))
+                    };
+                    if let Err(err) = result {
+                        error_handling_ctx::print_error(err);
+                    }
+                }
+            }
             DaemonCommand::PollVars(names) => {
                 for var_name in names {
                     self.force_poll_variable(var_name);
                 }
             }
-            DaemonCommand::ReloadConfigAndCss(sender) => {
+            DaemonCommand::ReloadConfigAndCss { soft, sender } => {
                 let mut errors = Vec::new();
 
                 let config_result = config::read_from_eww_paths(&self.paths);
-                if let Err(e) = config_result.and_then(|new_config| self.load_config(new_config)) {
+                if let Err(e) = config_result.and_then(|new_config| self.load_config(new_config, soft)) {
                     errors.push(e)
                 }
                 match crate::config::scss::parse_scss_from_config(self.paths.get_config_dir()) {
@@ -203,24 +325,36 @@ impl<B: DisplayBackend> App<B> {
                     self.close_window(&window_name)?;
                 }
             }
-            DaemonCommand::OpenMany { windows, args, should_toggle, sender } => {
-                let errors = windows
-                    .iter()
-                    .map(|w| {
-                        let (config_name, id) = w;
-                        if should_toggle && self.open_windows.contains_key(id) {
-                            self.close_window(id)
-                        } else {
-                            log::debug!("Config: {}, id: {}", config_name, id);
-                            let window_args = args
-                                .iter()
-                                .filter(|(win_id, ..)| win_id.is_empty() || win_id == id)
-                                .map(|(_, n, v)| (n.clone(), v.clone()))
-                                .collect();
-                            self.open_window(&WindowArguments::new_from_args(id.to_string(), config_name.clone(), window_args)?)
+            DaemonCommand::OpenMany { windows, args, should_toggle, atomic, sender } => {
+                let mut opened_ids = Vec::new();
+                let mut errors = Vec::new();
+                for (config_name, id) in &windows {
+                    let result = if should_toggle && self.open_windows.contains_key(id) {
+                        self.close_window(id)
+                    } else {
+                        log::debug!("Config: {}, id: {}", config_name, id);
+                        let window_args = args
+                            .iter()
+                            .filter(|(win_id, ..)| win_id.is_empty() || win_id == id)
+                            .map(|(_, n, v)| (n.clone(), v.clone()))
+                            .collect();
+                        let result = WindowArguments::new_from_args(id.to_string(), config_name.clone(), window_args)
+                            .and_then(|window_args| self.open_window(&window_args));
+                        if result.is_ok() {
+                            opened_ids.push(id.clone());
                         }
-                    })
-                    .filter_map(Result::err);
+                        result
+                    };
+                    if let Err(err) = result {
+                        errors.push(err);
+                        if atomic {
+                            break;
+                        }
+                    }
+                }
+                if atomic && !errors.is_empty() {
+                    self.roll_back_open_many(&windows, &opened_ids, &mut errors);
+                }
                 sender.respond_with_error_list(errors)?;
             }
             DaemonCommand::OpenWindow {
@@ -260,32 +394,69 @@ impl<B: DisplayBackend> App<B> {
                 let errors = windows.iter().map(|window| self.close_window(window)).filter_map(Result::err);
                 sender.respond_with_error_list(errors)?;
             }
-            DaemonCommand::PrintState { all, sender } => {
+            DaemonCommand::PrintState { all, json, sender } => {
                 let scope_graph = self.scope_graph.borrow();
                 let used_globals_names = scope_graph.currently_used_globals();
-                let output = scope_graph
-                    .global_scope()
-                    .data
-                    .iter()
-                    .filter(|(key, _)| all || used_globals_names.contains(*key))
-                    .map(|(key, value)| format!("{}: {}", key, value))
-                    .join("\n");
+                let vars = scope_graph.global_scope().data.iter().filter(|(key, _)| all || used_globals_names.contains(*key));
+                let output =
+                    if json { vars_to_json(vars) } else { vars.map(|(key, value)| format!("{}: {}", key, value)).join("\n") };
                 sender.send_success(output)?
             }
-            DaemonCommand::GetVar { name, sender } => {
+            DaemonCommand::GetVar { names, json, sender } => {
                 let scope_graph = &*self.scope_graph.borrow();
                 let vars = &scope_graph.global_scope().data;
-                match vars.get(name.as_str()) {
-                    Some(x) => sender.send_success(x.to_string())?,
-                    None => sender.send_failure(format!("Variable not found \"{}\"", name))?,
+                let values: Result<Vec<_>, _> =
+                    names.iter().map(|name| vars.get(name.as_str()).map(|value| (name, value)).ok_or(name)).collect();
+                match values {
+                    Ok(values) if json => sender.send_success(vars_to_json(values))?,
+                    Ok(values) => sender.send_success(values.into_iter().map(|(_, value)| value).join("\n"))?,
+                    Err(name) => sender.send_failure(format!("Variable not found \"{}\"", name))?,
+                }
+            }
+            DaemonCommand::MonitorsChanged => {
+                // Windows that are still on the monitor they target are left alone, to keep the state of their widgets
+                let moved_window_ids = self
+                    .open_windows
+                    .iter()
+                    .filter(|(_, window)| {
+                        get_gdk_monitor(window.monitor_identifier.clone()).map_or(true, |monitor| monitor != window.monitor)
+                    })
+                    .map(|(instance_id, _)| instance_id.clone());
+                let window_ids: Vec<String> = moved_window_ids.chain(self.failed_windows.iter().cloned()).unique().collect();
+                for instance_id in window_ids {
+                    if let Some(window_arguments) = self.instance_id_to_args.get(&instance_id).cloned() {
+                        // Failing here is expected for windows whose monitor is gone, they are reopened once it returns
+                        if let Err(err) = self.open_window(&window_arguments) {
+                            log::warn!("{:?}", err);
+                        }
+                    }
+                }
+            }
+            DaemonCommand::SubscribeVars { names, sender } => {
+                let scope_graph = self.scope_graph.borrow();
+                let vars = &scope_graph.global_scope().data;
+                let values: Result<Vec<_>, _> =
+                    names.iter().map(|name| vars.get(name).map(|value| (name, value)).ok_or(name)).collect();
+                match values {
+                    Ok(values) => {
+                        sender.send_success(vars_to_json(values))?;
+                        drop(scope_graph);
+                        self.var_subscriptions.push(VarSubscription { names, sender });
+                    }
+                    Err(name) => sender.send_failure(format!("Variable not found \"{}\"", name))?,
                 }
             }
             DaemonCommand::ListWindows(sender) => {
                 let output = self.eww_config.get_windows().keys().join("\n");
                 sender.send_success(output)?
             }
-            DaemonCommand::ListActiveWindows(sender) => {
-                let output = self.open_windows.iter().map(|(id, window)| format!("{id}: {}", window.name)).join("\n");
+            DaemonCommand::ListActiveWindows { json, sender } => {
+                let output = if json {
+                    let windows = self.open_windows.iter().map(|(id, window)| window.to_json(id)).collect();
+                    serde_json::Value::Array(windows).to_string()
+                } else {
+                    self.open_windows.iter().map(|(id, window)| format!("{id}: {}", window.name)).join("\n")
+                };
                 sender.send_success(output)?
             }
             DaemonCommand::PrintDebug(sender) => {
@@ -293,6 +464,51 @@ impl<B: DisplayBackend> App<B> {
                 sender.send_success(output)?
             }
             DaemonCommand::PrintGraph(sender) => sender.send_success(self.scope_graph.borrow().visualize())?,
+            DaemonCommand::InspectWindow { window_id, json, sender } => match self.open_windows.get(&window_id) {
+                Some(window) => {
+                    let tree = crate::widget_tree::WidgetNode::collect(window.gtk_window.upcast_ref());
+                    let output = if json { serde_json::to_string(&tree)? } else { tree.to_text() };
+                    sender.send_success(output)?
+                }
+                None => sender.send_failure(format!("There is no open window with the id {}", window_id))?,
+            },
+            DaemonCommand::Profile { action, sender } => match action {
+                opts::ProfileAction::Start => {
+                    crate::profiling::start(self.open_windows.values().map(|window| window.gtk_window.clone().upcast()));
+                    sender.send_success("Started profiling, run `eww profile show` to see the results".to_string())?
+                }
+                opts::ProfileAction::Show => match crate::profiling::report() {
+                    Some(report) => sender.send_success(report)?,
+                    None => sender.send_failure("Not profiling, run `eww profile start` first".to_string())?,
+                },
+                opts::ProfileAction::Stop => match crate::profiling::stop() {
+                    Some(profile) => sender.send_success(profile.report())?,
+                    None => sender.send_failure("Not profiling, run `eww profile start` first".to_string())?,
+                },
+            },
+            DaemonCommand::PrintErrors { clear, sender } => {
+                let output = error_handling_ctx::RECENT_ERRORS
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|error| format!("[{}] {}", error.time.format("%Y-%m-%d %H:%M:%S"), error.details.trim_end()))
+                    .join("\n");
+                if clear {
+                    error_handling_ctx::clear_recent_errors();
+                }
+                sender.send_success(output)?
+            }
+            DaemonCommand::MprisControl { action, sender } => {
+                tokio::spawn(async move {
+                    let result = match crate::mpris::control(action).await {
+                        Ok(player) => sender.send_success(format!("Sent {:?} to {}", action, player)),
+                        Err(err) => sender.send_failure(format!("{:?}", err)),
+                    };
+                    if let Err(err) = result {
+                        log::error!("Failed to respond to mpris command: {}", err);
+                    }
+                });
+            }
         }
         Ok(())
     }
@@ -309,14 +525,34 @@ impl<B: DisplayBackend> App<B> {
     }
 
     fn update_global_variable(&mut self, name: VarName, value: DynVal) {
-        let result = self.scope_graph.borrow_mut().update_global_value(&name, value);
-        if let Err(err) = result {
-            error_handling_ctx::print_error(err);
+        let changed = self.scope_graph.borrow().global_scope().data.get(&name) != Some(&value);
+        let result = self.scope_graph.borrow_mut().update_global_value(&name, value.clone());
+        match result {
+            Ok(()) if changed => {
+                self.notify_var_subscriptions(&name, &value);
+                if self.css_var_names.contains(&name) {
+                    self.update_css_vars();
+                }
+            }
+            Ok(()) => {}
+            Err(err) => error_handling_ctx::print_error(err),
         }
 
         self.apply_run_while_expressions_mentioning(&name);
     }
 
+    /// Send the new value of a variable to all clients listening to it, dropping the ones that disconnected.
+    fn notify_var_subscriptions(&mut self, name: &VarName, value: &DynVal) {
+        if self.var_subscriptions.is_empty() {
+            return;
+        }
+        let output = vars_to_json([(name, value)]);
+        self.var_subscriptions.retain(|subscription| {
+            !subscription.sender.is_closed()
+                && (!subscription.names.contains(name) || subscription.sender.send_success(output.clone()).is_ok())
+        });
+    }
+
     /// Variables may be referenced in defpoll :run-while expressions.
     /// Thus, when a variable changes, the run-while conditions of all variables
     /// that mention the changed variable need to be reevaluated and reapplied.
@@ -381,10 +617,46 @@ impl<B: DisplayBackend> App<B> {
         }
 
         self.instance_id_to_args.remove(instance_id);
+        self.restack_windows();
 
         Ok(())
     }
 
+    /// Undo an `open-many` that failed part way through, closing the windows it opened and forgetting about the ones that
+    /// failed, so that they don't get reopened when reloading.
+    fn roll_back_open_many(&mut self, windows: &[(String, String)], opened_ids: &[String], errors: &mut Vec<anyhow::Error>) {
+        log::info!("Not all windows could be opened, closing {}", opened_ids.join(", "));
+        for id in opened_ids.iter().rev() {
+            if let Err(err) = self.close_window(id) {
+                errors.push(err);
+            }
+        }
+        for (_, id) in windows {
+            if self.failed_windows.remove(id) {
+                self.instance_id_to_args.remove(id);
+            }
+        }
+        if !opened_ids.is_empty() {
+            errors.push(anyhow!("Closed the windows opened so far: {}", opened_ids.join(", ")));
+        }
+    }
+
+    /// Order the open windows that share a stacking layer by their z-index, raising windows with a higher z-index above
+    /// the others. This is only possible on X11, on Wayland the order within a layer is up to the compositor.
+    fn restack_windows(&self) {
+        if !B::IS_X11 {
+            return;
+        }
+        let mut windows: Vec<_> = self.open_windows.iter().collect();
+        windows.sort_by_key(|(instance_id, window)| (window.z_index, instance_id.to_string()));
+        for (_, window) in windows {
+            let shares_layer = self.open_windows.values().filter(|other| other.stacking == window.stacking).count() > 1;
+            if let Some(gdk_window) = window.gtk_window.window().filter(|_| shares_layer) {
+                gdk_window.raise();
+            }
+        }
+    }
+
     fn open_window(&mut self, window_args: &WindowArguments) -> Result<()> {
         let instance_id = &window_args.instance_id;
         self.failed_windows.remove(instance_id);
@@ -426,9 +698,41 @@ impl<B: DisplayBackend> App<B> {
 
             root_widget.style_context().add_class(window_name);
 
+            let css_provider = match &window_def.stylesheet {
+                Some(stylesheet) => {
+                    let (file_id, css) =
+                        config::scss::parse_window_stylesheet(self.paths.get_config_dir(), window_name, stylesheet)?;
+                    let css = self.replace_css_var_references(&css);
+                    self.update_css_vars();
+                    let css_provider = gtk::CssProvider::new();
+                    load_css_into_provider(&css_provider, file_id, &css)?;
+                    Some(css_provider)
+                }
+                None => None,
+            };
+
+            let anchor_window = match &initiator.anchor_to {
+                Some(_) if !B::IS_X11 => {
+                    log::warn!("`anchor-to` of window {} is only supported on X11", instance_id);
+                    None
+                }
+                Some(anchor_id) => match self.open_windows.get(anchor_id) {
+                    Some(anchor_window) => Some(anchor_window.gtk_window.downgrade()),
+                    None => {
+                        log::warn!("Window {} is anchored to {}, which is not open", instance_id, anchor_id);
+                        None
+                    }
+                },
+                None => None,
+            };
+
             let monitor = get_gdk_monitor(initiator.monitor.clone())?;
-            let mut eww_window = initialize_window::<B>(&initiator, monitor, root_widget, window_scope)?;
+            let mut eww_window = initialize_window::<B>(&initiator, monitor, anchor_window, root_widget, window_scope)?;
             eww_window.gtk_window.style_context().add_class(window_name);
+            if let (Some(css_provider), Some(screen)) = (&css_provider, gdk::Screen::default()) {
+                gtk::StyleContext::add_provider_for_screen(&screen, css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+            }
+            eww_window.css_provider = css_provider;
 
             // initialize script var handlers for variables. As starting a scriptvar with the script_var_handler is idempodent,
             // we can just start script vars that are already running without causing issues
@@ -454,6 +758,42 @@ impl<B: DisplayBackend> App<B> {
                 }
             }));
 
+            if !initiator.keybinds.is_empty() {
+                let keybinds = initiator.keybinds.clone();
+                let app_evt_sender = self.app_evt_send.clone();
+                let instance_id = instance_id.to_string();
+                eww_window.gtk_window.connect_key_press_event(move |_, event| {
+                    let Some(keybind) = keybinds.iter().find(|keybind| keybind.matches(event)) else {
+                        return glib::Propagation::Proceed;
+                    };
+                    match &keybind.action {
+                        KeybindAction::Close => {
+                            let (response_sender, _) = daemon_response::create_pair();
+                            let command =
+                                DaemonCommand::CloseWindows { windows: vec![instance_id.clone()], sender: response_sender };
+                            if let Err(err) = app_evt_sender.send(command) {
+                                log::error!("Error sending close window command to daemon after keybind: {}", err);
+                            }
+                        }
+                        // Keybinds commonly launch applications, which a timeout would kill right away
+                        KeybindAction::Command(cmd) => crate::widgets::run_command_detached(cmd),
+                    }
+                    glib::Propagation::Stop
+                });
+            }
+
+            if initiator.close_on_unfocus {
+                let app_evt_sender = self.app_evt_send.clone();
+                let instance_id = instance_id.to_string();
+                eww_window.unfocus_grab = Some(close_on_unfocus::connect(&eww_window.gtk_window, B::IS_X11, move || {
+                    let (response_sender, _) = daemon_response::create_pair();
+                    let command = DaemonCommand::CloseWindows { windows: vec![instance_id.clone()], sender: response_sender };
+                    if let Err(err) = app_evt_sender.send(command) {
+                        log::error!("Error sending close window command to daemon after the window lost focus: {}", err);
+                    }
+                }));
+            }
+
             let duration = window_args.duration;
             if let Some(duration) = duration {
                 let app_evt_sender = self.app_evt_send.clone();
@@ -483,6 +823,7 @@ impl<B: DisplayBackend> App<B> {
             }
 
             self.open_windows.insert(instance_id.to_string(), eww_window);
+            self.restack_windows();
             Ok(())
         })();
 
@@ -495,7 +836,9 @@ impl<B: DisplayBackend> App<B> {
     }
 
     /// Load the given configuration, reloading all script-vars and attempting to reopen all windows that where opened.
-    pub fn load_config(&mut self, config: config::EwwConfig) -> Result<()> {
+    /// Load a new configuration, reopening all open windows.
+    /// When `preserve_state` is set, variables whose definition didn't change keep their current value.
+    pub fn load_config(&mut self, config: config::EwwConfig, preserve_state: bool) -> Result<()> {
         log::info!("Reloading windows");
 
         self.script_var_handler.stop_all();
@@ -504,8 +847,14 @@ impl<B: DisplayBackend> App<B> {
 
         log::trace!("loading config: {:#?}", config);
 
-        self.eww_config = config;
-        self.scope_graph.borrow_mut().clear(self.eww_config.generate_initial_state()?);
+        let old_config = std::mem::replace(&mut self.eww_config, config);
+        let initial_state = if preserve_state {
+            let current_state = self.scope_graph.borrow().global_scope().data.clone();
+            self.eww_config.generate_preserved_state(&old_config, &current_state)?
+        } else {
+            self.eww_config.generate_initial_state()?
+        };
+        self.scope_graph.borrow_mut().clear(initial_state);
 
         let open_window_ids: Vec<String> =
             self.open_windows.keys().cloned().chain(self.failed_windows.iter().cloned()).dedup().collect();
@@ -520,38 +869,127 @@ impl<B: DisplayBackend> App<B> {
 
     /// Load a given CSS string into the gtk css provider, returning a nicely formatted [`DiagError`] when GTK errors out
     pub fn load_css(&mut self, file_id: usize, css: &str) -> Result<()> {
-        if let Err(err) = self.css_provider.load_from_data(css.as_bytes()) {
-            static PATTERN: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"[^:]*:(\d+):(\d+)(.*)$").unwrap());
-            let nice_error_option: Option<_> = (|| {
-                let captures = PATTERN.captures(err.message())?;
-                let line = captures.get(1).unwrap().as_str().parse::<usize>().ok()?;
-                let msg = captures.get(3).unwrap().as_str();
-                let db = error_handling_ctx::FILE_DATABASE.read().ok()?;
-                let line_range = db.line_range(file_id, line - 1).ok()?;
-                let span = Span(line_range.start, line_range.end - 1, file_id);
-                Some(DiagError(gen_diagnostic!(msg, span)))
-            })();
-            match nice_error_option {
-                Some(error) => Err(anyhow!(error)),
-                None => Err(anyhow!("CSS error: {}", err.message())),
+        let css = self.replace_css_var_references(css);
+        self.update_css_vars();
+        load_css_into_provider(&self.css_provider, file_id, &css)
+    }
+
+    /// Replace references to variables written as css custom properties, `var(--name)`, with references to the gtk named colors
+    /// defined for them in the `css_vars_provider`. References to names that aren't variables are left untouched.
+    fn replace_css_var_references(&mut self, css: &str) -> String {
+        let scope_graph = self.scope_graph.borrow();
+        let globals = &scope_graph.global_scope().data;
+        crate::regex!(r"var\(\s*--([\w-]+)\s*\)")
+            .replace_all(css, |captures: &regex::Captures| {
+                let name = VarName(captures[1].to_string());
+                if !globals.contains_key(&name) {
+                    return captures[0].to_string();
+                }
+                let reference = format!("@{}", name);
+                self.css_var_names.insert(name);
+                reference
+            })
+            .into_owned()
+    }
+
+    /// Define the named colors for all variables referenced from the stylesheets, using their current values.
+    /// This only reloads the small `css_vars_provider`, such that the stylesheets themselves don't need to be parsed again.
+    fn update_css_vars(&self) {
+        let scope_graph = self.scope_graph.borrow();
+        let globals = &scope_graph.global_scope().data;
+        let mut css = String::new();
+        for name in &self.css_var_names {
+            let Some(value) = globals.get(name) else { continue };
+            let value = value.as_string().unwrap_or_default();
+            if gdk::RGBA::parse(&value).is_err() {
+                log::warn!("Variable {} is used in css, but its value '{}' is not a valid color", name, value);
+                continue;
             }
-        } else {
-            Ok(())
+            css.push_str(&format!("@define-color {} {};\n", name, value));
+        }
+        if let Err(err) = self.css_vars_provider.load_from_data(css.as_bytes()) {
+            error_handling_ctx::print_error(anyhow!("Failed to define css variables: {}", err));
         }
     }
 }
 
+/// Load the given css into the provider. Errors reported by GTK are mapped back to the source with the given file id
+/// in the [`error_handling_ctx::FILE_DATABASE`].
+fn load_css_into_provider(css_provider: &gtk::CssProvider, file_id: usize, css: &str) -> Result<()> {
+    let Err(err) = css_provider.load_from_data(css.as_bytes()) else {
+        return Ok(());
+    };
+    static PATTERN: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"[^:]*:(\d+):(\d+)(.*)$").unwrap());
+    let Some(captures) = PATTERN.captures(err.message()) else {
+        return Err(anyhow!("CSS error: {}", err.message()));
+    };
+    let line = captures.get(1).unwrap().as_str().parse::<usize>()?;
+    let msg = captures.get(3).unwrap().as_str().trim_start_matches(':').trim();
+    let compiled_line = css.lines().nth(line.saturating_sub(1)).unwrap_or_default().trim();
+    match find_css_line_in_source(file_id, line, compiled_line) {
+        Some(span) => Err(anyhow!(DiagError(gen_diagnostic!(msg, span)))),
+        None => Err(anyhow!("CSS error: {}\n  in line {} of the compiled css: {}", msg, line, compiled_line)),
+    }
+}
+
+/// Find the span of a line of compiled css in the (s)css source it was compiled from.
+/// There is no source map from the compiled css to the source, so this looks for the same content in the source instead,
+/// preferring the same line, as is the case for plain css.
+fn find_css_line_in_source(file_id: usize, line: usize, compiled_line: &str) -> Option<Span> {
+    if compiled_line.is_empty() {
+        return None;
+    }
+    let db = error_handling_ctx::FILE_DATABASE.read().ok()?;
+    let source = db.source(file_id).ok()?;
+    let same_line =
+        db.line_range(file_id, line.checked_sub(1)?).ok().filter(|range| source[range.clone()].trim() == compiled_line);
+    let start = match same_line {
+        Some(range) => range.start + source[range].find(compiled_line)?,
+        None => source.find(compiled_line)?,
+    };
+    Some(Span(start, start + compiled_line.len(), file_id))
+}
+
 fn initialize_window<B: DisplayBackend>(
     window_init: &WindowInitiator,
     monitor: Monitor,
+    anchor_window: Option<glib::WeakRef<Window>>,
     root_widget: gtk::Widget,
     window_scope: ScopeIndex,
 ) -> Result<EwwWindow> {
+    let window_monitor = monitor.clone();
     let monitor_geometry = monitor.geometry();
+    // Windows that reserve space or are ignored by the window manager are usually meant to be placed within the
+    // area reserved for panels, so they're only kept within the monitor itself.
+    let x11_options = &window_init.backend_options.x11;
+    let workarea = if x11_options.wm_ignore || x11_options.struts.distance != NumWithUnit::Pixels(0) {
+        monitor_geometry
+    } else {
+        monitor.workarea()
+    };
+    // Windows anchored to another window are positioned within that window instead of the monitor
+    let positioning_area = move |anchor_window: &Option<glib::WeakRef<Window>>| {
+        anchor_window.as_ref().and_then(|x| x.upgrade()).and_then(|x| get_window_frame_rect(&x)).unwrap_or(monitor_geometry)
+    };
     let (actual_window_rect, x, y) = match window_init.geometry {
         Some(geometry) => {
-            let rect = get_window_rectangle(geometry, monitor_geometry);
-            (Some(rect), rect.x(), rect.y())
+            let rect = get_window_rectangle(geometry, positioning_area(&anchor_window));
+            let clamped_rect = clamp_to_workarea(rect, workarea);
+            if clamped_rect != rect {
+                log::warn!(
+                    "Window {} would be partly outside of the workarea of its monitor ({}x{}+{}+{}), moving it to {}x{}+{}+{} instead",
+                    window_init.name,
+                    rect.width(),
+                    rect.height(),
+                    rect.x(),
+                    rect.y(),
+                    clamped_rect.width(),
+                    clamped_rect.height(),
+                    clamped_rect.x(),
+                    clamped_rect.y(),
+                );
+            }
+            (Some(clamped_rect), clamped_rect.x(), clamped_rect.y())
         }
         _ => (None, 0, 0),
     };
@@ -578,44 +1016,118 @@ fn initialize_window<B: DisplayBackend>(
 
     window.realize();
 
+    #[allow(unused_mut)]
+    let mut anchor_handler = None;
+    #[allow(unused_mut)]
+    let mut placed_window_rect: Option<gdk::Rectangle> = None;
     #[cfg(feature = "x11")]
     if B::IS_X11 {
         if let Some(geometry) = window_init.geometry {
-            let _ = apply_window_position(geometry, monitor_geometry, &window);
+            placed_window_rect = apply_window_position(geometry, positioning_area(&anchor_window), workarea, &window).ok();
             if window_init.backend_options.x11.window_type != yuck::config::backend_window_options::X11WindowType::Normal {
+                let anchor_window = anchor_window.clone();
                 window.connect_configure_event(move |window, _| {
-                    let _ = apply_window_position(geometry, monitor_geometry, window);
+                    let _ = apply_window_position(geometry, positioning_area(&anchor_window), workarea, window);
                     false
                 });
             }
+            if let Some(anchor) = anchor_window.as_ref().and_then(|x| x.upgrade()) {
+                let dependent_window = window.downgrade();
+                let handler_id = anchor.connect_configure_event(move |anchor, _| {
+                    if let (Some(dependent_window), Some(area)) = (dependent_window.upgrade(), get_window_frame_rect(anchor)) {
+                        let _ = apply_window_position(geometry, area, workarea, &dependent_window);
+                    }
+                    false
+                });
+                anchor_handler = Some((anchor.downgrade(), handler_id));
+            }
         }
-        display_backend::set_xprops(&window, monitor, window_init)?;
+        display_backend::set_xprops(&window, monitor.clone(), placed_window_rect, window_init)?;
+    }
+
+    if window_init.backend_options.reserve_follows_content {
+        let window_init = window_init.clone();
+        let gtk_window = window.downgrade();
+        let last_content_size = Cell::new(None);
+        root_widget.connect_size_allocate(move |content, _| {
+            // The minimum size shrinks as soon as content is hidden, while the allocation is held at the window's size
+            let content_size = (content.preferred_width().0, content.preferred_height().0);
+            if last_content_size.replace(Some(content_size)) == Some(content_size) {
+                return;
+            }
+            let Some(gtk_window) = gtk_window.upgrade() else { return };
+            if let Err(err) = B::reserve_space_for_content(&gtk_window, &window_init, &monitor, placed_window_rect, content_size)
+            {
+                error_handling_ctx::print_error(err);
+            }
+        });
     }
 
     window.show_all();
+    window_transition::animate_open(
+        &window,
+        window_transition::supported_transition(window_init.open_transition, B::IS_X11),
+        window_init.transition_duration,
+    );
 
     Ok(EwwWindow {
         name: window_init.name.clone(),
         gtk_window: window,
         scope_index: window_scope,
         destroy_event_handler_id: None,
+        anchor_handler,
+        stacking: window_init.stacking,
+        z_index: window_init.z_index,
+        css_provider: None,
+        close_transition: window_transition::supported_transition(window_init.close_transition, B::IS_X11),
+        transition_duration: window_init.transition_duration,
+        unfocus_grab: None,
+        local_names: window_init.local_names.clone(),
+        monitor: window_monitor,
+        monitor_identifier: window_init.monitor.clone(),
     })
 }
 
-/// Apply the provided window-positioning rules to the window.
+/// Get the area covered by a window on the screen, if it is realized.
+fn get_window_frame_rect(window: &Window) -> Option<gdk::Rectangle> {
+    let gdk_window = window.window()?;
+    let (_, x, y) = gdk_window.origin();
+    Some(gdk::Rectangle::new(x, y, gdk_window.width(), gdk_window.height()))
+}
+
+/// Apply the provided window-positioning rules to the window, positioning it within the given area
+/// while keeping it inside of the workarea of its monitor.
+/// Returns the rectangle the window was placed in.
 #[cfg(feature = "x11")]
-fn apply_window_position(mut window_geometry: WindowGeometry, monitor_geometry: gdk::Rectangle, window: &Window) -> Result<()> {
+fn apply_window_position(
+    mut window_geometry: WindowGeometry,
+    area: gdk::Rectangle,
+    workarea: gdk::Rectangle,
+    window: &Window,
+) -> Result<gdk::Rectangle> {
     let gdk_window = window.window().context("Failed to get gdk window from gtk window")?;
     window_geometry.size = Coords::from_pixels(window.size());
-    let actual_window_rect = get_window_rectangle(window_geometry, monitor_geometry);
+    let actual_window_rect = clamp_to_workarea(get_window_rectangle(window_geometry, area), workarea);
 
     let gdk_origin = gdk_window.origin();
+    // While the window slides in or out, it is kept at its offset from the actual position
+    let (offset_x, offset_y) = window.slide_offset();
+    let (x, y) = (actual_window_rect.x() + offset_x, actual_window_rect.y() + offset_y);
 
-    if actual_window_rect.x() != gdk_origin.1 || actual_window_rect.y() != gdk_origin.2 {
-        gdk_window.move_(actual_window_rect.x(), actual_window_rect.y());
+    if x != gdk_origin.1 || y != gdk_origin.2 {
+        gdk_window.move_(x, y);
     }
 
-    Ok(())
+    Ok(actual_window_rect)
+}
+
+/// Move and shrink the rectangle as needed for it to fit within the workarea.
+fn clamp_to_workarea(rect: gdk::Rectangle, workarea: gdk::Rectangle) -> gdk::Rectangle {
+    let width = rect.width().min(workarea.width());
+    let height = rect.height().min(workarea.height());
+    let x = rect.x().clamp(workarea.x(), workarea.x() + workarea.width() - width);
+    let y = rect.y().clamp(workarea.y(), workarea.y() + workarea.height() - height);
+    gdk::Rectangle::new(x, y, width, height)
 }
 
 fn on_screen_changed(window: &Window, _old_screen: Option<&gdk::Screen>) {
@@ -624,22 +1136,47 @@ fn on_screen_changed(window: &Window, _old_screen: Option<&gdk::Screen>) {
     window.set_visual(visual.as_ref());
 }
 
+/// Convert the value of a variable to JSON, keeping values that aren't valid JSON as strings.
+pub fn dynval_to_json(value: &DynVal) -> serde_json::Value {
+    value.as_json_value().unwrap_or_else(|_| serde_json::Value::String(value.to_string()))
+}
+
+/// Format variables as a single-line JSON object mapping their names to their values.
+pub fn vars_to_json<'a, K: ToString>(vars: impl IntoIterator<Item = (K, &'a DynVal)>) -> String {
+    let object: serde_json::Map<_, _> = vars.into_iter().map(|(name, value)| (name.to_string(), dynval_to_json(value))).collect();
+    serde_json::Value::Object(object).to_string()
+}
+
 /// Get the monitor geometry of a given monitor, or the default if none is given
 fn get_gdk_monitor(identifier: Option<MonitorIdentifier>) -> Result<Monitor> {
     let display = gdk::Display::default().expect("could not get default display");
     let monitor = match identifier {
         Some(ident) => {
-            let mon = get_monitor_from_display(&display, &ident);
-            mon.with_context(|| {
-                let head = format!("Failed to get monitor {}\nThe available monitors are:", ident);
+            let available_monitors = || {
                 let mut body = String::new();
                 for m in 0..display.n_monitors() {
                     if let Some(model) = display.monitor(m).and_then(|x| x.model()) {
-                        body.push_str(format!("\n\t[{}] {}", m, model).as_str());
+                        match get_monitor_plug_name(&display, m) {
+                            Some(plug_name) => body.push_str(format!("\n\t[{}] {} ({})", m, model, plug_name).as_str()),
+                            None => body.push_str(format!("\n\t[{}] {}", m, model).as_str()),
+                        }
                     }
                 }
-                format!("{}{}", head, body)
-            })?
+                body
+            };
+            match get_monitor_from_display(&display, &ident) {
+                Some(monitor) => monitor,
+                // Names may not exist on every setup, so rather than not opening the window at all, show it on the first monitor
+                None if !ident.is_numeric() && display.n_monitors() > 0 => {
+                    log::warn!(
+                        "Failed to get monitor {}, falling back to monitor 0\nThe available monitors are:{}",
+                        ident,
+                        available_monitors()
+                    );
+                    display.monitor(0).context("Failed to get monitor 0")?
+                }
+                None => bail!("Failed to get monitor {}\nThe available monitors are:{}", ident, available_monitors()),
+            }
         }
         None => display
             .primary_monitor()
@@ -660,6 +1197,27 @@ fn get_monitor_plug_name(display: &gdk::Display, monitor_num: i32) -> Option<&st
     }
 }
 
+/// Describe all monitors of the display as a JSON list, for `EWW_MONITORS`.
+pub fn get_monitors_json(display: &gdk::Display) -> String {
+    let monitors = (0..display.n_monitors())
+        .filter_map(|m| Some((m, display.monitor(m)?)))
+        .map(|(m, monitor)| {
+            let geometry = monitor.geometry();
+            serde_json::json!({
+                "index": m,
+                "model": monitor.model().map(|x| x.to_string()),
+                "connector": get_monitor_plug_name(display, m),
+                "x": geometry.x(),
+                "y": geometry.y(),
+                "width": geometry.width(),
+                "height": geometry.height(),
+                "scale": monitor.scale_factor(),
+            })
+        })
+        .collect();
+    serde_json::Value::Array(monitors).to_string()
+}
+
 /// Returns the [Monitor][gdk::Monitor] structure corresponding to the identifer.
 /// Outside of x11, only [MonitorIdentifier::Numeric] is supported
 pub fn get_monitor_from_display(display: &gdk::Display, identifier: &MonitorIdentifier) -> Option<gdk::Monitor> {
@@ -684,6 +1242,23 @@ pub fn get_monitor_from_display(display: &gdk::Display, identifier: &MonitorIden
             }
             None
         }
+        MonitorIdentifier::Regex(pattern) => {
+            let regex = match regex::Regex::new(pattern) {
+                Ok(regex) => regex,
+                Err(err) => {
+                    log::error!("Invalid monitor regex /{}/: {}", pattern, err);
+                    return None;
+                }
+            };
+            for m in 0..display.n_monitors() {
+                let model = display.monitor(m).and_then(|x| x.model());
+                let plug_name = get_monitor_plug_name(display, m);
+                if model.as_deref().is_some_and(|model| regex.is_match(model)) || plug_name.is_some_and(|x| regex.is_match(x)) {
+                    return display.monitor(m);
+                }
+            }
+            None
+        }
     }
 }
 
@@ -27,14 +27,7 @@ pub fn handle_client_only_action(paths: &EwwPaths, action: ActionClientOnly) ->
 /// Connect to the daemon and send the given request.
 /// Returns the response from the daemon, or None if the daemon did not provide any useful response. An Ok(None) response does _not_ indicate failure.
 pub fn do_server_call(stream: &mut UnixStream, action: &opts::ActionWithServer) -> Result<Option<DaemonResponse>> {
-    log::debug!("Forwarding options to server");
-    stream.set_nonblocking(false).context("Failed to set stream to non-blocking")?;
-
-    let message_bytes = bincode::serialize(&action)?;
-
-    stream.write(&(message_bytes.len() as u32).to_be_bytes()).context("Failed to send command size header to IPC stream")?;
-
-    stream.write_all(&message_bytes).context("Failed to write command to IPC stream")?;
+    send_action(stream, action)?;
 
     let mut buf = Vec::new();
     stream.set_read_timeout(Some(std::time::Duration::from_millis(100))).context("Failed to set read timeout")?;
@@ -47,3 +40,36 @@ pub fn do_server_call(stream: &mut UnixStream, action: &opts::ActionWithServer)
         Some(buf)
     })
 }
+
+/// Connect to the daemon and send the given subscription request.
+/// Every response the daemon sends is passed to `on_response`, until the daemon closes the connection.
+pub fn do_subscription_call(
+    stream: &mut UnixStream,
+    action: &opts::ActionWithServer,
+    mut on_response: impl FnMut(DaemonResponse),
+) -> Result<()> {
+    send_action(stream, action)?;
+
+    loop {
+        let mut message_byte_length = [0u8; 4];
+        match stream.read_exact(&mut message_byte_length) {
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            result => result.context("Failed to read response size header from IPC stream")?,
+        }
+        let mut message = vec![0u8; u32::from_be_bytes(message_byte_length) as usize];
+        stream.read_exact(&mut message).context("Failed to read response from IPC stream")?;
+        on_response(bincode::deserialize(&message)?);
+    }
+}
+
+fn send_action(stream: &mut UnixStream, action: &opts::ActionWithServer) -> Result<()> {
+    log::debug!("Forwarding options to server");
+    stream.set_nonblocking(false).context("Failed to set stream to non-blocking")?;
+
+    let message_bytes = bincode::serialize(&action)?;
+
+    stream.write(&(message_bytes.len() as u32).to_be_bytes()).context("Failed to send command size header to IPC stream")?;
+
+    stream.write_all(&message_bytes).context("Failed to write command to IPC stream")?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! Closing windows once they lose focus or the user clicks outside of them, see `:close-on-unfocus`.
+
+use std::{cell::Cell, rc::Rc};
+
+use gtk::{gdk, glib, prelude::*};
+
+use crate::widgets::window::Window;
+
+/// Keeps track of the pointer grab taken for a window, so it can be released again when the window closes.
+#[derive(Debug)]
+pub struct UnfocusGrab {
+    window: glib::WeakRef<Window>,
+    grabbed: Rc<Cell<bool>>,
+}
+
+impl UnfocusGrab {
+    /// Release the pointer grab, if it is currently held. Must be called before the window is closed, as the grab
+    /// would otherwise stay in place during the close transition.
+    pub fn release(&self) {
+        if let Some(window) = self.window.upgrade() {
+            release_pointer(&window, &self.grabbed);
+        }
+    }
+}
+
+/// Call `close` once the window loses focus.
+/// On X11, where windows that don't take focus are common, the pointer is also grabbed while the window is shown, and
+/// `close` is called as soon as the user clicks outside of it.
+pub fn connect(window: &Window, is_x11: bool, close: impl Fn() + 'static) -> UnfocusGrab {
+    let grabbed = Rc::new(Cell::new(false));
+    // Losing focus and clicking outside usually happen together, but the window should only be closed once
+    let closed = Rc::new(Cell::new(false));
+    let close = Rc::new({
+        let window = window.downgrade();
+        let grabbed = grabbed.clone();
+        move || {
+            if closed.replace(true) {
+                return;
+            }
+            if let Some(window) = window.upgrade() {
+                release_pointer(&window, &grabbed);
+            }
+            close();
+        }
+    });
+
+    window.add_events(gdk::EventMask::FOCUS_CHANGE_MASK);
+    window.connect_focus_out_event({
+        let close = close.clone();
+        move |window, _| {
+            // Focus briefly moves away while a menu of the window itself is shown, so only close if it really went elsewhere
+            let window = window.downgrade();
+            let close = close.clone();
+            glib::idle_add_local_once(move || {
+                if window.upgrade().is_some_and(|window| !window.is_active()) {
+                    close();
+                }
+            });
+            glib::Propagation::Proceed
+        }
+    });
+
+    if is_x11 {
+        window.add_events(gdk::EventMask::BUTTON_PRESS_MASK);
+        window.connect_map_event({
+            let grabbed = grabbed.clone();
+            move |window, _| {
+                grab_pointer(window, &grabbed);
+                glib::Propagation::Proceed
+            }
+        });
+        window.connect_unmap_event({
+            let grabbed = grabbed.clone();
+            move |window, _| {
+                release_pointer(window, &grabbed);
+                glib::Propagation::Proceed
+            }
+        });
+        // While the pointer is grabbed, clicks outside of any of eww's windows are reported to this window
+        window.connect_button_press_event(move |window, event| {
+            let Some(gdk_window) = window.window() else { return glib::Propagation::Proceed };
+            let (_, x, y) = gdk_window.origin();
+            let (x, y, width, height) = (x as f64, y as f64, gdk_window.width() as f64, gdk_window.height() as f64);
+            let (root_x, root_y) = event.root();
+            if (x..x + width).contains(&root_x) && (y..y + height).contains(&root_y) {
+                return glib::Propagation::Proceed;
+            }
+            close();
+            glib::Propagation::Stop
+        });
+        // The window may already be mapped, in which case there won't be another map event
+        if window.window().map_or(false, |gdk_window| gdk_window.is_viewable()) {
+            grab_pointer(window, &grabbed);
+        }
+    }
+
+    UnfocusGrab { window: window.downgrade(), grabbed }
+}
+
+/// Grab the pointer, while still delivering events within eww's own windows as usual.
+fn grab_pointer(window: &Window, grabbed: &Cell<bool>) {
+    let (Some(gdk_window), Some(seat)) = (window.window(), window.display().default_seat()) else {
+        return;
+    };
+    match seat.grab(&gdk_window, gdk::SeatCapabilities::POINTER, true, None, None, None) {
+        gdk::GrabStatus::Success => grabbed.set(true),
+        status => log::warn!("Failed to grab the pointer for closing the window on a click outside of it: {:?}", status),
+    }
+}
+
+fn release_pointer(window: &Window, grabbed: &Cell<bool>) {
+    if grabbed.replace(false) {
+        if let Some(seat) = window.display().default_seat() {
+            seat.ungrab();
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Tracks whether the system prefers a dark or a light color scheme, for `EWW_COLOR_SCHEME`.
+//!
+//! The preference is read from the XDG desktop portal where available, falling back to the GTK settings
+//! (`gtk-application-prefer-dark-theme` and the name of the GTK theme) otherwise.
+
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use gtk::prelude::*;
+use once_cell::sync::Lazy;
+use zbus::zvariant::{OwnedValue, Value};
+
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+#[zbus::dbus_proxy(
+    interface = "org.freedesktop.portal.Settings",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait PortalSettings {
+    fn read(&self, namespace: &str, key: &str) -> zbus::Result<OwnedValue>;
+
+    #[dbus_proxy(signal)]
+    fn setting_changed(&self, namespace: &str, key: &str, value: Value<'_>) -> zbus::Result<()>;
+}
+
+#[derive(Debug, Default)]
+struct ColorSchemeState {
+    /// Preference reported by the portal: `dark`, `light`, or `None` if there is no portal or it has no preference
+    portal: Option<&'static str>,
+    /// Whether the GTK settings ask for a dark theme
+    gtk_prefers_dark: bool,
+}
+
+static STATE: Lazy<Mutex<ColorSchemeState>> = Lazy::new(|| Mutex::new(ColorSchemeState::default()));
+
+/// Get the preferred color scheme, either `dark` or `light`.
+pub fn get_color_scheme() -> String {
+    let state = STATE.lock().unwrap();
+    let scheme = state.portal.unwrap_or(if state.gtk_prefers_dark { "dark" } else { "light" });
+    scheme.to_string()
+}
+
+/// Interpret the value of the portal's `color-scheme` setting, where 1 means dark and 2 means light.
+/// Older portals wrap the value in another variant.
+fn parse_portal_color_scheme(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::U32(1) => Some("dark"),
+        Value::U32(2) => Some("light"),
+        Value::Value(inner) => parse_portal_color_scheme(inner),
+        _ => None,
+    }
+}
+
+/// Listen for changes of the color scheme reported by the XDG desktop portal, calling `on_change` with the new value of
+/// `EWW_COLOR_SCHEME` whenever it changed.
+pub async fn run_portal_listener(on_change: impl Fn(String)) -> Result<()> {
+    let connection = zbus::Connection::session().await.context("Failed to connect to the D-Bus session bus")?;
+    let portal = PortalSettingsProxy::new(&connection).await?;
+    let mut changes = portal.receive_setting_changed().await?;
+
+    let initial =
+        portal.read(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY).await.context("Failed to read the color scheme from the portal")?;
+    STATE.lock().unwrap().portal = parse_portal_color_scheme(&initial);
+    on_change(get_color_scheme());
+
+    while let Some(change) = changes.next().await {
+        let args = change.args()?;
+        if *args.namespace() == APPEARANCE_NAMESPACE && *args.key() == COLOR_SCHEME_KEY {
+            STATE.lock().unwrap().portal = parse_portal_color_scheme(args.value());
+            on_change(get_color_scheme());
+        }
+    }
+    Ok(())
+}
+
+/// Follow the GTK settings, calling `on_change` with the new value of `EWW_COLOR_SCHEME` whenever they change.
+/// Must be called from the main thread, after GTK has been initialized.
+pub fn start_gtk_settings_listener(on_change: impl Fn(String) + 'static) {
+    let Some(settings) = gtk::Settings::default() else {
+        return;
+    };
+    let update = |settings: &gtk::Settings| {
+        let dark_theme = settings.gtk_theme_name().map_or(false, |name| name.to_lowercase().ends_with("dark"));
+        STATE.lock().unwrap().gtk_prefers_dark = settings.is_gtk_application_prefer_dark_theme() || dark_theme;
+    };
+    update(&settings);
+
+    let on_change = std::rc::Rc::new(move |settings: &gtk::Settings| {
+        update(settings);
+        on_change(get_color_scheme());
+    });
+    settings.connect_gtk_application_prefer_dark_theme_notify({
+        let on_change = on_change.clone();
+        move |settings| on_change(settings)
+    });
+    settings.connect_gtk_theme_name_notify(move |settings| on_change(settings));
+}
@@ -1,5 +1,6 @@
 use anyhow::{bail, Context, Result};
 use eww_shared_util::VarName;
+use itertools::Itertools;
 use std::collections::HashMap;
 use yuck::{
     config::{
@@ -8,6 +9,7 @@ use yuck::{
     },
     error::DiagError,
     format_diagnostic::ToDiagnostic,
+    gen_diagnostic,
 };
 
 use simplexpr::dynval::DynVal;
@@ -57,8 +59,19 @@ impl EwwConfig {
             }
         }
 
-        let Config { widget_definitions, window_definitions, mut var_definitions, mut script_vars } = config;
-        script_vars.extend(inbuilt::get_inbuilt_vars());
+        let Config { widget_definitions, window_definitions, mut var_definitions, mut script_vars, magic_var_intervals } = config;
+        for interval in magic_var_intervals.values() {
+            if !inbuilt::INBUILT_VAR_NAMES.contains(&interval.name.0.as_str()) {
+                return Err(DiagError(gen_diagnostic! {
+                    msg = format!("`{}` is not an updating magic variable", interval.name),
+                    label = interval.name_span => "Used here",
+                    note = format!("Must be one of: {}", inbuilt::INBUILT_VAR_NAMES.iter().join(", ")),
+                })
+                .into());
+            }
+        }
+        let intervals = magic_var_intervals.into_iter().map(|(name, x)| (name, x.interval)).collect();
+        script_vars.extend(inbuilt::get_inbuilt_vars(&intervals));
         var_definitions.extend(inbuilt::get_magic_constants(eww_paths));
 
         let mut run_while_mentions = HashMap::<VarName, Vec<VarName>>::new();
@@ -90,6 +103,32 @@ impl EwwConfig {
         Ok(vars)
     }
 
+    /// Generate the state after reloading from `old_config`, keeping the current values of variables that were defined before.
+    /// Normal variables only keep their value if their initial value didn't change.
+    /// Script vars always keep their value, until their command updates it.
+    pub fn generate_preserved_state(
+        &self,
+        old_config: &EwwConfig,
+        current_state: &HashMap<VarName, DynVal>,
+    ) -> Result<HashMap<VarName, DynVal>> {
+        let mut vars = HashMap::new();
+        for (name, var) in &self.script_vars {
+            let value = match current_state.get(name) {
+                Some(current) if old_config.script_vars.contains_key(name) => current.clone(),
+                _ => script_var::initial_value(var)?,
+            };
+            vars.insert(name.clone(), value);
+        }
+        for (name, initial_value) in &self.initial_variables {
+            let value = match (old_config.initial_variables.get(name), current_state.get(name)) {
+                (Some(old_initial_value), Some(current)) if old_initial_value == initial_value => current.clone(),
+                _ => initial_value.clone(),
+            };
+            vars.insert(name.clone(), value);
+        }
+        Ok(vars)
+    }
+
     pub fn get_windows(&self) -> &HashMap<String, WindowDefinition> {
         &self.windows
     }
@@ -1,6 +1,9 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
-use simplexpr::{dynval::DynVal, SimplExpr};
+use simplexpr::{
+    dynval::{DynVal, VarType},
+    SimplExpr,
+};
 use yuck::config::{
     script_var_definition::{PollScriptVar, ScriptVarDefinition, VarSource},
     var_definition::VarDefinition,
@@ -12,7 +15,8 @@ use eww_shared_util::VarName;
 macro_rules! define_builtin_vars {
     ($($name:literal [$interval:literal] => $fun:expr),*$(,)?) => {
         pub static INBUILT_VAR_NAMES: &[&'static str] = &[$($name),*];
-        pub fn get_inbuilt_vars() -> HashMap<VarName, ScriptVarDefinition> {
+        /// Get the definitions of all inbuilt variables, using the given intervals instead of the default ones where provided.
+        pub fn get_inbuilt_vars(intervals: &HashMap<VarName, Duration>) -> HashMap<VarName, ScriptVarDefinition> {
             maplit::hashmap! {
                 $(
                 VarName::from($name) => ScriptVarDefinition::Poll(PollScriptVar {
@@ -20,7 +24,10 @@ macro_rules! define_builtin_vars {
                     run_while_expr: SimplExpr::Literal(DynVal::from(true)),
                     command: VarSource::Function($fun),
                     initial_value: None,
-                    interval: std::time::Duration::from_secs($interval),
+                    interval: intervals.get(&VarName::from($name)).copied().unwrap_or(Duration::from_secs($interval)),
+                    jitter: false,
+                    align: false,
+                    var_type: VarType::String,
                     name_span: eww_shared_util::span::Span::DUMMY,
                 })
                 ),*
@@ -47,7 +54,7 @@ define_builtin_vars! {
     "EWW_BATTERY" [2] => || Ok(DynVal::from(
         match get_battery_capacity() {
             Err(e) => {
-                log::error!("Couldn't get the battery capacity: {:?}", e);
+                crate::error_handling_ctx::print_error(e.context("Couldn't get the battery capacity"));
                 "Error: Check `eww log` for more details".to_string()
             }
             Ok(o) => o,
@@ -62,6 +69,44 @@ define_builtin_vars! {
     // @prop { <name>: { up, down } }
     "EWW_NET" [2] => || Ok(DynVal::from(net())) ,
 
+    // @desc EWW_NET_IF - Bytes per second up/down on every interface, read from `/proc/net/dev` (Linux only)
+    // @prop { <name>: { up, down } }
+    "EWW_NET_IF" [2] => || Ok(DynVal::from(
+        match get_net_interfaces() {
+            Err(e) => {
+                crate::error_handling_ctx::print_error(e.context("Couldn't get the network interfaces"));
+                "Error: Check `eww log` for more details".to_string()
+            }
+            Ok(o) => o,
+        }
+    )),
+
+    // @desc EWW_KB_LAYOUT - Name of the active keyboard layout. On X11, this updates as soon as the layout changes. On Wayland, it is polled from Hyprland or Sway.
+    "EWW_KB_LAYOUT" [1] => || Ok(DynVal::from(
+        match get_keyboard_layout() {
+            Err(e) => {
+                crate::error_handling_ctx::print_error(e.context("Couldn't get the keyboard layout"));
+                "Error: Check `eww log` for more details".to_string()
+            }
+            Ok(o) => o,
+        }
+    )),
+
+    // @desc EWW_MONITORS - The connected monitors, in the order of their indices. Updates as soon as a monitor is added or removed.
+    // @prop [{ index, model, connector, x, y, width, height, scale }]
+    "EWW_MONITORS" [5] => || Ok(DynVal::from(get_monitors())),
+
+    // @desc EWW_MPRIS - The media player that is playing (or otherwise paused), via MPRIS. Updates as soon as the player changes its state, and the position is updated every second. Control the player with `eww mpris play-pause`, `next`, `previous`, etc.
+    // @prop { player, status, title, artist, album, art_url, length, position }
+    "EWW_MPRIS" [1] => || Ok(DynVal::from(crate::mpris::get_mpris_json())),
+
+    // @desc EWW_COLOR_SCHEME - The color scheme preferred by the system, either `dark` or `light`. Read from the XDG desktop portal if available, or the GTK settings otherwise, and updates as soon as the preference changes.
+    "EWW_COLOR_SCHEME" [5] => || Ok(DynVal::from(crate::color_scheme::get_color_scheme())),
+
+    // @desc EWW_ERRORS - The most recent errors and warnings eww encountered, oldest first, as also shown by `eww errors`. Updates as soon as an error occurs. Useful for an error overlay window, see [Troubleshooting](troubleshooting.md#showing-errors-in-a-window).
+    // @prop [{ time, severity, message, file, line, details }]
+    "EWW_ERRORS" [5] => || Ok(DynVal::from(crate::error_handling_ctx::recent_errors_json())),
+
     // @desc EWW_TIME - the current UNIX timestamp
     "EWW_TIME" [1] => || Ok(DynVal::from(get_time())) ,
 }
@@ -1,9 +1,7 @@
-use std::process::Command;
-
 use anyhow::{anyhow, bail, Context, Result};
 use codespan_reporting::diagnostic::Severity;
 use eww_shared_util::{Span, VarName};
-use simplexpr::dynval::DynVal;
+use simplexpr::dynval::{DynVal, VarType};
 use yuck::{
     config::script_var_definition::{ScriptVarDefinition, VarSource},
     error::DiagError,
@@ -30,17 +28,25 @@ pub fn initial_value(var: &ScriptVarDefinition) -> Result<DynVal> {
                 VarSource::Shell(span, command) => {
                     run_command(command).map_err(|e| anyhow!(create_script_var_failed_warn(*span, var.name(), &e.to_string())))
                 }
-            },
+            }
+            .and_then(|value| convert_to_var_type(var.name(), x.var_type, value)),
         },
 
         ScriptVarDefinition::Listen(var) => Ok(var.initial_value.clone()),
     }
 }
 
+/// Check the output of a script against the declared `:type` of its variable.
+pub fn convert_to_var_type(var_name: &VarName, var_type: VarType, value: DynVal) -> Result<DynVal> {
+    var_type
+        .convert(value)
+        .with_context(|| format!("The script for the `{}`-variable produced a value that doesn't match its type", var_name))
+}
+
 /// Run a command and get the output
 pub fn run_command(cmd: &str) -> Result<DynVal> {
     log::debug!("Running command: {}", cmd);
-    let command = Command::new("/bin/sh").arg("-c").arg(cmd).output()?;
+    let command = crate::util::shell_command(cmd).output()?;
     if !command.status.success() {
         bail!("Failed with output:\n{}", String::from_utf8(command.stderr)?);
     }
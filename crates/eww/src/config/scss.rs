@@ -1,12 +1,48 @@
-use std::path::Path;
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, Context};
+use once_cell::sync::Lazy;
+use tokio::sync::watch;
+
+use codespan_reporting::files::Files;
+use eww_shared_util::Span;
+use yuck::{error::DiagError, gen_diagnostic};
 
 use crate::{error_handling_ctx, util::replace_env_var_references};
 
+/// Files that were imported by the last compiled stylesheet. The file watcher subscribes to this,
+/// such that changes to these files also cause the styles to be reloaded.
+pub static SCSS_IMPORTS: Lazy<watch::Sender<HashSet<PathBuf>>> = Lazy::new(|| watch::channel(HashSet::new()).0);
+
+/// [`grass::Fs`] implementation that reads from disk, while keeping track of every file the compiler reads.
+#[derive(Debug, Default)]
+struct ImportTrackingFs {
+    imported: RefCell<HashSet<PathBuf>>,
+}
+
+impl grass::Fs for ImportTrackingFs {
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.imported.borrow_mut().insert(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+        std::fs::read(path)
+    }
+}
+
 /// read an (s)css file, replace all environment variable references within it and
 /// then parse it into css.
-/// Also adds the CSS to the [`crate::file_database::FileDatabase`]
+/// Also adds the source to the [`crate::file_database::FileDatabase`], returning its file id along with the css.
 pub fn parse_scss_from_config(path: &Path) -> anyhow::Result<(usize, String)> {
     let css_file = path.join("eww.css");
     let scss_file = path.join("eww.scss");
@@ -14,21 +50,80 @@ pub fn parse_scss_from_config(path: &Path) -> anyhow::Result<(usize, String)> {
         return Err(anyhow!("Encountered both an SCSS and CSS file. Only one of these may exist at a time"));
     }
 
-    let (s_css_path, css) = if css_file.exists() {
+    if css_file.exists() {
+        SCSS_IMPORTS.send_replace(HashSet::new());
         let css_file_content = std::fs::read_to_string(&css_file)
             .with_context(|| format!("Given CSS file doesn't exist: {}", css_file.display()))?;
         let css = replace_env_var_references(css_file_content);
-        (css_file, css)
+        let file_id =
+            error_handling_ctx::FILE_DATABASE.write().unwrap().insert_string(css_file.display().to_string(), css.clone())?;
+        Ok((file_id, css))
     } else {
         let scss_file_content =
             std::fs::read_to_string(&scss_file).with_context(|| format!("Given SCSS file doesn't exist! {}", path.display()))?;
         let file_content = replace_env_var_references(scss_file_content);
-        let grass_config = grass::Options::default().load_path(path);
-        let css = grass::from_string(file_content, &grass_config).map_err(|err| anyhow!("SCSS parsing error: {}", err))?;
-        (scss_file, css)
-    };
+        let file_id = error_handling_ctx::FILE_DATABASE
+            .write()
+            .unwrap()
+            .insert_string(scss_file.display().to_string(), file_content.clone())?;
+        let fs = ImportTrackingFs::default();
+        let grass_config = grass::Options::default().load_path(path).fs(&fs);
+        let css = grass::from_string(file_content, &grass_config);
+        // Update the imports even if compilation failed, so that fixing the error in an imported file triggers a reload
+        SCSS_IMPORTS.send_replace(fs.imported.into_inner());
+        let css = css.map_err(|err| scss_error_to_diagnostic(*err, file_id, 0))?;
+        Ok((file_id, css))
+    }
+}
 
-    let mut file_db = error_handling_ctx::FILE_DATABASE.write().unwrap();
-    let file_id = file_db.insert_string(s_css_path.display().to_string(), css.clone())?;
+/// Read the `:stylesheet` of a window and compile it, nesting all of its rules within the window's class,
+/// such that they only apply to widgets within that window.
+/// Also adds the source to the [`crate::file_database::FileDatabase`], returning its file id along with the css.
+pub fn parse_window_stylesheet(config_dir: &Path, window_name: &str, stylesheet: &str) -> anyhow::Result<(usize, String)> {
+    let path = config_dir.join(stylesheet);
+    let file_content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read stylesheet of window {}: {}", window_name, path.display()))?;
+    let file_content = replace_env_var_references(file_content);
+    let file_id =
+        error_handling_ctx::FILE_DATABASE.write().unwrap().insert_string(path.display().to_string(), file_content.clone())?;
+    let load_path = path.parent().unwrap_or(config_dir);
+    let grass_config = grass::Options::default().load_path(load_path).load_path(config_dir);
+    // The wrapping selector is on its own line, such that lines in the compiled source are only offset by one
+    let css = grass::from_string(format!(".{} {{\n{}\n}}", window_name, file_content), &grass_config)
+        .map_err(|err| scss_error_to_diagnostic(*err, file_id, 1))?;
     Ok((file_id, css))
 }
+
+/// Turn an error of the SCSS compiler into a diagnostic pointing at the location of the error in the source.
+/// Errors in the compiled string are mapped into the file with the given id, subtracting `line_offset` from the line number.
+/// Errors in imported files add that file to the [`crate::file_database::FileDatabase`].
+fn scss_error_to_diagnostic(err: grass::Error, file_id: usize, line_offset: usize) -> anyhow::Error {
+    let fallback = anyhow!("SCSS parsing error: {}", err);
+    let grass::ErrorKind::ParseError { message, loc, .. } = err.kind() else {
+        return fallback;
+    };
+    let span = (|| {
+        let mut file_db = error_handling_ctx::FILE_DATABASE.write().ok()?;
+        // grass calls the input given as a string "stdin"
+        let (file_id, line) = if loc.file.name() == "stdin" {
+            (file_id, loc.begin.line.checked_sub(line_offset)?)
+        } else {
+            (file_db.insert_string(loc.file.name().to_string(), loc.file.source().to_string()).ok()?, loc.begin.line)
+        };
+        let line_range = file_db.line_range(file_id, line).ok()?;
+        let line_content = &file_db.source(file_id).ok()?[line_range.clone()];
+        // Columns are counted in characters, while spans are in bytes
+        let column = line_content.char_indices().nth(loc.begin.column).map_or(line_content.len(), |(i, _)| i);
+        let start = line_range.start + column;
+        let end = if loc.end.line == loc.begin.line && loc.end.column > loc.begin.column {
+            line_range.start + line_content.char_indices().nth(loc.end.column).map_or(line_content.len(), |(i, _)| i)
+        } else {
+            start
+        };
+        Some(Span(start, end, file_id))
+    })();
+    match span {
+        Some(span) => anyhow!(DiagError(gen_diagnostic!(format!("SCSS parsing error: {}", message), span))),
+        None => fallback,
+    }
+}
@@ -1,7 +1,7 @@
 use crate::util::IterAverage;
 use anyhow::{Context, Result};
 use once_cell::sync::Lazy;
-use std::{fs::read_to_string, sync::Mutex};
+use std::{collections::HashMap, fs::read_to_string, sync::Mutex};
 use sysinfo::System;
 
 struct RefreshTime(std::time::Instant);
@@ -23,6 +23,13 @@ static DISKS: Lazy<Mutex<sysinfo::Disks>> = Lazy::new(|| Mutex::new(sysinfo::Dis
 static COMPONENTS: Lazy<Mutex<sysinfo::Components>> = Lazy::new(|| Mutex::new(sysinfo::Components::new_with_refreshed_list()));
 static NETWORKS: Lazy<Mutex<(RefreshTime, sysinfo::Networks)>> =
     Lazy::new(|| Mutex::new((RefreshTime::new(), sysinfo::Networks::new_with_refreshed_list())));
+/// The keyboard layout last reported by a listener for layout changes, i.e. via XKB on X11
+pub static KEYBOARD_LAYOUT: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+/// JSON list of the connected monitors, kept up to date by the main thread as GDK can't be used from other threads
+pub static MONITORS: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+/// The total bytes (received, transmitted) per interface at the last refresh
+static NET_INTERFACE_BYTES: Lazy<Mutex<(RefreshTime, HashMap<String, (u64, u64)>)>> =
+    Lazy::new(|| Mutex::new((RefreshTime::new(), HashMap::new())));
 
 pub fn get_disks() -> String {
     let mut disks = DISKS.lock().unwrap();
@@ -271,6 +278,132 @@ pub fn net() -> String {
         .to_string()
 }
 
+/// Get the up and down rates of every interface in bytes per second, computed from the counters in `/proc/net/dev`.
+/// Interfaces that weren't there at the last refresh report a rate of 0.
+pub fn get_net_interfaces() -> Result<String> {
+    let (ref mut last_refresh, ref mut last_bytes) = &mut *NET_INTERFACE_BYTES.lock().unwrap();
+    let current_bytes = parse_proc_net_dev(&read_to_string("/proc/net/dev").context("Couldn't read /proc/net/dev")?)?;
+    let elapsed = last_refresh.next_refresh().as_secs_f64();
+    let rates = net_interface_rates(&current_bytes, last_bytes, elapsed);
+    *last_bytes = current_bytes;
+    Ok(serde_json::Value::Object(rates).to_string())
+}
+
+/// Compute the up and down rates of every interface from its received and transmitted bytes now and `elapsed` seconds ago.
+fn net_interface_rates(
+    current_bytes: &HashMap<String, (u64, u64)>,
+    last_bytes: &HashMap<String, (u64, u64)>,
+    elapsed: f64,
+) -> serde_json::Map<String, serde_json::Value> {
+    current_bytes
+        .iter()
+        .map(|(name, (received, transmitted))| {
+            let (last_received, last_transmitted) = last_bytes.get(name).copied().unwrap_or((*received, *transmitted));
+            // Counters may reset or wrap around, i.e. when an interface is recreated
+            let rate = |current: u64, last: u64| (current.saturating_sub(last) as f64 / elapsed).round() as u64;
+            (
+                name.clone(),
+                serde_json::json!({ "up": rate(*transmitted, last_transmitted), "down": rate(*received, last_received) }),
+            )
+        })
+        .collect()
+}
+
+/// Parse the received and transmitted bytes of every interface from the contents of `/proc/net/dev`.
+fn parse_proc_net_dev(content: &str) -> Result<HashMap<String, (u64, u64)>> {
+    // The first two lines are headers
+    content
+        .lines()
+        .skip(2)
+        .map(|line| {
+            let (name, counters) = line.split_once(':').with_context(|| format!("Malformed line in /proc/net/dev: {}", line))?;
+            let counters = counters.split_whitespace().map(|x| x.parse::<u64>()).collect::<Result<Vec<_>, _>>()?;
+            // There are 8 counters for received data, the first of which is bytes, followed by the ones for transmitted data
+            match (counters.first(), counters.get(8)) {
+                (Some(received), Some(transmitted)) => Ok((name.trim().to_string(), (*received, *transmitted))),
+                _ => Err(anyhow::anyhow!("Malformed line in /proc/net/dev: {}", line)),
+            }
+        })
+        .collect()
+}
+
+pub fn get_monitors() -> String {
+    MONITORS.lock().unwrap().clone().unwrap_or_else(|| "[]".to_string())
+}
+
+/// Get the name of the active keyboard layout.
+/// This is the layout reported by the listener for layout changes if there is one, and otherwise asks the compositor.
+pub fn get_keyboard_layout() -> Result<String> {
+    if let Some(layout) = &*KEYBOARD_LAYOUT.lock().unwrap() {
+        return Ok(layout.clone());
+    }
+
+    let run = |cmd: &str, args: &[&str]| -> Option<serde_json::Value> {
+        let output = std::process::Command::new(cmd).args(args).output().ok().filter(|output| output.status.success())?;
+        serde_json::from_slice(&output.stdout).ok()
+    };
+    if let Some(devices) = run("hyprctl", &["devices", "-j"]) {
+        let keyboards = devices["keyboards"].as_array().into_iter().flatten();
+        if let Some(layout) =
+            keyboards.filter(|kb| kb["main"].as_bool() == Some(true)).find_map(|kb| kb["active_keymap"].as_str())
+        {
+            return Ok(layout.to_string());
+        }
+    }
+    if let Some(inputs) = run("swaymsg", &["-t", "get_inputs", "-r"]) {
+        let keyboards = inputs.as_array().into_iter().flatten();
+        if let Some(layout) = keyboards.filter(|x| x["type"] == "keyboard").find_map(|kb| kb["xkb_active_layout_name"].as_str()) {
+            return Ok(layout.to_string());
+        }
+    }
+    Err(anyhow::anyhow!("Couldn't get the keyboard layout from XKB, Hyprland or Sway"))
+}
+
 pub fn get_time() -> String {
     chrono::offset::Utc::now().timestamp().to_string()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PROC_NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:  123456     100    0    0    0     0          0         0   123456     100    0    0    0     0       0          0
+wlp2s0: 9876543   20000    0   12    0     0          0       300  1234567   15000    0    0    0     0       0          0
+";
+
+    #[test]
+    fn test_parse_proc_net_dev() {
+        let interfaces = parse_proc_net_dev(PROC_NET_DEV).unwrap();
+        assert_eq!(
+            maplit::hashmap! {
+                "lo".to_string() => (123456, 123456),
+                "wlp2s0".to_string() => (9876543, 1234567),
+            },
+            interfaces
+        );
+        assert!(parse_proc_net_dev("header\nheader\neth0 1 2 3\n").is_err());
+        assert!(parse_proc_net_dev("header\nheader\neth0: 1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn test_net_interface_rates() {
+        let last = maplit::hashmap! {
+            "eth0".to_string() => (1000, 500),
+            "wlan0".to_string() => (u64::MAX - 10, 2000),
+        };
+        let current = maplit::hashmap! {
+            "eth0".to_string() => (3000, 1500),
+            // The received counter wrapped around
+            "wlan0".to_string() => (5, 2000),
+            "usb0".to_string() => (100, 100),
+        };
+        let rates = net_interface_rates(&current, &last, 2.0);
+        assert_eq!(serde_json::json!({ "up": 500, "down": 1000 }), rates["eth0"]);
+        assert_eq!(serde_json::json!({ "up": 0, "down": 0 }), rates["wlan0"]);
+        // Interfaces that weren't there before have no rate yet
+        assert_eq!(serde_json::json!({ "up": 0, "down": 0 }), rates["usb0"]);
+    }
+}
@@ -35,6 +35,11 @@ impl DaemonResponseSender {
         self.0.send(DaemonResponse::Failure(s)).context("Failed to send failure response from application thread")
     }
 
+    /// Whether the receiving end was dropped, i.e. because the client disconnected.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
     /// Given a list of errors, respond with an error value if there are any errors, and respond with success otherwise.
     pub fn respond_with_error_list(&self, errors: impl IntoIterator<Item = anyhow::Error>) -> Result<()> {
         let errors = errors.into_iter().map(|e| error_handling_ctx::format_error(&e)).join("\n");
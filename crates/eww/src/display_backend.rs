@@ -1,18 +1,31 @@
 use crate::{widgets::window::Window, window_initiator::WindowInitiator};
 
+use anyhow::Result;
 use gtk::gdk;
 
 #[cfg(feature = "wayland")]
 pub use platform_wayland::WaylandBackend;
 
 #[cfg(feature = "x11")]
-pub use platform_x11::{set_xprops, X11Backend};
+pub use platform_x11::{run_keyboard_layout_listener, set_xprops, X11Backend};
 
 pub trait DisplayBackend: Send + Sync + 'static {
     const IS_X11: bool;
     const IS_WAYLAND: bool;
 
     fn initialize_window(window_init: &WindowInitiator, monitor: gdk::Rectangle, x: i32, y: i32) -> Option<Window>;
+
+    /// Limit the space reserved for the window to what its content currently needs, for `:reserve-follows-content`.
+    /// `content_size` is the minimum width and height of the content.
+    fn reserve_space_for_content(
+        _window: &Window,
+        _window_init: &WindowInitiator,
+        _monitor: &gdk::Monitor,
+        _window_rect: Option<gdk::Rectangle>,
+        _content_size: (i32, i32),
+    ) -> Result<()> {
+        Ok(())
+    }
 }
 
 pub struct NoBackend;
@@ -32,8 +45,8 @@ mod platform_wayland {
     use crate::{widgets::window::Window, window_initiator::WindowInitiator};
     use gtk::gdk;
     use gtk::prelude::*;
-    use gtk_layer_shell::{KeyboardMode, LayerShell};
-    use yuck::config::backend_window_options::WlWindowFocusable;
+    use gtk_layer_shell::{Edge, KeyboardMode, LayerShell};
+    use yuck::config::backend_window_options::{Side, WlExclusiveZone, WlWindowFocusable};
     use yuck::config::{window_definition::WindowStacking, window_geometry::AnchorAlignment};
 
     pub struct WaylandBackend;
@@ -112,19 +125,67 @@ mod platform_wayland {
                 } else {
                     window.set_layer_shell_margin(gtk_layer_shell::Edge::Top, yoffset);
                 }
-                // https://github.com/elkowar/eww/issues/296
-                if window_init.backend_options.wayland.exclusive
+
+                if let Some(edge) = window_init.backend_options.wayland.exclusive_edge {
+                    // The compositor reserves space at the edge a surface is anchored to, if it is also anchored to both
+                    // perpendicular edges. Anchor it that way, using margins to keep the window where the geometry puts it.
+                    let width = geometry.size.x.pixels_relative_to(monitor.width());
+                    let height = geometry.size.y.pixels_relative_to(monitor.height());
+                    let left_margin = x - monitor.x();
+                    let top_margin = y - monitor.y();
+                    let (anchored_edges, opposite_edge) = match edge {
+                        Side::Top => ([Edge::Top, Edge::Left, Edge::Right], Edge::Bottom),
+                        Side::Bottom => ([Edge::Bottom, Edge::Left, Edge::Right], Edge::Top),
+                        Side::Left => ([Edge::Left, Edge::Top, Edge::Bottom], Edge::Right),
+                        Side::Right => ([Edge::Right, Edge::Top, Edge::Bottom], Edge::Left),
+                    };
+                    for edge in anchored_edges {
+                        window.set_anchor(edge, true);
+                    }
+                    window.set_anchor(opposite_edge, false);
+                    window.set_layer_shell_margin(Edge::Left, left_margin);
+                    window.set_layer_shell_margin(Edge::Right, monitor.width() - left_margin - width);
+                    window.set_layer_shell_margin(Edge::Top, top_margin);
+                    window.set_layer_shell_margin(Edge::Bottom, monitor.height() - top_margin - height);
+                } else if window_init.backend_options.wayland.exclusive.is_enabled()
                     && geometry.anchor_point.x != AnchorAlignment::CENTER
                     && geometry.anchor_point.y != AnchorAlignment::CENTER
                 {
-                    log::warn!("When ':exclusive true' the anchor has to include 'center', otherwise exlcusive won't work")
+                    // https://github.com/elkowar/eww/issues/296
+                    log::warn!(
+                        "When ':exclusive' is set, the anchor has to include 'center' or ':exclusive-edge' has to be set, \
+                         otherwise exclusive won't work"
+                    )
                 }
             }
-            if window_init.backend_options.wayland.exclusive {
-                window.auto_exclusive_zone_enable();
+            match window_init.backend_options.wayland.exclusive {
+                WlExclusiveZone::Disabled => {}
+                WlExclusiveZone::Auto => window.auto_exclusive_zone_enable(),
+                WlExclusiveZone::Fixed(zone) => window.set_exclusive_zone(zone),
             }
             Some(window)
         }
+
+        fn reserve_space_for_content(
+            window: &Window,
+            window_init: &WindowInitiator,
+            _monitor: &gdk::Monitor,
+            _window_rect: Option<gdk::Rectangle>,
+            (content_width, content_height): (i32, i32),
+        ) -> anyhow::Result<()> {
+            let wayland_options = &window_init.backend_options.wayland;
+            let is_vertical_edge = match wayland_options.exclusive_edge {
+                Some(edge) => matches!(edge, Side::Left | Side::Right),
+                None => window_init.geometry.is_some_and(|geometry| geometry.anchor_point.y == AnchorAlignment::CENTER),
+            };
+            let content_extent = if is_vertical_edge { content_width } else { content_height };
+            match wayland_options.exclusive {
+                WlExclusiveZone::Auto => window.set_exclusive_zone(content_extent),
+                WlExclusiveZone::Fixed(zone) if zone > 0 => window.set_exclusive_zone(zone.min(content_extent)),
+                WlExclusiveZone::Disabled | WlExclusiveZone::Fixed(_) => {}
+            }
+            Ok(())
+        }
     }
 }
 
@@ -143,9 +204,12 @@ mod platform_x11 {
         protocol::xproto::*,
         rust_connection::{DefaultStream, RustConnection},
     };
-    use yuck::config::{
-        backend_window_options::{Side, X11WindowType},
-        window_definition::WindowStacking,
+    use yuck::{
+        config::{
+            backend_window_options::{Side, X11WindowType},
+            window_definition::WindowStacking,
+        },
+        value::NumWithUnit,
     };
 
     use super::DisplayBackend;
@@ -159,6 +223,13 @@ mod platform_x11 {
             let window_type =
                 if window_init.backend_options.x11.wm_ignore { gtk::WindowType::Popup } else { gtk::WindowType::Toplevel };
             let window = Window::new(window_type, x, y);
+            let wayland_options = &window_init.backend_options.wayland;
+            if wayland_options.exclusive.is_enabled() || wayland_options.exclusive_edge.is_some() {
+                log::warn!(
+                    "':exclusive' and ':exclusive-edge' of window {} only work on Wayland, use ':reserve' on X11 instead",
+                    window_init.name
+                );
+            }
             window.set_resizable(window_init.resizable);
             window.set_keep_above(window_init.stacking == WindowStacking::Foreground);
             window.set_keep_below(window_init.stacking == WindowStacking::Background);
@@ -169,14 +240,83 @@ mod platform_x11 {
             }
             Some(window)
         }
+
+        fn reserve_space_for_content(
+            window: &Window,
+            window_init: &WindowInitiator,
+            monitor: &gdk::Monitor,
+            window_rect: Option<gdk::Rectangle>,
+            (content_width, content_height): (i32, i32),
+        ) -> Result<()> {
+            let mut window_init = window_init.clone();
+            let struts = &mut window_init.backend_options.x11.struts;
+            let monitor_rect = monitor.geometry();
+            let (configured, content_extent) = match struts.side {
+                Side::Left | Side::Right => (struts.distance.pixels_relative_to(monitor_rect.width()), content_width),
+                Side::Top | Side::Bottom => (struts.distance.pixels_relative_to(monitor_rect.height()), content_height),
+            };
+            struts.distance = NumWithUnit::Pixels(configured.min(content_extent.max(0)));
+            set_xprops(window, monitor.clone(), window_rect, &window_init)
+        }
     }
 
-    pub fn set_xprops(window: &Window, monitor: Monitor, window_init: &WindowInitiator) -> Result<()> {
+    /// Set the X11 properties of the window. `window_rect` is the area the window is placed in, which the struts are
+    /// limited to along their edge. Without it, the struts span the whole monitor.
+    pub fn set_xprops(
+        window: &Window,
+        monitor: Monitor,
+        window_rect: Option<gdk::Rectangle>,
+        window_init: &WindowInitiator,
+    ) -> Result<()> {
         let backend = X11BackendConnection::new()?;
-        backend.set_xprops_for(window, monitor, window_init)?;
+        backend.set_xprops_for(window, monitor, window_rect, window_init)?;
         Ok(())
     }
 
+    /// Listen for changes of the active keyboard layout via XKB, calling `on_change` with the name of the initial layout
+    /// and every layout that is switched to. This blocks until the connection to the X server fails.
+    pub fn run_keyboard_layout_listener(on_change: impl Fn(String)) -> Result<()> {
+        use x11rb::protocol::{xkb, xkb::ConnectionExt as _, Event};
+
+        let (conn, _) = x11rb::connect(None)?;
+        conn.xkb_use_extension(1, 0)?.reply().context("XKB extension is not available")?;
+        let device: xkb::DeviceSpec = xkb::ID::USE_CORE_KBD.into();
+        conn.xkb_select_events(
+            device,
+            xkb::EventType::from(0u16),
+            xkb::EventType::STATE_NOTIFY | xkb::EventType::NEW_KEYBOARD_NOTIFY,
+            xkb::MapPart::from(0u16),
+            xkb::MapPart::from(0u16),
+            &xkb::SelectEventsAux::new(),
+        )?
+        .check()?;
+
+        let layout_name = |group: xkb::Group| -> Result<String> {
+            let names = conn.xkb_get_names(device, xkb::NameDetail::GROUP_NAMES)?.reply()?;
+            let atom = names
+                .value_list
+                .groups
+                .as_ref()
+                .and_then(|groups| groups.get(u8::from(group) as usize).copied())
+                .context("XKB didn't report a name for the active layout")?;
+            Ok(String::from_utf8_lossy(&conn.get_atom_name(atom)?.reply()?.name).into_owned())
+        };
+
+        let mut group = conn.xkb_get_state(device)?.reply()?.group;
+        on_change(layout_name(group)?);
+        loop {
+            match conn.wait_for_event()? {
+                Event::XkbStateNotify(evt) if evt.group != group => {
+                    group = evt.group;
+                    on_change(layout_name(group)?);
+                }
+                // The names of the layouts may have changed, i.e. after running setxkbmap
+                Event::XkbNewKeyboardNotify(_) => on_change(layout_name(group)?),
+                _ => {}
+            }
+        }
+    }
+
     struct X11BackendConnection {
         conn: RustConnection<DefaultStream>,
         root_window: u32,
@@ -191,7 +331,13 @@ mod platform_x11 {
             Ok(X11BackendConnection { conn, root_window: screen.root, atoms })
         }
 
-        fn set_xprops_for(&self, window: &Window, monitor: Monitor, window_init: &WindowInitiator) -> Result<()> {
+        fn set_xprops_for(
+            &self,
+            window: &Window,
+            monitor: Monitor,
+            window_rect: Option<gdk::Rectangle>,
+            window_init: &WindowInitiator,
+        ) -> Result<()> {
             let monitor_rect = monitor.geometry();
             let scale_factor = monitor.scale_factor() as u32;
             let gdk_window = window.window().context("Couldn't get gdk window from gtk window")?;
@@ -205,6 +351,13 @@ mod platform_x11 {
             let mon_end_x = scale_factor * (monitor_rect.x() + monitor_rect.width()) as u32 - 1u32;
             let mon_end_y = scale_factor * (monitor_rect.y() + monitor_rect.height()) as u32 - 1u32;
 
+            // The struts only cover the part of the edge the window actually spans
+            let span_rect = window_rect.unwrap_or(monitor_rect);
+            let span_x = scale_factor * span_rect.x().max(0) as u32;
+            let span_y = scale_factor * span_rect.y().max(0) as u32;
+            let span_end_x = scale_factor * (span_rect.x() + span_rect.width()).max(1) as u32 - 1u32;
+            let span_end_y = scale_factor * (span_rect.y() + span_rect.height()).max(1) as u32 - 1u32;
+
             let dist = match strut_def.side {
                 Side::Left | Side::Right => strut_def.distance.pixels_relative_to(monitor_rect.width()) as u32,
                 Side::Top | Side::Bottom => strut_def.distance.pixels_relative_to(monitor_rect.height()) as u32,
@@ -215,10 +368,10 @@ mod platform_x11 {
             // left, right, top, bottom, left_start_y, left_end_y, right_start_y, right_end_y, top_start_x, top_end_x, bottom_start_x, bottom_end_x
             #[rustfmt::skip]
             let strut_list: Vec<u8> = match strut_def.side {
-                Side::Left   => vec![dist + mon_x, 0,                                                    0,                   0,                                                     mon_x, mon_end_y, 0,     0,         0,     0,         0,             0],
-                Side::Right  => vec![0,            root_window_geometry.width as u32 - mon_end_x + dist, 0,                   0,                                                     0,     0,         mon_x, mon_end_y, 0,     0,         0,             0],
-                Side::Top    => vec![0,            0,                                                    dist + mon_y, 0,                                                     0,     0,         0,     0,         mon_x, mon_end_x, 0,             0],
-                Side::Bottom => vec![0,            0,                                                    0,                   root_window_geometry.height as u32 - mon_end_y + dist, 0,     0,         0,     0,         0,     0,         mon_x,  mon_end_x],
+                Side::Left   => vec![dist + mon_x, 0,                                                    0,                   0,                                                     span_y, span_end_y, 0,      0,          0,      0,          0,      0],
+                Side::Right  => vec![0,            root_window_geometry.width as u32 - mon_end_x + dist, 0,                   0,                                                     0,      0,          span_y, span_end_y, 0,      0,          0,      0],
+                Side::Top    => vec![0,            0,                                                    dist + mon_y, 0,                                                     0,      0,          0,      0,          span_x, span_end_x, 0,      0],
+                Side::Bottom => vec![0,            0,                                                    0,                   root_window_geometry.height as u32 - mon_end_y + dist, 0,      0,          0,      0,          0,      0,          span_x, span_end_x],
                 // This should never happen but if it does the window will be anchored on the
                 // right of the screen
             }.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect();
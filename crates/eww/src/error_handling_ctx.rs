@@ -1,10 +1,14 @@
 //! Disgusting global state.
 //! I hate this, but [buffet](https://github.com/buffet) told me that this is what I should do for peak maintainability!
 
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, RwLock},
+};
 
 use codespan_reporting::{
-    diagnostic::Diagnostic,
+    diagnostic::{Diagnostic, LabelStyle, Severity},
+    files::Files,
     term::{self, Chars},
 };
 use eww_shared_util::Span;
@@ -16,20 +20,132 @@ use crate::file_database::FileDatabase;
 
 pub static FILE_DATABASE: Lazy<Arc<RwLock<FileDatabase>>> = Lazy::new(|| Arc::new(RwLock::new(FileDatabase::new())));
 
+/// How many of the most recent errors are kept around for `eww errors` and `EWW_ERRORS`
+const RECENT_ERRORS_CAPACITY: usize = 100;
+
+/// Errors printed via [`print_error`] or recorded via [`record_error`], most recent last.
+pub static RECENT_ERRORS: Lazy<Mutex<VecDeque<RecordedError>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(RECENT_ERRORS_CAPACITY)));
+
+/// Notified whenever [`RECENT_ERRORS`] changes, so that `EWW_ERRORS` can be updated.
+pub static RECENT_ERRORS_CHANGED: Lazy<tokio::sync::Notify> = Lazy::new(tokio::sync::Notify::new);
+
+/// An error that was printed, along with the time it occurred at.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecordedError {
+    #[serde(serialize_with = "serialize_time")]
+    pub time: chrono::DateTime<chrono::Local>,
+    #[serde(serialize_with = "serialize_severity")]
+    pub severity: Severity,
+    /// Short, single-line description of the error
+    pub message: String,
+    /// Name of the file the error originates from, if it points at the configuration
+    pub file: Option<String>,
+    /// Line within `file`, starting at 1
+    pub line: Option<usize>,
+    /// The full error as it was printed, without any colors
+    pub details: String,
+}
+
+fn serialize_time<S: serde::Serializer>(time: &chrono::DateTime<chrono::Local>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&time.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn serialize_severity<S: serde::Serializer>(severity: &Severity, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(match severity {
+        Severity::Bug | Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note | Severity::Help => "info",
+    })
+}
+
 pub fn clear_files() {
     *FILE_DATABASE.write().unwrap() = FileDatabase::new();
 }
 
 pub fn print_error(err: anyhow::Error) {
     match anyhow_err_to_diagnostic(&err) {
-        Some(diag) => match stringify_diagnostic(diag) {
-            Ok(diag) => eprintln!("{}", diag),
-            Err(_) => log::error!("{:?}", err),
+        Some(diag) => match stringify_diagnostic(diag.clone()) {
+            Ok(printed) => {
+                eprintln!("{}", printed);
+                record_diagnostic(diag);
+            }
+            Err(_) => {
+                log::error!("{:?}", err);
+                record_error(Severity::Error, &err);
+            }
         },
-        None => log::error!("{:?}", err),
+        None => {
+            log::error!("{:?}", err);
+            record_error(Severity::Error, &err);
+        }
     }
 }
 
+/// Log a warning, and record it like the errors printed via [`print_error`].
+pub fn print_warning(err: anyhow::Error) {
+    log::warn!("{:?}", err);
+    record_error(Severity::Warning, &err);
+}
+
+/// Record an error that is logged without going through [`print_error`], so it shows up in `eww errors` and `EWW_ERRORS`.
+pub fn record_error(severity: Severity, err: &anyhow::Error) {
+    push_recent_error(RecordedError {
+        time: chrono::Local::now(),
+        severity,
+        message: format!("{:#}", err),
+        file: None,
+        line: None,
+        details: format!("{:?}", err),
+    });
+}
+
+fn record_diagnostic(diagnostic: Diagnostic<usize>) {
+    let (file, line) = {
+        let files = FILE_DATABASE.read().unwrap();
+        let primary_label = diagnostic.labels.iter().find(|label| {
+            label.style == LabelStyle::Primary && !Span(label.range.start, label.range.end, label.file_id).is_dummy()
+        });
+        match primary_label {
+            Some(label) => (
+                files.name(label.file_id).ok().map(|name| name.to_string()),
+                files.line_index(label.file_id, label.range.start).ok().map(|line| line + 1),
+            ),
+            None => (None, None),
+        }
+    };
+    push_recent_error(RecordedError {
+        time: chrono::Local::now(),
+        severity: diagnostic.severity,
+        message: diagnostic.message.clone(),
+        file,
+        line,
+        details: stringify_diagnostic_plain(diagnostic).unwrap_or_default(),
+    });
+}
+
+/// Add an error to the [`RECENT_ERRORS`], dropping the oldest one if there are too many.
+fn push_recent_error(error: RecordedError) {
+    let mut recent_errors = RECENT_ERRORS.lock().unwrap();
+    if recent_errors.len() >= RECENT_ERRORS_CAPACITY {
+        recent_errors.pop_front();
+    }
+    recent_errors.push_back(error);
+    RECENT_ERRORS_CHANGED.notify_one();
+}
+
+/// Remove all [`RECENT_ERRORS`].
+pub fn clear_recent_errors() {
+    RECENT_ERRORS.lock().unwrap().clear();
+    RECENT_ERRORS_CHANGED.notify_one();
+}
+
+/// Get the [`RECENT_ERRORS`] as a JSON array, for `EWW_ERRORS`.
+pub fn recent_errors_json() -> String {
+    let recent_errors = RECENT_ERRORS.lock().unwrap();
+    serde_json::to_string(&*recent_errors).unwrap_or_else(|_| "[]".to_string())
+}
+
 pub fn format_error(err: &anyhow::Error) -> String {
     anyhow_err_to_diagnostic(err).and_then(|diag| stringify_diagnostic(diag).ok()).unwrap_or_else(|| format!("{:?}", err))
 }
@@ -49,7 +165,23 @@ pub fn anyhow_err_to_diagnostic(err: &anyhow::Error) -> Option<Diagnostic<usize>
     }
 }
 
-pub fn stringify_diagnostic(mut diagnostic: codespan_reporting::diagnostic::Diagnostic<usize>) -> anyhow::Result<String> {
+pub fn stringify_diagnostic(diagnostic: codespan_reporting::diagnostic::Diagnostic<usize>) -> anyhow::Result<String> {
+    let mut buf = Vec::new();
+    emit_diagnostic(&mut term::termcolor::Ansi::new(&mut buf), diagnostic)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Like [`stringify_diagnostic`], but without any colors.
+pub fn stringify_diagnostic_plain(diagnostic: codespan_reporting::diagnostic::Diagnostic<usize>) -> anyhow::Result<String> {
+    let mut buf = Vec::new();
+    emit_diagnostic(&mut term::termcolor::NoColor::new(&mut buf), diagnostic)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn emit_diagnostic(
+    writer: &mut dyn term::termcolor::WriteColor,
+    mut diagnostic: codespan_reporting::diagnostic::Diagnostic<usize>,
+) -> anyhow::Result<()> {
     diagnostic.labels.retain(|label| !Span(label.range.start, label.range.end, label.file_id).is_dummy());
 
     let mut config = term::Config::default();
@@ -57,9 +189,7 @@ pub fn stringify_diagnostic(mut diagnostic: codespan_reporting::diagnostic::Diag
     chars.single_primary_caret = '─';
     config.chars = chars;
     config.chars.note_bullet = '→';
-    let mut buf = Vec::new();
-    let mut writer = term::termcolor::Ansi::new(&mut buf);
     let files = FILE_DATABASE.read().unwrap();
-    term::emit(&mut writer, &config, &*files, &diagnostic)?;
-    Ok(String::from_utf8(buf)?)
+    term::emit(writer, &config, &*files, &diagnostic)?;
+    Ok(())
 }
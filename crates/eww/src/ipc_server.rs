@@ -1,4 +1,4 @@
-use crate::{app, opts};
+use crate::{app, daemon_response, opts};
 use anyhow::{Context, Result};
 use std::time::Duration;
 use tokio::{
@@ -33,22 +33,54 @@ async fn handle_connection(mut stream: tokio::net::UnixStream, evt_send: Unbound
 
     log::debug!("received command from IPC: {:?}", &action);
 
+    let is_subscription = action.is_subscription();
     let (command, maybe_response_recv) = action.into_daemon_command();
 
     evt_send.send(command)?;
 
-    if let Some(mut response_recv) = maybe_response_recv {
-        log::debug!("Waiting for response for IPC client");
-        if let Ok(Some(response)) = tokio::time::timeout(Duration::from_millis(100), response_recv.recv()).await {
-            let response = bincode::serialize(&response)?;
-            let result = &stream_write.write_all(&response).await;
-            crate::print_result_err!("sending text response to ipc client", &result);
+    match maybe_response_recv {
+        Some(response_recv) if is_subscription => {
+            forward_subscription_responses(&mut stream_read, &mut stream_write, response_recv).await;
+            // The client may already be gone, so failing to shut down the stream is expected here
+            let _ = stream_write.shutdown().await;
+            return Ok(());
         }
+        Some(mut response_recv) => {
+            log::debug!("Waiting for response for IPC client");
+            if let Ok(Some(response)) = tokio::time::timeout(Duration::from_millis(100), response_recv.recv()).await {
+                let response = bincode::serialize(&response)?;
+                let result = &stream_write.write_all(&response).await;
+                crate::print_result_err!("sending text response to ipc client", &result);
+            }
+        }
+        None => {}
     }
     stream_write.shutdown().await?;
     Ok(())
 }
 
+/// Send every response of a subscription to the client, until either side stops.
+async fn forward_subscription_responses(
+    stream_read: &'_ mut tokio::net::unix::ReadHalf<'_>,
+    stream_write: &'_ mut tokio::net::unix::WriteHalf<'_>,
+    mut response_recv: daemon_response::DaemonResponseReceiver,
+) {
+    log::debug!("Forwarding responses to IPC client until it disconnects");
+    let mut buf = [0u8; 1];
+    crate::loop_select_exiting! {
+        // The client never sends anything after the request, so this only finishes once it disconnected
+        Ok(0) | Err(_) = stream_read.read(&mut buf) => break,
+        response = response_recv.recv() => match response {
+            Some(response) => {
+                if write_response_to_stream(stream_write, &response).await.is_err() {
+                    break;
+                }
+            }
+            None => break,
+        },
+    }
+}
+
 /// Read a single message from a unix stream, and parses it into a `ActionWithServer`
 /// The format here requires the first 4 bytes to be the size of the rest of the message (in big-endian), followed by the rest of the message.
 async fn read_action_from_stream(stream_read: &'_ mut tokio::net::unix::ReadHalf<'_>) -> Result<opts::ActionWithServer> {
@@ -62,3 +94,14 @@ async fn read_action_from_stream(stream_read: &'_ mut tokio::net::unix::ReadHalf
 
     bincode::deserialize(&raw_message).context("Failed to parse client message")
 }
+
+/// Write a single response of a subscription to a unix stream, using the same format as `read_action_from_stream`.
+async fn write_response_to_stream(
+    stream_write: &'_ mut tokio::net::unix::WriteHalf<'_>,
+    response: &daemon_response::DaemonResponse,
+) -> Result<()> {
+    let response = bincode::serialize(response)?;
+    stream_write.write_all(&(response.len() as u32).to_be_bytes()).await?;
+    stream_write.write_all(&response).await?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Key combinations defined via the `:keybinds` attribute of a window.
+
+use anyhow::{anyhow, bail, Result};
+use gtk::gdk::{self, ModifierType};
+
+/// What happens when a keybind is pressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeybindAction {
+    /// Close the window the keybind belongs to
+    Close,
+    /// Run a shell command
+    Command(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Keybind {
+    keyval: gdk::keys::Key,
+    modifiers: ModifierType,
+    pub action: KeybindAction,
+}
+
+/// The modifiers that have to match exactly. Others, such as caps lock or num lock, are ignored.
+fn relevant_modifiers() -> ModifierType {
+    ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK | ModifierType::MOD1_MASK | ModifierType::SUPER_MASK
+}
+
+impl Keybind {
+    /// Parse a key combination such as `Escape`, `ctrl+q` or `ctrl+shift+Return`, where the last part is the name of a key
+    /// as understood by gdk.
+    pub fn parse(combo: &str, action: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = combo.split('+').map(str::trim).collect();
+        let key_name = parts.pop().filter(|x| !x.is_empty()).ok_or_else(|| anyhow!("Keybind `{}` is missing a key", combo))?;
+        let mut modifiers = ModifierType::empty();
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => ModifierType::CONTROL_MASK,
+                "shift" => ModifierType::SHIFT_MASK,
+                "alt" | "mod1" => ModifierType::MOD1_MASK,
+                "super" | "mod4" | "meta" => ModifierType::SUPER_MASK,
+                other => bail!("Unknown modifier `{}` in keybind `{}`, must be one of ctrl, shift, alt, super", other, combo),
+            };
+        }
+        let keyval = gdk::keys::Key::from_name(key_name);
+        if keyval == gdk::keys::constants::VoidSymbol {
+            bail!("Unknown key `{}` in keybind `{}`", key_name, combo);
+        }
+        let action = if action.trim() == "close" { KeybindAction::Close } else { KeybindAction::Command(action.to_string()) };
+        Ok(Keybind { keyval: keyval.to_lower(), modifiers, action })
+    }
+
+    pub fn matches(&self, event: &gdk::EventKey) -> bool {
+        event.keyval().to_lower() == self.keyval && event.state() & relevant_modifiers() == self.modifiers
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use gdk::keys::constants;
+
+    #[test]
+    fn test_parse_keybind() {
+        let keybind = Keybind::parse("ctrl+shift+Return", "close").unwrap();
+        assert_eq!(constants::Return, keybind.keyval);
+        assert_eq!(ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK, keybind.modifiers);
+        assert_eq!(KeybindAction::Close, keybind.action);
+
+        let keybind = Keybind::parse("Escape", "notify-send hi").unwrap();
+        assert_eq!(constants::Escape, keybind.keyval);
+        assert_eq!(ModifierType::empty(), keybind.modifiers);
+        assert_eq!(KeybindAction::Command("notify-send hi".to_string()), keybind.action);
+
+        // Modifiers are case insensitive and may be surrounded by spaces, and keys always match their lowercase variant
+        let keybind = Keybind::parse("Alt + Super + Q", "close").unwrap();
+        assert_eq!(constants::q, keybind.keyval);
+        assert_eq!(ModifierType::MOD1_MASK | ModifierType::SUPER_MASK, keybind.modifiers);
+    }
+
+    #[test]
+    fn test_parse_invalid_keybind() {
+        let err = Keybind::parse("hyper+a", "close").unwrap_err();
+        assert!(err.to_string().starts_with("Unknown modifier `hyper`"), "unexpected error: {}", err);
+        let err = Keybind::parse("ctrl+NotAKey", "close").unwrap_err();
+        assert_eq!("Unknown key `NotAKey` in keybind `ctrl+NotAKey`", err.to_string());
+        let err = Keybind::parse("ctrl+", "close").unwrap_err();
+        assert_eq!("Keybind `ctrl+` is missing a key", err.to_string());
+    }
+}
@@ -17,6 +17,8 @@ use crate::server::ForkResult;
 mod app;
 mod application_lifecycle;
 mod client;
+mod close_on_unfocus;
+mod color_scheme;
 mod config;
 mod daemon_response;
 mod display_backend;
@@ -24,49 +26,86 @@ mod error_handling_ctx;
 mod file_database;
 mod geometry;
 mod ipc_server;
+mod keybinds;
+mod mpris;
 mod opts;
 mod paths;
+mod profiling;
 mod script_var_handler;
 mod server;
 mod state;
 mod util;
+mod widget_tree;
 mod widgets;
 mod window_arguments;
 mod window_initiator;
+mod window_transition;
 
 fn main() {
     let eww_binary_name = std::env::args().next().unwrap();
     let opts: opts::Opt = opts::Opt::from_env();
 
-    let log_level_filter = if opts.log_debug { log::LevelFilter::Debug } else { log::LevelFilter::Info };
-    if std::env::var("RUST_LOG").is_ok() {
-        pretty_env_logger::init_timed();
-    } else {
-        pretty_env_logger::formatted_timed_builder()
-            .filter(Some("eww"), log_level_filter)
-            .filter(Some("notifier_host"), log_level_filter)
-            .init();
+    let log_level_filter = log::LevelFilter::from(opts.log_level);
+    let mut log_builder = pretty_env_logger::formatted_timed_builder();
+    match std::env::var("RUST_LOG") {
+        Ok(filters) => log_builder.parse_filters(&filters),
+        Err(_) => log_builder.filter(Some("eww"), log_level_filter).filter(Some("notifier_host"), log_level_filter),
+    };
+    if opts.log_format == opts::LogFormat::Json {
+        log_builder.format(|buf, record| {
+            use std::io::Write;
+            let entry = serde_json::json!({
+                "time": chrono::Local::now().to_rfc3339(),
+                "level": record.level().to_string(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{}", entry)
+        });
     }
+    log_builder.init();
 
     if let opts::Action::ShellCompletions { shell } = opts.action {
         clap_complete::generate(shell, &mut opts::RawOpt::command(), "eww", &mut std::io::stdout());
         return;
     }
 
+    let requested_backend = match requested_backend(opts.backend) {
+        Ok(backend) => backend,
+        Err(err) => {
+            error_handling_ctx::print_error(err);
+            std::process::exit(1);
+        }
+    };
     let detected_wayland = detect_wayland();
     #[allow(unused)]
-    let use_wayland = opts.force_wayland || detected_wayland;
+    let use_wayland = match requested_backend {
+        Some(backend) => backend == opts::Backend::Wayland,
+        None => opts.force_wayland || detected_wayland,
+    };
     #[cfg(all(feature = "wayland", feature = "x11"))]
     let result = if use_wayland {
-        log::debug!("Running on wayland. force_wayland={}, detected_wayland={}", opts.force_wayland, detected_wayland);
+        log::debug!(
+            "Running on wayland. backend={:?}, force_wayland={}, detected_wayland={}",
+            requested_backend,
+            opts.force_wayland,
+            detected_wayland
+        );
         run::<display_backend::WaylandBackend>(opts, eww_binary_name)
     } else {
-        log::debug!("Running on X11. force_wayland={}, detected_wayland={}", opts.force_wayland, detected_wayland);
+        log::debug!(
+            "Running on X11. backend={:?}, force_wayland={}, detected_wayland={}",
+            requested_backend,
+            opts.force_wayland,
+            detected_wayland
+        );
         run::<display_backend::X11Backend>(opts, eww_binary_name)
     };
 
     #[cfg(all(not(feature = "wayland"), feature = "x11"))]
-    let result = {
+    let result = if requested_backend == Some(opts::Backend::Wayland) {
+        Err(anyhow::anyhow!("The wayland backend was requested, but eww was compiled without wayland support"))
+    } else {
         if use_wayland {
             log::warn!("Eww compiled without wayland support. Falling back to X11, eventhough wayland was requested.");
         }
@@ -74,10 +113,19 @@ fn main() {
     };
 
     #[cfg(all(feature = "wayland", not(feature = "x11")))]
-    let result = run::<display_backend::WaylandBackend>(opts, eww_binary_name);
+    let result = if requested_backend == Some(opts::Backend::X11) {
+        Err(anyhow::anyhow!("The x11 backend was requested, but eww was compiled without x11 support"))
+    } else {
+        run::<display_backend::WaylandBackend>(opts, eww_binary_name)
+    };
 
     #[cfg(not(any(feature = "wayland", feature = "x11")))]
-    let result = run::<display_backend::NoBackend>(opts, eww_binary_name);
+    let result = match requested_backend {
+        Some(backend) => {
+            Err(anyhow::anyhow!("The {} backend was requested, but eww was compiled without support for it", backend))
+        }
+        None => run::<display_backend::NoBackend>(opts, eww_binary_name),
+    };
 
     if let Err(err) = result {
         error_handling_ctx::print_error(err);
@@ -85,6 +133,19 @@ fn main() {
     }
 }
 
+/// The backend given via `--backend`, or otherwise the `EWW_BACKEND` environment variable.
+fn requested_backend(from_args: Option<opts::Backend>) -> Result<Option<opts::Backend>> {
+    if from_args.is_some() {
+        return Ok(from_args);
+    }
+    match std::env::var("EWW_BACKEND") {
+        Ok(value) if !value.trim().is_empty() => <opts::Backend as clap::ValueEnum>::from_str(value.trim(), true)
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("Invalid value `{}` for EWW_BACKEND, must be one of x11, wayland", value)),
+        _ => Ok(None),
+    }
+}
+
 fn detect_wayland() -> bool {
     let session_type = std::env::var("XDG_SESSION_TYPE").unwrap_or_default();
     let wayland_display = std::env::var("WAYLAND_DISPLAY").unwrap_or_default();
@@ -112,6 +173,7 @@ fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()
         std::thread::sleep(std::time::Duration::from_millis(200));
     }
 
+    let batch_window = std::time::Duration::from_millis(opts.batch_window_ms);
     let would_show_logs = match opts.action {
         opts::Action::ShellCompletions { .. } => unreachable!(),
         opts::Action::ClientOnly(action) => {
@@ -131,7 +193,7 @@ fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()
             if !opts.show_logs {
                 println!("Run `{} logs` to see any errors while editing your configuration.", eww_binary_name);
             }
-            let fork_result = server::initialize_server::<B>(paths.clone(), None, !opts.no_daemonize)?;
+            let fork_result = server::initialize_server::<B>(paths.clone(), None, !opts.no_daemonize, batch_window)?;
             opts.no_daemonize || fork_result == ForkResult::Parent
         }
 
@@ -142,6 +204,13 @@ fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()
             false
         }
 
+        opts::Action::WithServer(action) if action.is_subscription() => {
+            let mut stream = attempt_connect(paths.get_ipc_socket_file(), 5).context("Failed to connect to daemon")?;
+            client::do_subscription_call(&mut stream, &action, handle_daemon_response)
+                .context("Error while listening to responses from server")?;
+            false
+        }
+
         // a running daemon is necessary for this command
         opts::Action::WithServer(action) => {
             // attempt to just send the command to a running daemon
@@ -163,7 +232,7 @@ fn run<B: DisplayBackend>(opts: opts::Opt, eww_binary_name: String) -> Result<()
 
                     let (command, response_recv) = action.into_daemon_command();
                     // start the daemon and give it the command
-                    let fork_result = server::initialize_server::<B>(paths.clone(), Some(command), true)?;
+                    let fork_result = server::initialize_server::<B>(paths.clone(), Some(command), true, batch_window)?;
                     let is_parent = fork_result == ForkResult::Parent;
                     if let (Some(recv), true) = (response_recv, is_parent) {
                         listen_for_daemon_response(recv);
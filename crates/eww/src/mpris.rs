@@ -0,0 +1,190 @@
+//! Tracks media players implementing the MPRIS D-Bus interface, for `EWW_MPRIS` and `eww mpris`.
+
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+use anyhow::{anyhow, Context, Result};
+use futures::StreamExt;
+use once_cell::sync::{Lazy, OnceCell};
+use zbus::zvariant::{OwnedValue, Value};
+
+use crate::opts::MprisAction;
+
+const PLAYER_NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+#[zbus::dbus_proxy(interface = "org.mpris.MediaPlayer2.Player", default_path = "/org/mpris/MediaPlayer2")]
+trait Player {
+    fn play(&self) -> zbus::Result<()>;
+    fn pause(&self) -> zbus::Result<()>;
+    fn play_pause(&self) -> zbus::Result<()>;
+    fn stop(&self) -> zbus::Result<()>;
+    fn next(&self) -> zbus::Result<()>;
+    fn previous(&self) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+    #[dbus_proxy(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+    /// Position in the current track, in microseconds
+    #[dbus_proxy(property)]
+    fn position(&self) -> zbus::Result<i64>;
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+struct PlayerState {
+    /// Name of the player, i.e. `spotify` for `org.mpris.MediaPlayer2.spotify`
+    player: String,
+    /// `Playing`, `Paused` or `Stopped`
+    status: String,
+    title: String,
+    artist: String,
+    album: String,
+    art_url: String,
+    /// Length of the track in seconds
+    length: f64,
+    /// Position in the track in seconds
+    position: f64,
+    #[serde(skip)]
+    updated_at: Option<Instant>,
+}
+
+/// State of the active player, as of the last change. This is `None` until the listener has started.
+static STATE: Lazy<Mutex<Option<PlayerState>>> = Lazy::new(|| Mutex::new(None));
+
+static CONNECTION: OnceCell<zbus::Connection> = OnceCell::new();
+
+/// Get the state of the active player as JSON, estimating the current position from the time that passed since the last change.
+pub fn get_mpris_json() -> String {
+    let mut state =
+        STATE.lock().unwrap().clone().unwrap_or_else(|| PlayerState { status: "Stopped".to_string(), ..Default::default() });
+    if let (Some(updated_at), "Playing") = (state.updated_at, state.status.as_str()) {
+        state.position += updated_at.elapsed().as_secs_f64();
+        if state.length > 0.0 {
+            state.position = state.position.min(state.length);
+        }
+    }
+    serde_json::to_string(&state).unwrap_or_default()
+}
+
+/// Listen for players appearing, disappearing and changing their state, calling `on_change` with the new value of
+/// `EWW_MPRIS` whenever anything changed.
+pub async fn run_listener(on_change: impl Fn(String)) -> Result<()> {
+    let connection = zbus::Connection::session().await.context("Failed to connect to the D-Bus session bus")?;
+    let _ = CONNECTION.set(connection.clone());
+
+    let match_rule = |rule: &'static str| zbus::MessageStream::for_match_rule(rule, &connection, None);
+    let mut property_changes = match_rule(
+        "type='signal',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',path='/org/mpris/MediaPlayer2'",
+    )
+    .await?;
+    let mut seeks = match_rule("type='signal',interface='org.mpris.MediaPlayer2.Player',member='Seeked'").await?;
+    let mut owner_changes = match_rule(
+        "type='signal',sender='org.freedesktop.DBus',interface='org.freedesktop.DBus',member='NameOwnerChanged',arg0namespace='org.mpris.MediaPlayer2'",
+    )
+    .await?;
+
+    loop {
+        let state = match find_active_player(&connection).await {
+            Ok(Some((name, player))) => read_player_state(&name, &player).await.unwrap_or_else(|err| {
+                log::warn!("Failed to read the state of media player {}: {}", name, err);
+                PlayerState { status: "Stopped".to_string(), ..Default::default() }
+            }),
+            Ok(None) => PlayerState { status: "Stopped".to_string(), ..Default::default() },
+            Err(err) => {
+                log::warn!("Failed to look up media players: {}", err);
+                PlayerState { status: "Stopped".to_string(), ..Default::default() }
+            }
+        };
+        *STATE.lock().unwrap() = Some(state);
+        on_change(get_mpris_json());
+
+        tokio::select! {
+            Some(_) = property_changes.next() => {},
+            Some(_) = seeks.next() => {},
+            Some(_) = owner_changes.next() => {},
+            else => break,
+        }
+    }
+    Ok(())
+}
+
+/// Find the player that is most likely the one the user is interested in, which is the first one that's playing, or,
+/// if there is none, the first one that's paused.
+async fn find_active_player(connection: &zbus::Connection) -> zbus::Result<Option<(String, PlayerProxy<'static>)>> {
+    let names = zbus::fdo::DBusProxy::new(connection).await?.list_names().await?;
+    let mut best_player = None;
+    let mut best_rank = 0;
+    for name in names.into_iter().map(|x| x.to_string()).filter(|x| x.starts_with(PLAYER_NAME_PREFIX)) {
+        let player = PlayerProxy::builder(connection)
+            .destination(name.clone())?
+            .cache_properties(zbus::CacheProperties::No)
+            .build()
+            .await?;
+        let rank = match player.playback_status().await.as_deref() {
+            Ok("Playing") => 3,
+            Ok("Paused") => 2,
+            _ => 1,
+        };
+        if rank > best_rank {
+            best_rank = rank;
+            best_player = Some((name, player));
+        }
+    }
+    Ok(best_player)
+}
+
+async fn read_player_state(name: &str, player: &PlayerProxy<'_>) -> zbus::Result<PlayerState> {
+    let metadata = player.metadata().await.unwrap_or_default();
+    let micros_to_secs = |micros: i64| micros as f64 / 1_000_000.0;
+    Ok(PlayerState {
+        player: name.trim_start_matches(PLAYER_NAME_PREFIX).to_string(),
+        status: player.playback_status().await?,
+        title: metadata_string(&metadata, "xesam:title"),
+        artist: metadata_string(&metadata, "xesam:artist"),
+        album: metadata_string(&metadata, "xesam:album"),
+        art_url: metadata_string(&metadata, "mpris:artUrl"),
+        length: micros_to_secs(match metadata.get("mpris:length").map(|x| &**x) {
+            Some(Value::I64(length)) => *length,
+            Some(Value::U64(length)) => *length as i64,
+            _ => 0,
+        }),
+        // Not all players support reporting their position
+        position: micros_to_secs(player.position().await.unwrap_or(0)),
+        updated_at: Some(Instant::now()),
+    })
+}
+
+/// Get a metadata entry as a string, joining lists (such as artists) with commas.
+fn metadata_string(metadata: &HashMap<String, OwnedValue>, key: &str) -> String {
+    match metadata.get(key).map(|x| &**x) {
+        Some(Value::Str(value)) => value.to_string(),
+        Some(Value::Array(values)) => values
+            .get()
+            .iter()
+            .filter_map(|x| match x {
+                Value::Str(value) => Some(value.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => String::new(),
+    }
+}
+
+/// Send an action to the active player.
+pub async fn control(action: MprisAction) -> Result<String> {
+    let connection = match CONNECTION.get() {
+        Some(connection) => connection.clone(),
+        None => zbus::Connection::session().await.context("Failed to connect to the D-Bus session bus")?,
+    };
+    let (name, player) = find_active_player(&connection).await?.ok_or_else(|| anyhow!("No media player is running"))?;
+    match action {
+        MprisAction::Play => player.play().await,
+        MprisAction::Pause => player.pause().await,
+        MprisAction::PlayPause => player.play_pause().await,
+        MprisAction::Stop => player.stop().await,
+        MprisAction::Next => player.next().await,
+        MprisAction::Previous => player.previous().await,
+    }
+    .with_context(|| format!("Failed to control media player {}", name))?;
+    Ok(name.trim_start_matches(PLAYER_NAME_PREFIX).to_string())
+}
@@ -17,26 +17,42 @@ use crate::{
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct Opt {
     pub force_wayland: bool,
-    pub log_debug: bool,
+    pub backend: Option<Backend>,
+    pub log_level: LogLevel,
+    pub log_format: LogFormat,
     pub show_logs: bool,
     pub restart: bool,
     pub config_path: Option<std::path::PathBuf>,
     pub action: Action,
     pub no_daemonize: bool,
+    pub batch_window_ms: u64,
 }
 
 #[derive(Parser, Debug, Serialize, Deserialize, PartialEq)]
 #[clap(author = "ElKowar")]
 #[clap(version, about)]
 pub(super) struct RawOpt {
-    /// Write out debug logs. (To read the logs, run `eww logs`).
+    /// Write out debug logs. (To read the logs, run `eww logs`). Same as `--log-level debug`.
     #[arg(long = "debug", global = true)]
     log_debug: bool,
 
+    /// Only log messages of this level or more severe. Ignored if the `RUST_LOG` environment variable is set.
+    #[arg(long = "log-level", global = true, value_enum, default_value_t = LogLevel::Info)]
+    log_level: LogLevel,
+
+    /// Format to write log messages in. `json` writes one object per line, with `time`, `level`, `target` and `message` fields.
+    #[arg(long = "log-format", global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
     /// Force eww to use wayland. This is a no-op if eww was compiled without wayland support.
     #[arg(long = "force-wayland", global = true)]
     force_wayland: bool,
 
+    /// Display backend to use instead of the detected one. Can also be set via the `EWW_BACKEND` environment variable.
+    /// Fails if eww was compiled without support for it, or no display of that kind is available.
+    #[arg(long = "backend", global = true, value_enum)]
+    backend: Option<Backend>,
+
     /// override path to configuration directory (directory that contains eww.yuck and eww.(s)css)
     #[arg(short, long, global = true)]
     config: Option<std::path::PathBuf>,
@@ -53,6 +69,12 @@ pub(super) struct RawOpt {
     #[arg(long = "restart", global = true)]
     restart: bool,
 
+    /// When starting the daemon: after a variable changes, wait this many milliseconds for other variables to change,
+    /// and apply all of these changes at once. This reduces redraws when many variables change in quick succession.
+    /// Other commands sent to the daemon during that time, such as `eww open`, are still handled right away.
+    #[arg(long = "batch-window-ms", global = true, default_value_t = 0)]
+    batch_window_ms: u64,
+
     #[command(subcommand)]
     action: Action,
 }
@@ -96,6 +118,10 @@ pub enum ActionWithServer {
         /// variable_name="new_value"-pairs that will be updated
         #[arg(value_parser = parse_var_update_arg)]
         mappings: Vec<(VarName, DynVal)>,
+
+        /// Update the window-local variables of the open window with this id (as given to `eww open --id`), instead of global variables
+        #[arg(long)]
+        id: Option<String>,
     },
 
     /// Update a polling variable using its script.
@@ -126,6 +152,10 @@ pub enum ActionWithServer {
         #[arg(long)]
         screen: Option<MonitorIdentifier>,
 
+        /// A regex matched against the connector names and models of the monitors, selecting the monitor the window should open on
+        #[arg(long, conflicts_with = "screen")]
+        monitor: Option<String>,
+
         /// The position of the window, where it should open. (i.e.: 200x100)
         #[arg(short, long)]
         pos: Option<Coords>,
@@ -166,6 +196,10 @@ pub enum ActionWithServer {
         /// If a window is already open, close it instead
         #[arg(long = "toggle")]
         should_toggle: bool,
+
+        /// If any of the windows fails to open, close the ones that were opened, rather than leaving only some of them open
+        #[arg(long)]
+        atomic: bool,
     },
 
     /// Close the given windows
@@ -174,7 +208,11 @@ pub enum ActionWithServer {
 
     /// Reload the configuration
     #[command(name = "reload", alias = "r")]
-    Reload,
+    Reload {
+        /// Keep the current values of variables whose definition didn't change
+        #[arg(long)]
+        soft: bool,
+    },
 
     /// Kill the eww daemon
     #[command(name = "kill", alias = "k")]
@@ -190,11 +228,33 @@ pub enum ActionWithServer {
         /// Shows all variables, including not currently used ones
         #[arg(short, long)]
         all: bool,
+
+        /// Print a JSON object mapping the variable names to their values
+        #[arg(long)]
+        json: bool,
     },
 
-    /// Get the value of a variable if defined
+    /// Get the value of one or more variables if defined
     #[command(name = "get")]
-    GetVar { name: String },
+    GetVar {
+        /// Variables to get, printed linewise in the given order
+        #[arg(required = true)]
+        names: Vec<String>,
+
+        /// Print a JSON object mapping the variable names to their values
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print the values of variables whenever they change, as newline-delimited JSON objects, until interrupted.
+    ///
+    /// The current values are printed first.
+    #[command(name = "listen-var")]
+    ListenVars {
+        /// Variables to listen to
+        #[arg(required = true)]
+        names: Vec<VarName>,
+    },
 
     /// List the names of active windows
     #[command(name = "list-windows")]
@@ -202,7 +262,11 @@ pub enum ActionWithServer {
 
     /// Show active window IDs, formatted linewise `<window_id>: <window_name>`
     #[command(name = "active-windows")]
-    ListActiveWindows,
+    ListActiveWindows {
+        /// Print a JSON list of the open windows, including their monitor and geometry
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Print out the widget structure as seen by eww.
     ///
@@ -211,9 +275,107 @@ pub enum ActionWithServer {
     #[command(name = "debug")]
     ShowDebug,
 
+    /// Print the GTK widget hierarchy of an open window, including the CSS classes and current size of each widget.
+    #[command(name = "inspect")]
+    Inspect {
+        /// Id of the window, which is its name unless it was opened with `--id`
+        window_id: String,
+
+        /// Print the hierarchy as a JSON object
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Print out the scope graph structure in graphviz dot format.
     #[command(name = "graph")]
     ShowGraph,
+
+    /// Measure how long widgets take to build and draw, to find out which widgets are slowing down your bar.
+    ///
+    /// Start collecting with `eww profile start`, use your widgets for a while, then view the results with `eww profile show`.
+    #[command(name = "profile")]
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+
+    /// Control the active media player, which is the first player found that is playing, or otherwise paused.
+    /// Its state is available in the `EWW_MPRIS` variable.
+    #[command(name = "mpris")]
+    Mpris {
+        #[command(subcommand)]
+        action: MprisAction,
+    },
+
+    /// Print the most recent errors the daemon encountered, such as errors in widgets, along with the time they occurred at.
+    #[command(name = "errors")]
+    ShowErrors {
+        /// Clear the list of errors after printing them
+        #[arg(long)]
+        clear: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum ProfileAction {
+    /// Start collecting timings, discarding the ones collected so far
+    Start,
+    /// Print the timings collected so far, per widget
+    Show,
+    /// Print the timings collected so far and stop collecting
+    Stop,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum MprisAction {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+}
+
+#[derive(clap::ValueEnum, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(clap::ValueEnum, Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum Backend {
+    X11,
+    Wayland,
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backend::X11 => write!(f, "x11"),
+            Backend::Wayland => write!(f, "wayland"),
+        }
+    }
 }
 
 impl Opt {
@@ -225,8 +387,33 @@ impl Opt {
 
 impl From<RawOpt> for Opt {
     fn from(other: RawOpt) -> Self {
-        let RawOpt { log_debug, force_wayland, config, show_logs, no_daemonize, restart, action } = other;
-        Opt { log_debug, force_wayland, show_logs, restart, config_path: config, action, no_daemonize }
+        let RawOpt {
+            log_debug,
+            log_level,
+            log_format,
+            force_wayland,
+            backend,
+            config,
+            show_logs,
+            no_daemonize,
+            restart,
+            batch_window_ms,
+            action,
+        } = other;
+        // `--debug` predates `--log-level`, and should never make the logs less verbose
+        let log_level = if log_debug { log_level.max(LogLevel::Debug) } else { log_level };
+        Opt {
+            log_level,
+            log_format,
+            force_wayland,
+            backend,
+            show_logs,
+            restart,
+            config_path: config,
+            action,
+            no_daemonize,
+            batch_window_ms,
+        }
     }
 }
 
@@ -261,9 +448,17 @@ impl ActionWithServer {
         matches!(self, ActionWithServer::OpenWindow { .. } | ActionWithServer::OpenMany { .. })
     }
 
+    /// Whether the daemon keeps sending responses to this action until the client disconnects.
+    pub fn is_subscription(&self) -> bool {
+        matches!(self, ActionWithServer::ListenVars { .. })
+    }
+
     pub fn into_daemon_command(self) -> (app::DaemonCommand, Option<daemon_response::DaemonResponseReceiver>) {
         let command = match self {
-            ActionWithServer::Update { mappings } => app::DaemonCommand::UpdateVars(mappings),
+            ActionWithServer::Update { mappings, id: None } => app::DaemonCommand::UpdateVars(mappings),
+            ActionWithServer::Update { mappings, id: Some(instance_id) } => {
+                app::DaemonCommand::UpdateWindowVars { instance_id, mappings }
+            }
             ActionWithServer::Poll { names } => app::DaemonCommand::PollVars(names),
             ActionWithServer::OpenInspector => app::DaemonCommand::OpenInspector,
 
@@ -274,17 +469,34 @@ impl ActionWithServer {
                 let _ = send.send(DaemonResponse::Success("pong".to_owned()));
                 return (app::DaemonCommand::NoOp, Some(recv));
             }
-            ActionWithServer::OpenMany { windows, args, should_toggle } => {
-                return with_response_channel(|sender| app::DaemonCommand::OpenMany { windows, args, should_toggle, sender });
+            ActionWithServer::OpenMany { windows, args, should_toggle, atomic } => {
+                return with_response_channel(|sender| app::DaemonCommand::OpenMany {
+                    windows,
+                    args,
+                    should_toggle,
+                    atomic,
+                    sender,
+                });
             }
-            ActionWithServer::OpenWindow { window_name, id, pos, size, screen, anchor, should_toggle, duration, args } => {
+            ActionWithServer::OpenWindow {
+                window_name,
+                id,
+                pos,
+                size,
+                screen,
+                monitor,
+                anchor,
+                should_toggle,
+                duration,
+                args,
+            } => {
                 return with_response_channel(|sender| app::DaemonCommand::OpenWindow {
                     window_name,
                     instance_id: id,
                     pos,
                     size,
                     anchor,
-                    screen,
+                    screen: screen.or(monitor.map(MonitorIdentifier::Regex)),
                     should_toggle,
                     duration,
                     sender,
@@ -294,17 +506,36 @@ impl ActionWithServer {
             ActionWithServer::CloseWindows { windows } => {
                 return with_response_channel(|sender| app::DaemonCommand::CloseWindows { windows, sender });
             }
-            ActionWithServer::Reload => return with_response_channel(app::DaemonCommand::ReloadConfigAndCss),
+            ActionWithServer::Reload { soft } => {
+                return with_response_channel(|sender| app::DaemonCommand::ReloadConfigAndCss { soft, sender })
+            }
             ActionWithServer::ListWindows => return with_response_channel(app::DaemonCommand::ListWindows),
-            ActionWithServer::ListActiveWindows => return with_response_channel(app::DaemonCommand::ListActiveWindows),
-            ActionWithServer::ShowState { all } => {
-                return with_response_channel(|sender| app::DaemonCommand::PrintState { all, sender })
+            ActionWithServer::ListActiveWindows { json } => {
+                return with_response_channel(|sender| app::DaemonCommand::ListActiveWindows { json, sender })
+            }
+            ActionWithServer::ShowState { all, json } => {
+                return with_response_channel(|sender| app::DaemonCommand::PrintState { all, json, sender })
+            }
+            ActionWithServer::GetVar { names, json } => {
+                return with_response_channel(|sender| app::DaemonCommand::GetVar { names, json, sender })
             }
-            ActionWithServer::GetVar { name } => {
-                return with_response_channel(|sender| app::DaemonCommand::GetVar { name, sender })
+            ActionWithServer::ListenVars { names } => {
+                return with_response_channel(|sender| app::DaemonCommand::SubscribeVars { names, sender })
             }
             ActionWithServer::ShowDebug => return with_response_channel(app::DaemonCommand::PrintDebug),
+            ActionWithServer::Inspect { window_id, json } => {
+                return with_response_channel(|sender| app::DaemonCommand::InspectWindow { window_id, json, sender })
+            }
             ActionWithServer::ShowGraph => return with_response_channel(app::DaemonCommand::PrintGraph),
+            ActionWithServer::Profile { action } => {
+                return with_response_channel(|sender| app::DaemonCommand::Profile { action, sender })
+            }
+            ActionWithServer::Mpris { action } => {
+                return with_response_channel(|sender| app::DaemonCommand::MprisControl { action, sender })
+            }
+            ActionWithServer::ShowErrors { clear } => {
+                return with_response_channel(|sender| app::DaemonCommand::PrintErrors { clear, sender })
+            }
         };
         (command, None)
     }
@@ -0,0 +1,172 @@
+//! Collects how long building and drawing widgets takes, grouped by widget name, for `eww profile`.
+//!
+//! Timings are inclusive, meaning that the time a widget takes includes the time its children take.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt::Write,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use gtk::{glib, prelude::*};
+use once_cell::sync::Lazy;
+
+/// Whether a profile is currently being collected.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+static PROFILE: Lazy<Mutex<Option<Profile>>> = Lazy::new(|| Mutex::new(None));
+
+/// Key of the names of the eww widgets a GTK widget was built for, stored on the GTK widget
+const WIDGET_NAMES_KEY: &str = "eww-profiling-widget-names";
+
+thread_local! {
+    /// Draw handlers connected for the current profile, which are disconnected again once it stops
+    static DRAW_HANDLERS: RefCell<Vec<(glib::WeakRef<gtk::Widget>, [glib::SignalHandlerId; 2])>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Debug)]
+pub struct Profile {
+    started_at: Instant,
+    widgets: HashMap<String, WidgetTimings>,
+}
+
+#[derive(Debug, Default)]
+struct WidgetTimings {
+    builds: u32,
+    build_time: Duration,
+    draws: u32,
+    draw_time: Duration,
+}
+
+/// Start collecting a new profile, discarding the current one.
+/// The draws of the widgets in the given windows, as well as of all widgets built while profiling, are measured.
+pub fn start(windows: impl IntoIterator<Item = gtk::Widget>) {
+    disconnect_draw_handlers();
+    *PROFILE.lock().unwrap() = Some(Profile { started_at: Instant::now(), widgets: HashMap::new() });
+    ACTIVE.store(true, Ordering::SeqCst);
+    for window in windows {
+        track_draws_recursively(&window);
+    }
+}
+
+/// Stop collecting, returning the profile collected so far.
+pub fn stop() -> Option<Profile> {
+    ACTIVE.store(false, Ordering::SeqCst);
+    disconnect_draw_handlers();
+    PROFILE.lock().unwrap().take()
+}
+
+/// Format the profile collected so far, if any.
+pub fn report() -> Option<String> {
+    PROFILE.lock().unwrap().as_ref().map(Profile::report)
+}
+
+fn record(widget_name: &str, f: impl FnOnce(&mut WidgetTimings)) {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(profile) = PROFILE.lock().unwrap().as_mut() {
+        f(profile.widgets.entry(widget_name.to_string()).or_default());
+    }
+}
+
+/// Record that building a widget took the given time.
+pub fn record_build(widget_name: &str, duration: Duration) {
+    record(widget_name, |timings| {
+        timings.builds += 1;
+        timings.build_time += duration;
+    });
+}
+
+/// Remember which eww widget a GTK widget was built for, so its draws can be measured once a profile is started.
+/// If a profile is running already, its draws are measured right away.
+pub fn register_widget(gtk_widget: &gtk::Widget, widget_name: String) {
+    // A custom widget returns the GTK widget of its body, so several eww widgets may share one GTK widget
+    let mut widget_names = unsafe { gtk_widget.steal_data::<Vec<String>>(WIDGET_NAMES_KEY) }.unwrap_or_default();
+    if ACTIVE.load(Ordering::Relaxed) {
+        track_draws(gtk_widget, vec![widget_name.clone()]);
+    }
+    widget_names.push(widget_name);
+    unsafe { gtk_widget.set_data(WIDGET_NAMES_KEY, widget_names) };
+}
+
+fn track_draws_recursively(gtk_widget: &gtk::Widget) {
+    if let Some(widget_names) = unsafe { gtk_widget.data::<Vec<String>>(WIDGET_NAMES_KEY) } {
+        track_draws(gtk_widget, unsafe { widget_names.as_ref() }.clone());
+    }
+    if let Some(container) = gtk_widget.downcast_ref::<gtk::Container>() {
+        for child in container.children() {
+            track_draws_recursively(&child);
+        }
+    }
+}
+
+/// Measure how long drawing the widget takes, until the profile is stopped.
+fn track_draws(gtk_widget: &gtk::Widget, widget_names: Vec<String>) {
+    let draw_started_at = Rc::new(Cell::new(None));
+    let before_draw = gtk_widget.connect_draw({
+        let draw_started_at = draw_started_at.clone();
+        move |_, _| {
+            draw_started_at.set(Some(Instant::now()));
+            glib::Propagation::Proceed
+        }
+    });
+    // Handlers connected with `after` run once the widget itself has been drawn
+    let after_draw = gtk_widget.connect_local("draw", true, move |_| {
+        if let Some(started_at) = draw_started_at.take() {
+            for widget_name in &widget_names {
+                record(widget_name, |timings| {
+                    timings.draws += 1;
+                    timings.draw_time += started_at.elapsed();
+                });
+            }
+        }
+        Some(false.to_value())
+    });
+    DRAW_HANDLERS.with(|handlers| handlers.borrow_mut().push((gtk_widget.downgrade(), [before_draw, after_draw])));
+}
+
+fn disconnect_draw_handlers() {
+    for (gtk_widget, handler_ids) in DRAW_HANDLERS.with(|handlers| handlers.take()) {
+        if let Some(gtk_widget) = gtk_widget.upgrade() {
+            for handler_id in handler_ids {
+                gtk_widget.disconnect(handler_id);
+            }
+        }
+    }
+}
+
+impl Profile {
+    pub fn report(&self) -> String {
+        let mut widgets: Vec<_> = self.widgets.iter().collect();
+        widgets.sort_by_key(|(_, timings)| std::cmp::Reverse(timings.build_time + timings.draw_time));
+
+        let mut output = format!("Profile of the last {:.1}s\n", self.started_at.elapsed().as_secs_f64());
+        let _ = writeln!(
+            output,
+            "{:<30} {:>8} {:>12} {:>8} {:>12} {:>12}",
+            "widget", "builds", "build (ms)", "draws", "draw (ms)", "avg draw (ms)"
+        );
+        for (name, timings) in widgets {
+            let avg_draw = if timings.draws > 0 { timings.draw_time / timings.draws } else { Duration::ZERO };
+            let _ = writeln!(
+                output,
+                "{:<30} {:>8} {:>12.3} {:>8} {:>12.3} {:>12.3}",
+                name,
+                timings.builds,
+                timings.build_time.as_secs_f64() * 1000.0,
+                timings.draws,
+                timings.draw_time.as_secs_f64() * 1000.0,
+                avg_draw.as_secs_f64() * 1000.0,
+            );
+        }
+        output.trim_end().to_string()
+    }
+}
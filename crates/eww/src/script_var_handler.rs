@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, hash::BuildHasher};
 
 use crate::{
     app,
@@ -168,9 +168,10 @@ impl PollVarHandler {
                 crate::error_handling_ctx::print_error(err);
             }
 
+            let mut delay = first_poll_delay(&var);
             crate::loop_select_exiting! {
                 _ = cancellation_token.cancelled() => break,
-                _ = tokio::time::sleep(var.interval) => {
+                _ = tokio::time::sleep(delay) => {
                     let result: Result<_> = (|| {
                         evt_send.send(app::DaemonCommand::UpdateVars(vec![(var.name.clone(), run_poll_once(&var)?)]))?;
                         Ok(())
@@ -179,6 +180,7 @@ impl PollVarHandler {
                     if let Err(err) = result {
                         crate::error_handling_ctx::print_error(err);
                     }
+                    delay = if var.align { time_until_aligned(var.interval) } else { var.interval };
                 }
             }
         });
@@ -196,13 +198,37 @@ impl PollVarHandler {
     }
 }
 
+/// Delay between starting a poll var and its first scheduled poll.
+fn first_poll_delay(var: &PollScriptVar) -> std::time::Duration {
+    if var.align {
+        time_until_aligned(var.interval)
+    } else if var.jitter {
+        // Spread out the polls of variables that were started at the same time
+        let random = std::collections::hash_map::RandomState::new().hash_one(&var.name);
+        var.interval.mul_f64(random as f64 / u64::MAX as f64)
+    } else {
+        var.interval
+    }
+}
+
+/// Time until the next multiple of the interval since the unix epoch, i.e. the top of the minute for an interval of 60s.
+fn time_until_aligned(interval: std::time::Duration) -> std::time::Duration {
+    let interval_nanos = interval.as_nanos();
+    if interval_nanos == 0 {
+        return interval;
+    }
+    let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos();
+    std::time::Duration::from_nanos((interval_nanos - since_epoch % interval_nanos) as u64)
+}
+
 pub fn run_poll_once(var: &PollScriptVar) -> Result<DynVal> {
-    match &var.command {
+    let value = match &var.command {
         VarSource::Shell(span, command) => {
             script_var::run_command(command).map_err(|e| anyhow!(create_script_var_failed_warn(*span, &var.name, &e.to_string())))
         }
         VarSource::Function(x) => x().map_err(|e| anyhow!(e)),
-    }
+    }?;
+    script_var::convert_to_var_type(&var.name, var.var_type, value)
 }
 
 impl Drop for PollVarHandler {
@@ -211,6 +237,22 @@ impl Drop for PollVarHandler {
     }
 }
 
+/// Delay before restarting a listen-var command that failed for the first time.
+const LISTEN_RESTART_INITIAL_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+/// Upper bound for the delay between restarts, which doubles on every consecutive failure.
+const LISTEN_RESTART_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+/// How long a listen-var command has to run before failing for it to not count as crash-looping.
+const LISTEN_HEALTHY_RUN_DURATION: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Get the delay before restarting a listen-var command that failed after running for `run_time`,
+/// given the delay before the previous restart, if there was one.
+fn listen_restart_delay(previous_delay: Option<std::time::Duration>, run_time: std::time::Duration) -> std::time::Duration {
+    match previous_delay {
+        Some(previous_delay) if run_time < LISTEN_HEALTHY_RUN_DURATION => (previous_delay * 2).min(LISTEN_RESTART_MAX_DELAY),
+        _ => LISTEN_RESTART_INITIAL_DELAY,
+    }
+}
+
 struct ListenVarHandler {
     evt_send: UnboundedSender<DaemonCommand>,
     listen_process_handles: HashMap<VarName, cancellation::AwaitableCancelationSender>,
@@ -237,54 +279,51 @@ impl ListenVarHandler {
 
         let evt_send = self.evt_send.clone();
         tokio::spawn(async move {
-            let result: Result<_> = async {
-                let mut handle = unsafe {
-                    tokio::process::Command::new("sh")
-                        .args(["-c", &var.command])
-                        .stdout(std::process::Stdio::piped())
-                        .stderr(std::process::Stdio::piped())
-                        .stdin(std::process::Stdio::null())
-                        .pre_exec(|| {
-                            let _ = setpgid(Pid::from_raw(0), Pid::from_raw(0));
-                            Ok(())
-                        })
-                        .spawn()?
-                };
-                let mut stdout_lines = BufReader::new(handle.stdout.take().unwrap()).lines();
-                let mut stderr_lines = BufReader::new(handle.stderr.take().unwrap()).lines();
-                let mut completion_notify = None;
-                crate::loop_select_exiting! {
-                    _ = handle.wait() => break,
-                    notify = cancel_recv.wait_for_cancel() => {
-                        completion_notify = notify;
+            let mut previous_restart_delay = None;
+            loop {
+                let started_at = std::time::Instant::now();
+                let exit_status = match run_listen_process(&var, &evt_send, &mut cancel_recv).await {
+                    Ok(ListenProcessOutcome::Exited(exit_status)) => exit_status,
+                    Ok(ListenProcessOutcome::Stopped) => break,
+                    Err(err) => {
+                        crate::error_handling_ctx::print_error(
+                            err.context(format!("Error while executing listen-var command {}", &var.command)),
+                        );
                         break;
                     }
-                    Ok(Some(line)) = stdout_lines.next_line() => {
-                        let new_value = DynVal::from_string(line.to_owned());
-                        evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.to_owned(), new_value)]))?;
-                    }
-                    Ok(Some(line)) = stderr_lines.next_line() => {
-                        log::warn!("stderr of `{}`: {}", var.name, line);
-                    }
-                    else => break,
                 };
-                terminate_handle(handle).await;
-
-                if let Some(completion_notify) = completion_notify {
-                    completion_notify.completed().await;
+                // A script that exits successfully is done producing values, rather than broken
+                if exit_status.success() {
+                    log::info!("listen-var command of `{}` exited successfully", var.name);
+                    break;
+                }
+                if !var.restart {
+                    crate::error_handling_ctx::print_warning(anyhow!(
+                        "listen-var command of `{}` exited with {}",
+                        var.name,
+                        exit_status
+                    ));
+                    break;
                 }
-                Ok(())
-            }
-            .await;
 
-            if let Err(err) = result {
-                log::error!(
-                    "[{}:{}] Error while executing listen-var command {}: {:?}",
-                    ::std::file!(),
-                    ::std::line!(),
-                    &var.command,
-                    err
-                );
+                let restart_delay = listen_restart_delay(previous_restart_delay, started_at.elapsed());
+                previous_restart_delay = Some(restart_delay);
+                crate::error_handling_ctx::print_warning(anyhow!(
+                    "listen-var command of `{}` exited with {}, restarting in {}s",
+                    var.name,
+                    exit_status,
+                    restart_delay.as_secs_f64()
+                ));
+                tokio::select! {
+                    _ = tokio::time::sleep(restart_delay) => {}
+                    notify = cancel_recv.wait_for_cancel() => {
+                        if let Some(completion_notify) = notify {
+                            completion_notify.completed().await;
+                        }
+                        break;
+                    }
+                    Ok(()) = crate::application_lifecycle::recv_exit() => break,
+                }
             }
         });
     }
@@ -321,6 +360,65 @@ impl Drop for ListenVarHandler {
     }
 }
 
+/// How a run of a listen-var process ended.
+enum ListenProcessOutcome {
+    /// The process exited on its own.
+    Exited(std::process::ExitStatus),
+    /// The listen-var was stopped, or eww is shutting down.
+    Stopped,
+}
+
+/// Run the command of a listen-var once, forwarding its output until it exits or the listen-var is stopped.
+async fn run_listen_process(
+    var: &ListenScriptVar,
+    evt_send: &UnboundedSender<DaemonCommand>,
+    cancel_recv: &mut cancellation::AwaitableCancelationReceiver,
+) -> Result<ListenProcessOutcome> {
+    let mut handle = unsafe {
+        tokio::process::Command::new("sh")
+            .args(["-c", &var.command])
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .stdin(std::process::Stdio::null())
+            .pre_exec(|| {
+                let _ = setpgid(Pid::from_raw(0), Pid::from_raw(0));
+                Ok(())
+            })
+            .spawn()?
+    };
+    let mut stdout_lines = BufReader::new(handle.stdout.take().unwrap()).lines();
+    let mut stderr_lines = BufReader::new(handle.stderr.take().unwrap()).lines();
+    let mut completion_notify = None;
+    let mut outcome = ListenProcessOutcome::Stopped;
+    crate::loop_select_exiting! {
+        exit_status = handle.wait() => {
+            outcome = ListenProcessOutcome::Exited(exit_status?);
+            break;
+        }
+        notify = cancel_recv.wait_for_cancel() => {
+            completion_notify = notify;
+            break;
+        }
+        Ok(Some(line)) = stdout_lines.next_line() => {
+            // Malformed lines are reported and skipped, keeping the last valid value
+            match script_var::convert_to_var_type(&var.name, var.var_type, DynVal::from_string(line.to_owned())) {
+                Ok(new_value) => evt_send.send(DaemonCommand::UpdateVars(vec![(var.name.to_owned(), new_value)]))?,
+                Err(err) => crate::error_handling_ctx::print_error(err),
+            }
+        }
+        Ok(Some(line)) = stderr_lines.next_line() => {
+            log::warn!("stderr of `{}`: {}", var.name, line);
+        }
+        else => break,
+    };
+    terminate_handle(handle).await;
+
+    if let Some(completion_notify) = completion_notify {
+        completion_notify.completed().await;
+    }
+    Ok(outcome)
+}
+
 async fn terminate_handle(mut child: tokio::process::Child) {
     if let Some(id) = child.id() {
         log::debug!("Killing process with id {}", id);
@@ -372,3 +470,65 @@ mod cancellation {
         (AwaitableCancelationSender(send), AwaitableCancelationReceiver(recv))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn poll_var(name: &str, interval: Duration, jitter: bool, align: bool) -> PollScriptVar {
+        PollScriptVar {
+            name: VarName::from(name),
+            run_while_expr: simplexpr::SimplExpr::Literal(DynVal::from(true)),
+            command: VarSource::Shell(eww_shared_util::Span::DUMMY, "true".to_string()),
+            initial_value: None,
+            interval,
+            jitter,
+            align,
+            var_type: Default::default(),
+            name_span: eww_shared_util::Span::DUMMY,
+        }
+    }
+
+    #[test]
+    fn test_time_until_aligned() {
+        for interval in [Duration::from_millis(1), Duration::from_secs(1), Duration::from_secs(60), Duration::from_secs(3600)] {
+            let delay = time_until_aligned(interval);
+            assert!(delay > Duration::ZERO && delay <= interval, "{:?} is not within (0, {:?}]", delay, interval);
+        }
+        assert_eq!(Duration::ZERO, time_until_aligned(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_listen_restart_delay() {
+        let quickly = Duration::from_millis(100);
+        assert_eq!(LISTEN_RESTART_INITIAL_DELAY, listen_restart_delay(None, quickly));
+        // consecutive quick failures back off exponentially, up to the maximum
+        assert_eq!(Duration::from_secs(2), listen_restart_delay(Some(Duration::from_secs(1)), quickly));
+        assert_eq!(LISTEN_RESTART_MAX_DELAY, listen_restart_delay(Some(Duration::from_secs(40)), quickly));
+        assert_eq!(LISTEN_RESTART_MAX_DELAY, listen_restart_delay(Some(LISTEN_RESTART_MAX_DELAY), quickly));
+        // a command that ran for a while before failing starts over with the initial delay
+        assert_eq!(
+            LISTEN_RESTART_INITIAL_DELAY,
+            listen_restart_delay(Some(LISTEN_RESTART_MAX_DELAY), LISTEN_HEALTHY_RUN_DURATION)
+        );
+    }
+
+    #[test]
+    fn test_first_poll_delay() {
+        let interval = Duration::from_secs(10);
+        assert_eq!(interval, first_poll_delay(&poll_var("plain", interval, false, false)));
+
+        let delay = first_poll_delay(&poll_var("aligned", interval, false, true));
+        assert!(delay > Duration::ZERO && delay <= interval, "{:?} is not within (0, {:?}]", delay, interval);
+
+        for name in ["a", "b", "c", "d", "e"] {
+            let delay = first_poll_delay(&poll_var(name, interval, true, false));
+            assert!(delay <= interval, "{:?} is not within [0, {:?}]", delay, interval);
+        }
+
+        for (jitter, align) in [(false, false), (true, false), (false, true)] {
+            assert_eq!(Duration::ZERO, first_poll_delay(&poll_var("zero", Duration::ZERO, jitter, align)));
+        }
+    }
+}
@@ -7,6 +7,8 @@ use crate::{
     EwwPaths,
 };
 use anyhow::{Context, Result};
+use eww_shared_util::VarName;
+use simplexpr::dynval::DynVal;
 
 use std::{
     cell::RefCell,
@@ -14,16 +16,20 @@ use std::{
     io::Write,
     marker::PhantomData,
     os::unix::io::AsRawFd,
-    path::Path,
+    path::{Path, PathBuf},
     rc::Rc,
     sync::{atomic::Ordering, Arc},
 };
 use tokio::sync::mpsc::*;
 
+/// Minimum time between two updates of `EWW_ERRORS`
+const ERRORS_UPDATE_MIN_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
 pub fn initialize_server<B: DisplayBackend>(
     paths: EwwPaths,
     action: Option<DaemonCommand>,
     should_daemonize: bool,
+    batch_window: std::time::Duration,
 ) -> Result<ForkResult> {
     let (ui_send, mut ui_recv) = tokio::sync::mpsc::unbounded_channel();
 
@@ -68,14 +74,40 @@ pub fn initialize_server<B: DisplayBackend>(
         }
     });
 
-    if B::IS_WAYLAND {
-        std::env::set_var("GDK_BACKEND", "wayland")
+    // GDK has to use the same backend as eww, rather than picking one by itself
+    let gdk_backend = if B::IS_WAYLAND {
+        Some("wayland")
+    } else if B::IS_X11 {
+        Some("x11")
+    } else {
+        None
+    };
+    if let Some(gdk_backend) = gdk_backend {
+        std::env::set_var("GDK_BACKEND", gdk_backend)
+    }
+    gtk::init().with_context(|| match gdk_backend {
+        Some(gdk_backend) => format!("Failed to initialize GTK. Is a {} display available?", gdk_backend),
+        None => "Failed to initialize GTK".to_string(),
+    })?;
+    if let Some(gdk_backend) = gdk_backend {
+        validate_display_backend(gdk_backend)?;
     }
-    gtk::init()?;
 
     log::debug!("Initializing script var handler");
     let script_var_handler = script_var_handler::init(ui_send.clone());
 
+    #[cfg(feature = "x11")]
+    if B::IS_X11 {
+        start_keyboard_layout_listener(ui_send.clone());
+    }
+    start_monitor_listener(ui_send.clone());
+    crate::color_scheme::start_gtk_settings_listener({
+        let ui_send = ui_send.clone();
+        move |scheme| {
+            let _ = ui_send.send(app::DaemonCommand::UpdateVars(vec![("EWW_COLOR_SCHEME".into(), scheme.into())]));
+        }
+    });
+
     let (scope_graph_evt_send, mut scope_graph_evt_recv) = tokio::sync::mpsc::unbounded_channel();
 
     let mut app: App<B> = app::App {
@@ -88,8 +120,11 @@ pub fn initialize_server<B: DisplayBackend>(
         failed_windows: HashSet::new(),
         instance_id_to_args: HashMap::new(),
         css_provider: gtk::CssProvider::new(),
+        css_vars_provider: gtk::CssProvider::new(),
+        css_var_names: HashSet::new(),
         script_var_handler,
         app_evt_send: ui_send.clone(),
+        var_subscriptions: Vec::new(),
         window_close_timer_abort_senders: HashMap::new(),
         paths,
         phantom: PhantomData,
@@ -97,6 +132,7 @@ pub fn initialize_server<B: DisplayBackend>(
 
     if let Some(screen) = gtk::gdk::Screen::default() {
         gtk::StyleContext::add_provider_for_screen(&screen, &app.css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+        gtk::StyleContext::add_provider_for_screen(&screen, &app.css_vars_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
     }
 
     if let Ok((file_id, css)) = config::scss::parse_scss_from_config(app.paths.get_config_dir()) {
@@ -120,7 +156,42 @@ pub fn initialize_server<B: DisplayBackend>(
                     app.scope_graph.borrow_mut().handle_scope_graph_event(scope_graph_evt);
                 },
                 Some(ui_event) = ui_recv.recv() => {
-                    app.handle_command(ui_event);
+                    let DaemonCommand::UpdateVars(mut updates) = ui_event else {
+                        app.handle_command(ui_event);
+                        continue;
+                    };
+                    // Apply variable updates that arrive in quick succession together, so that widgets only update once
+                    // Scope graph events and other commands keep being handled while waiting, they are not part of the batch
+                    if !batch_window.is_zero() {
+                        let mut batch_timeout = gtk::glib::timeout_future(batch_window);
+                        loop {
+                            tokio::select! {
+                                _ = &mut batch_timeout => break,
+                                Some(scope_graph_evt) = scope_graph_evt_recv.recv() => {
+                                    app.scope_graph.borrow_mut().handle_scope_graph_event(scope_graph_evt);
+                                },
+                                Some(command) = ui_recv.recv() => {
+                                    if let Some(command) = add_to_batch(&mut updates, command) {
+                                        app.handle_command(command);
+                                    }
+                                },
+                            }
+                        }
+                    }
+                    let mut next_command = None;
+                    while let Ok(command) = ui_recv.try_recv() {
+                        match command {
+                            DaemonCommand::UpdateVars(more_updates) => updates.extend(more_updates),
+                            command => {
+                                next_command = Some(command);
+                                break;
+                            }
+                        }
+                    }
+                    app.handle_command(DaemonCommand::UpdateVars(coalesce_var_updates(updates)));
+                    if let Some(command) = next_command {
+                        app.handle_command(command);
+                    }
                 }
                 else => break,
             }
@@ -159,6 +230,48 @@ fn init_async_part(paths: EwwPaths, ui_send: UnboundedSender<app::DaemonCommand>
                     tokio::spawn(async move { ipc_server::run_server(ui_send, paths.get_ipc_socket_file()).await })
                 };
 
+                // Not being able to track media players shouldn't stop the daemon, so this isn't joined below
+                tokio::spawn({
+                    let ui_send = ui_send.clone();
+                    async move {
+                        let result = crate::mpris::run_listener(|mpris| {
+                            let _ = ui_send.send(app::DaemonCommand::UpdateVars(vec![("EWW_MPRIS".into(), mpris.into())]));
+                        })
+                        .await;
+                        if let Err(err) = result {
+                            log::warn!("Stopped listening for media players: {:?}", err);
+                        }
+                    }
+                });
+
+                // Without a portal, the color scheme is still taken from the GTK settings
+                tokio::spawn({
+                    let ui_send = ui_send.clone();
+                    async move {
+                        let result = crate::color_scheme::run_portal_listener(|scheme| {
+                            let _ =
+                                ui_send.send(app::DaemonCommand::UpdateVars(vec![("EWW_COLOR_SCHEME".into(), scheme.into())]));
+                        })
+                        .await;
+                        if let Err(err) = result {
+                            log::info!("Not following the color scheme of the desktop portal: {:?}", err);
+                        }
+                    }
+                });
+
+                // Errors that occur while showing EWW_ERRORS would otherwise keep updating it as fast as they happen
+                tokio::spawn({
+                    let ui_send = ui_send.clone();
+                    async move {
+                        loop {
+                            error_handling_ctx::RECENT_ERRORS_CHANGED.notified().await;
+                            let errors = error_handling_ctx::recent_errors_json();
+                            let _ = ui_send.send(app::DaemonCommand::UpdateVars(vec![("EWW_ERRORS".into(), errors.into())]));
+                            tokio::time::sleep(ERRORS_UPDATE_MIN_INTERVAL).await;
+                        }
+                    }
+                });
+
                 let forward_exit_to_app_handle = {
                     let ui_send = ui_send.clone();
                     tokio::spawn(async move {
@@ -182,16 +295,92 @@ fn init_async_part(paths: EwwPaths, ui_send: UnboundedSender<app::DaemonCommand>
     handle
 }
 
+/// Keep `EWW_KB_LAYOUT` up to date by listening for layout changes via XKB, instead of polling it.
+#[cfg(feature = "x11")]
+fn start_keyboard_layout_listener(ui_send: UnboundedSender<app::DaemonCommand>) {
+    let result = std::thread::Builder::new().name("keyboard-layout-listener".to_string()).spawn(move || {
+        let result = crate::display_backend::run_keyboard_layout_listener(|layout| {
+            *config::system_stats::KEYBOARD_LAYOUT.lock().unwrap() = Some(layout.clone());
+            let _ = ui_send.send(app::DaemonCommand::UpdateVars(vec![("EWW_KB_LAYOUT".into(), layout.into())]));
+        });
+        if let Err(err) = result {
+            error_handling_ctx::print_warning(err.context("Stopped listening for keyboard layout changes"));
+        }
+        *config::system_stats::KEYBOARD_LAYOUT.lock().unwrap() = None;
+    });
+    if let Err(err) = result {
+        error_handling_ctx::print_error(anyhow::Error::new(err).context("Failed to start keyboard layout listener"));
+    }
+}
+
+/// Make sure GDK actually opened a display of the given backend, i.e. `x11` or `wayland`.
+fn validate_display_backend(gdk_backend: &str) -> Result<()> {
+    use gtk::prelude::ObjectExt;
+    let display = gtk::gdk::Display::default().context("GTK was initialized, but no display was opened")?;
+    let expected_type = match gdk_backend {
+        "wayland" => "GdkWaylandDisplay",
+        _ => "GdkX11Display",
+    };
+    let actual_type = display.type_().name();
+    if actual_type != expected_type {
+        anyhow::bail!("Requested the {} backend, but GDK opened a display of type {} instead", gdk_backend, actual_type);
+    }
+    Ok(())
+}
+
+/// Keep `EWW_MONITORS` up to date, and let the app move windows around whenever a monitor is added or removed.
+fn start_monitor_listener(ui_send: UnboundedSender<app::DaemonCommand>) {
+    let Some(display) = gtk::gdk::Display::default() else {
+        return;
+    };
+    *config::system_stats::MONITORS.lock().unwrap() = Some(app::get_monitors_json(&display));
+
+    let on_change = Rc::new(move |display: &gtk::gdk::Display| {
+        let monitors = app::get_monitors_json(display);
+        log::info!("Monitors changed: {}", monitors);
+        *config::system_stats::MONITORS.lock().unwrap() = Some(monitors.clone());
+        let _ = ui_send.send(app::DaemonCommand::UpdateVars(vec![("EWW_MONITORS".into(), monitors.into())]));
+        let _ = ui_send.send(app::DaemonCommand::MonitorsChanged);
+    });
+    display.connect_monitor_added({
+        let on_change = on_change.clone();
+        move |display, _| on_change(display)
+    });
+    display.connect_monitor_removed(move |display, _| on_change(display));
+}
+
+/// Add the updates of a command to a batch of variable updates, returning the command if it isn't a variable update
+/// and should be handled right away instead.
+fn add_to_batch(batch: &mut Vec<(VarName, DynVal)>, command: DaemonCommand) -> Option<DaemonCommand> {
+    match command {
+        DaemonCommand::UpdateVars(updates) => {
+            batch.extend(updates);
+            None
+        }
+        command => Some(command),
+    }
+}
+
+/// Merge a list of variable updates, such that each variable is only updated once, to the last value it was given.
+fn coalesce_var_updates(updates: Vec<(VarName, DynVal)>) -> Vec<(VarName, DynVal)> {
+    let mut seen = HashSet::new();
+    let mut coalesced: Vec<_> = updates.into_iter().rev().filter(|(name, _)| seen.insert(name.clone())).collect();
+    coalesced.reverse();
+    coalesced
+}
+
 /// Watch configuration files for changes, sending reload events to the eww app when the files change.
 async fn run_filewatch<P: AsRef<Path>>(config_dir: P, evt_send: UnboundedSender<app::DaemonCommand>) -> Result<()> {
     use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut scss_imports_recv = config::scss::SCSS_IMPORTS.subscribe();
+    let scss_imports = scss_imports_recv.clone();
     let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
         Ok(notify::Event { kind: notify::EventKind::Modify(_), paths, .. }) => {
             let relevant_files_changed = paths.iter().any(|path| {
                 let ext = path.extension().unwrap_or_default();
-                ext == "yuck" || ext == "scss" || ext == "css"
+                ext == "yuck" || ext == "scss" || ext == "css" || scss_imports.borrow().contains(path)
             });
             if relevant_files_changed {
                 if let Err(err) = tx.send(()) {
@@ -204,10 +393,35 @@ async fn run_filewatch<P: AsRef<Path>>(config_dir: P, evt_send: UnboundedSender<
     })?;
     watcher.watch(config_dir.as_ref(), RecursiveMode::Recursive)?;
 
+    // Files imported from the stylesheet may live outside of the config directory, so their directories need to be watched separately.
+    let config_dir = config_dir.as_ref().canonicalize().unwrap_or_else(|_| config_dir.as_ref().to_path_buf());
+    let mut watched_import_dirs = HashSet::new();
+    let mut update_import_watches = |watcher: &mut RecommendedWatcher, imports: &HashSet<PathBuf>| {
+        let import_dirs: HashSet<PathBuf> = imports
+            .iter()
+            .filter(|path| !path.starts_with(&config_dir))
+            .filter_map(|path| path.parent().map(Path::to_path_buf))
+            .collect();
+        for dir in watched_import_dirs.difference(&import_dirs) {
+            let _ = watcher.unwatch(dir);
+        }
+        for dir in import_dirs.difference(&watched_import_dirs) {
+            if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                log::warn!("Failed to watch directory of imported stylesheet {}: {}", dir.display(), err);
+            }
+        }
+        watched_import_dirs = import_dirs;
+    };
+    update_import_watches(&mut watcher, &scss_imports_recv.borrow_and_update());
+
     // make sure to not trigger reloads too much by only accepting one reload every 500ms.
     let debounce_done = Arc::new(std::sync::atomic::AtomicBool::new(true));
 
     crate::loop_select_exiting! {
+        Ok(()) = scss_imports_recv.changed() => {
+            let imports = scss_imports_recv.borrow_and_update().clone();
+            update_import_watches(&mut watcher, &imports);
+        },
         Some(()) = rx.recv() => {
             let debounce_done = debounce_done.clone();
             if debounce_done.swap(false, Ordering::SeqCst) {
@@ -222,7 +436,7 @@ async fn run_filewatch<P: AsRef<Path>>(config_dir: P, evt_send: UnboundedSender<
                 // and eww being too fast, thus reading the file while it's empty.
                 // There should be some cleaner solution for this, but this will do for now.
                 tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-                evt_send.send(app::DaemonCommand::ReloadConfigAndCss(daemon_resp_sender))?;
+                evt_send.send(app::DaemonCommand::ReloadConfigAndCss { soft: false, sender: daemon_resp_sender })?;
                 tokio::spawn(async move {
                     match daemon_resp_response.recv().await {
                         Some(daemon_response::DaemonResponse::Success(_)) => log::info!("Reloaded config successfully"),
@@ -318,3 +532,35 @@ fn cleanup_log_dir(log_dir: impl AsRef<Path>) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn updates(values: &[(&str, &str)]) -> Vec<(VarName, DynVal)> {
+        values.iter().map(|(name, value)| (VarName::from(*name), DynVal::from(*value))).collect()
+    }
+
+    #[test]
+    fn test_add_to_batch() {
+        let mut batch = updates(&[("a", "1")]);
+        assert!(add_to_batch(&mut batch, DaemonCommand::UpdateVars(updates(&[("b", "1"), ("a", "2")]))).is_none());
+        assert_eq!(batch, updates(&[("a", "1"), ("b", "1"), ("a", "2")]));
+
+        // other commands aren't delayed until the batch is applied
+        assert!(matches!(add_to_batch(&mut batch, DaemonCommand::CloseAll), Some(DaemonCommand::CloseAll)));
+        let command = DaemonCommand::UpdateWindowVars { instance_id: "foo".to_string(), mappings: updates(&[("c", "1")]) };
+        assert!(matches!(add_to_batch(&mut batch, command), Some(DaemonCommand::UpdateWindowVars { .. })));
+        assert_eq!(batch, updates(&[("a", "1"), ("b", "1"), ("a", "2")]));
+    }
+
+    #[test]
+    fn test_coalesce_var_updates() {
+        assert_eq!(coalesce_var_updates(Vec::new()), Vec::new());
+        assert_eq!(coalesce_var_updates(updates(&[("a", "1"), ("b", "1")])), updates(&[("a", "1"), ("b", "1")]));
+        assert_eq!(
+            coalesce_var_updates(updates(&[("a", "1"), ("b", "1"), ("a", "2"), ("c", "1"), ("b", "2")])),
+            updates(&[("a", "2"), ("c", "1"), ("b", "2")])
+        );
+    }
+}
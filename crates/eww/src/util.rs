@@ -111,6 +111,47 @@ pub fn replace_env_var_references(input: String) -> String {
         .into_owned()
 }
 
+/// Create the [`std::process::Command`] that runs the given shell command.
+/// Commands that consist of plain words only, like `playerctl play-pause`, are executed directly,
+/// which saves having to spawn a shell for every run. Anything else is executed via `/bin/sh -c`.
+pub fn shell_command(cmd: &str) -> std::process::Command {
+    match split_plain_command(cmd) {
+        Some((program, args)) => {
+            let mut command = std::process::Command::new(program);
+            command.args(args);
+            command
+        }
+        None => {
+            let mut command = std::process::Command::new("/bin/sh");
+            command.arg("-c").arg(cmd);
+            command
+        }
+    }
+}
+
+/// Split a command into its words, if it doesn't make use of any shell syntax,
+/// meaning that executing those words directly behaves the same as running the command in a shell.
+fn split_plain_command(cmd: &str) -> Option<(&str, Vec<&str>)> {
+    // Builtins and keywords that only exist within the shell, or that only work as intended when run by the shell itself,
+    // and thus can't be executed directly
+    const SHELL_BUILTINS: &[&str] = &[
+        ".", ":", "alias", "bg", "break", "builtin", "cd", "command", "continue", "declare", "disown", "eval", "exec", "exit",
+        "export", "fg", "getopts", "hash", "jobs", "let", "local", "popd", "pushd", "read", "readonly", "return", "set", "shift",
+        "source", "time", "times", "trap", "type", "typeset", "ulimit", "umask", "unalias", "unset", "wait",
+    ];
+
+    if !cmd.chars().all(|c| c.is_alphanumeric() || c == ' ' || "-_./:=@%+,".contains(c)) {
+        return None;
+    }
+    let mut words = cmd.split(' ').filter(|word| !word.is_empty());
+    let program = words.next()?;
+    // `NAME=value command` sets an environment variable in the shell
+    if program.contains('=') || SHELL_BUILTINS.contains(&program) {
+        return None;
+    }
+    Some((program, words.collect()))
+}
+
 pub fn unindent(text: &str) -> String {
     // take all the lines of our text and skip over the first empty ones
     let lines = text.lines().skip_while(|x| x.is_empty());
@@ -133,7 +174,7 @@ pub fn unindent(text: &str) -> String {
 
 #[cfg(test)]
 mod test {
-    use super::{replace_env_var_references, unindent};
+    use super::{replace_env_var_references, split_plain_command, unindent};
 
     #[test]
     fn test_replace_env_var_references() {
@@ -145,6 +186,37 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_split_plain_command() {
+        assert_eq!(Some(("playerctl", vec!["play-pause"])), split_plain_command("playerctl play-pause"));
+        assert_eq!(Some(("eww", vec!["update", "volume=50"])), split_plain_command("eww  update volume=50"));
+        assert_eq!(None, split_plain_command("echo $HOME"));
+        assert_eq!(None, split_plain_command("notify-send 'hello world'"));
+        assert_eq!(None, split_plain_command("pamixer -i 5 && eww update x=1"));
+        assert_eq!(None, split_plain_command("FOO=bar some-command"));
+        assert_eq!(None, split_plain_command("cd /tmp"));
+        for builtin in [
+            ". ./script.sh",
+            ": noop",
+            "shift 2",
+            "return 1",
+            "local x",
+            "readonly x",
+            "type ls",
+            "command ls",
+            "hash ls",
+            "getopts ab opt",
+            "times",
+            "jobs",
+            "fg",
+            "bg",
+            "time sleep 1",
+        ] {
+            assert_eq!(None, split_plain_command(builtin), "{} is a shell builtin", builtin);
+        }
+        assert_eq!(None, split_plain_command(""));
+    }
+
     #[test]
     fn test_unindent() {
         let indented = "
@@ -0,0 +1,79 @@
+//! Dumps the GTK widget hierarchy of an open window, for `eww inspect`.
+
+use std::fmt::Write;
+
+use gtk::prelude::*;
+
+#[derive(Debug, serde::Serialize)]
+pub struct WidgetNode {
+    /// Name of the GTK type, i.e. `GtkBox`
+    #[serde(rename = "type")]
+    type_name: String,
+    /// Name set via `gtk_widget_set_name`, if it differs from the type name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    classes: Vec<String>,
+    visible: bool,
+    allocation: Allocation,
+    children: Vec<WidgetNode>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Allocation {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl WidgetNode {
+    /// Walk the widget and all of its descendants, including the ones that are currently hidden.
+    pub fn collect(widget: &gtk::Widget) -> Self {
+        let type_name = widget.type_().name().to_string();
+        let name = Some(widget.widget_name().to_string()).filter(|name| *name != type_name);
+        let allocation = widget.allocation();
+        let children = match widget.downcast_ref::<gtk::Container>() {
+            Some(container) => container.children().iter().map(WidgetNode::collect).collect(),
+            None => Vec::new(),
+        };
+        WidgetNode {
+            type_name,
+            name,
+            classes: widget.style_context().list_classes().iter().map(|class| class.to_string()).collect(),
+            visible: widget.is_visible(),
+            allocation: Allocation {
+                x: allocation.x(),
+                y: allocation.y(),
+                width: allocation.width(),
+                height: allocation.height(),
+            },
+            children,
+        }
+    }
+
+    /// Format the tree with one widget per line, indenting children below their parent.
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+        self.write_text(&mut output, 0);
+        output.trim_end().to_string()
+    }
+
+    fn write_text(&self, output: &mut String, depth: usize) {
+        let _ = write!(output, "{}{}", "  ".repeat(depth), self.type_name);
+        if let Some(name) = &self.name {
+            let _ = write!(output, "#{}", name);
+        }
+        for class in &self.classes {
+            let _ = write!(output, ".{}", class);
+        }
+        let Allocation { x, y, width, height } = self.allocation;
+        let _ = write!(output, " {}x{}+{}+{}", width, height, x, y);
+        if !self.visible {
+            output.push_str(" (hidden)");
+        }
+        output.push('\n');
+        for child in &self.children {
+            child.write_text(output, depth + 1);
+        }
+    }
+}
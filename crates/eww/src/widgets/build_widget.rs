@@ -14,7 +14,7 @@ use yuck::{
     config::{
         attributes::AttrEntry,
         widget_definition::WidgetDefinition,
-        widget_use::{BasicWidgetUse, ChildrenWidgetUse, LoopWidgetUse, WidgetUse},
+        widget_use::{BasicWidgetUse, ChildrenWidgetUse, LetWidgetUse, LoopWidgetUse, WidgetUse},
     },
     error::DiagError,
     gen_diagnostic,
@@ -55,8 +55,14 @@ pub fn build_gtk_widget(
 ) -> Result<gtk::Widget> {
     match widget_use {
         WidgetUse::Basic(widget_use) => {
-            build_basic_gtk_widget(graph, widget_defs, calling_scope, widget_use, custom_widget_invocation)
+            let name = widget_use.name.clone();
+            let build_started_at = std::time::Instant::now();
+            let gtk_widget = build_basic_gtk_widget(graph, widget_defs, calling_scope, widget_use, custom_widget_invocation)?;
+            crate::profiling::record_build(&name, build_started_at.elapsed());
+            crate::profiling::register_widget(&gtk_widget, name);
+            Ok(gtk_widget)
         }
+        WidgetUse::Let(widget_use) => build_let_widget(graph, widget_defs, calling_scope, widget_use, custom_widget_invocation),
         WidgetUse::Loop(_) | WidgetUse::Children(_) => Err(anyhow::anyhow!(DiagError(gen_diagnostic! {
             msg = "This widget can only be used as a child of some container widget such as box",
             label = widget_use.span(),
@@ -73,6 +79,8 @@ fn build_basic_gtk_widget(
     custom_widget_invocation: Option<Rc<CustomWidgetInvocation>>,
 ) -> Result<gtk::Widget> {
     if let Some(custom_widget) = widget_defs.clone().get(&widget_use.name) {
+        check_widget_recursion(graph, &widget_defs, calling_scope, &widget_use)?;
+
         let widget_use_attributes = custom_widget
             .expected_args
             .iter()
@@ -81,7 +89,7 @@ fn build_basic_gtk_widget(
                     widget_use
                         .attrs
                         .ast_optional::<SimplExpr>(&spec.name.0)?
-                        .unwrap_or_else(|| SimplExpr::literal(spec.span, "".to_string()))
+                        .unwrap_or_else(|| spec.default.clone().unwrap_or_else(|| SimplExpr::literal(spec.span, "".to_string())))
                 } else {
                     widget_use.attrs.ast_required::<SimplExpr>(&spec.name.0)?
                 };
@@ -98,7 +106,11 @@ fn build_basic_gtk_widget(
             widget_defs,
             new_scope_index,
             custom_widget.widget.clone(),
-            Some(Rc::new(CustomWidgetInvocation { scope: calling_scope, children: widget_use.children })),
+            Some(Rc::new(CustomWidgetInvocation {
+                scope: calling_scope,
+                children: widget_use.children,
+                parent: custom_widget_invocation,
+            })),
         )?;
 
         let scope_graph_sender = graph.event_sender.clone();
@@ -112,6 +124,74 @@ fn build_basic_gtk_widget(
     }
 }
 
+/// How often a custom widget may be used within itself.
+/// Recursion only ends if the widget is eventually not used anymore, like when iterating over a nested structure with `for`,
+/// so this only limits accidental infinite recursion.
+const MAX_WIDGET_RECURSION_DEPTH: usize = 64;
+
+/// Ensure that using the custom widget within the given scope doesn't nest it within itself more than [`MAX_WIDGET_RECURSION_DEPTH`] times,
+/// which would otherwise overflow the stack. The error names the cycle of widgets that lead to the recursion.
+fn check_widget_recursion(
+    graph: &ScopeGraph,
+    widget_defs: &HashMap<String, WidgetDefinition>,
+    calling_scope: ScopeIndex,
+    widget_use: &BasicWidgetUse,
+) -> Result<()> {
+    // The scopes of custom widgets are named after the widget, so the chain of calling scopes tells us which widgets we're in
+    let mut enclosing_widgets = Vec::new();
+    let mut scope = graph.scope_at(calling_scope);
+    while let Some(current) = scope {
+        if widget_defs.contains_key(&current.name) {
+            enclosing_widgets.push(current.name.as_str());
+        }
+        scope = current.ancestor.and_then(|ancestor| graph.scope_at(ancestor));
+    }
+
+    if enclosing_widgets.iter().filter(|name| **name == widget_use.name).count() < MAX_WIDGET_RECURSION_DEPTH {
+        return Ok(());
+    }
+    let cycle_len = enclosing_widgets.iter().position(|name| *name == widget_use.name).unwrap_or_default();
+    let cycle = enclosing_widgets[..=cycle_len].iter().rev().chain(std::iter::once(&widget_use.name.as_str())).join(" → ");
+    Err(anyhow::anyhow!(DiagError(gen_diagnostic! {
+        msg = format!("Widget `{}` is used recursively: {}", widget_use.name, cycle),
+        label = widget_use.name_span => format!("Nested within itself more than {} times here", MAX_WIDGET_RECURSION_DEPTH),
+        note = "Recursive widgets need to stop using themselves at some point, for example by iterating over a nested structure with `for`",
+    })))
+}
+
+/// Build the body of a `let`, in a chain of scopes that each provide one of the bindings.
+/// Each binding is evaluated once in the scope before it, so it may refer to the previous bindings.
+fn build_let_widget(
+    graph: &mut ScopeGraph,
+    widget_defs: Rc<HashMap<String, WidgetDefinition>>,
+    calling_scope: ScopeIndex,
+    widget_use: LetWidgetUse,
+    custom_widget_invocation: Option<Rc<CustomWidgetInvocation>>,
+) -> Result<gtk::Widget> {
+    let mut body_scope = calling_scope;
+    let mut outermost_scope = None;
+    for binding in widget_use.bindings {
+        let scope = graph.register_new_scope(
+            format!("let {}", binding.name),
+            Some(body_scope),
+            body_scope,
+            hashmap! { binding.name.into() => binding.value },
+        )?;
+        outermost_scope.get_or_insert(scope);
+        body_scope = scope;
+    }
+
+    let gtk_widget = build_gtk_widget(graph, widget_defs, body_scope, *widget_use.body, custom_widget_invocation)?;
+
+    if let Some(outermost_scope) = outermost_scope {
+        let scope_graph_sender = graph.event_sender.clone();
+        gtk_widget.connect_destroy(move |_| {
+            let _ = scope_graph_sender.send(ScopeGraphEvent::RemoveScope(outermost_scope));
+        });
+    }
+    Ok(gtk_widget)
+}
+
 /// build a [`gtk::Widget`] out of a [`WidgetUse`] that uses a
 /// **builtin widget**. User defined widgets are handled by [`widget_definitions::widget_use_to_gtk_widget`].
 ///
@@ -231,6 +311,7 @@ fn build_loop_special_widget(
                 let elements_expr = widget_use.elements_expr.clone();
                 let elements_expr_span = widget_use.elements_expr_span;
                 let element_name = widget_use.element_name.clone();
+                let index_name = widget_use.index_name.clone();
                 let body: WidgetUse = widget_use.body.as_ref().clone();
                 let created_children = Rc::new(RefCell::new(Vec::<gtk::Widget>::new()));
                 let created_child_scopes = Rc::new(RefCell::new(Vec::<ScopeIndex>::new()));
@@ -240,7 +321,12 @@ fn build_loop_special_widget(
                         .eval(&values)?
                         .as_json_value()?
                         .as_array()
-                        .context("Not an array value")?
+                        .ok_or_else(|| {
+                            DiagError(gen_diagnostic! {
+                                msg = "The value to loop over in `for` must be a JSON array",
+                                label = elements_expr_span
+                            })
+                        })?
                         .iter()
                         .map(DynVal::from)
                         .collect_vec();
@@ -253,14 +339,18 @@ fn build_loop_special_widget(
                         tree.remove_scope(child_scope);
                     }
 
-                    for element in elements_value {
+                    for (index, element) in elements_value.into_iter().enumerate() {
+                        let mut scope_vars = hashmap! {
+                            element_name.clone().into() => SimplExpr::Literal(DynVal(element.0.clone(), elements_expr_span))
+                        };
+                        if let Some(index_name) = &index_name {
+                            scope_vars.insert(index_name.clone().into(), SimplExpr::Literal(DynVal::from(index as i32)));
+                        }
                         let scope = tree.register_new_scope(
                             format!("for {} = {}", element_name.0, element),
                             Some(calling_scope),
                             calling_scope,
-                            hashmap! {
-                                element_name.clone().into() => SimplExpr::Literal(DynVal(element.0, elements_expr_span))
-                            },
+                            scope_vars,
                         )?;
                         created_child_scopes.push(scope);
                         let new_child_widget =
@@ -279,7 +369,8 @@ fn build_loop_special_widget(
 /// Handle an invocation of the special `children` [`WidgetUse`].
 /// This widget expands to multiple other widgets, thus we require the `gtk_container` we should expand the widgets into.
 /// The `custom_widget_invocation` will be used here to evaluate the provided children in their
-/// original scope and expand them into the given container.
+/// original scope and expand them into the given container. Children may be `for` loops or `children` placeholders
+/// themselves, the latter of which expand to the children of the enclosing custom widget.
 fn build_children_special_widget(
     tree: &mut ScopeGraph,
     widget_defs: Rc<HashMap<String, WidgetDefinition>>,
@@ -312,11 +403,16 @@ fn build_children_special_widget(
                             calling_scope,
                             HashMap::new(),
                         )?;
-                        let new_child_widget =
-                            build_gtk_widget(tree, widget_defs.clone(), scope, nth_child_widget_use.clone(), None)?;
                         child_container.children().iter().for_each(|f| child_container.remove(f));
-                        child_container.set_child(Some(&new_child_widget));
-                        new_child_widget.show();
+                        populate_widget_children(
+                            tree,
+                            widget_defs.clone(),
+                            scope,
+                            child_container.upcast_ref(),
+                            vec![nth_child_widget_use.clone()],
+                            custom_widget_invocation.parent.clone(),
+                        )?;
+                        child_container.children().iter().for_each(|child| child.show());
                         Ok(())
                     }
                 }),
@@ -330,8 +426,14 @@ fn build_children_special_widget(
                 calling_scope,
                 HashMap::new(),
             )?;
-            let child_widget = build_gtk_widget(tree, widget_defs.clone(), scope, child.clone(), None)?;
-            gtk_container.add(&child_widget);
+            populate_widget_children(
+                tree,
+                widget_defs.clone(),
+                scope,
+                gtk_container,
+                vec![child.clone()],
+                custom_widget_invocation.parent.clone(),
+            )?;
         }
     }
     Ok(())
@@ -346,6 +448,9 @@ pub struct CustomWidgetInvocation {
     scope: ScopeIndex,
     /// The children the custom widget was given. These should be evaluated in [`Self::scope`]
     children: Vec<WidgetUse>,
+    /// The invocation of the custom widget this one was used in, if any.
+    /// Needed when one of the [`Self::children`] is a `children` placeholder itself, forwarding the children of that outer widget.
+    parent: Option<Rc<CustomWidgetInvocation>>,
 }
 
 /// Make sure that [`gtk::Bin`] widgets only get a single child.
@@ -3,6 +3,7 @@ use gtk::glib::{self, object_subclass, prelude::*, wrapper, Properties};
 use gtk::{cairo, gdk, prelude::*, subclass::prelude::*};
 use std::cell::RefCell;
 
+use super::color;
 use crate::error_handling_ctx;
 
 wrapper! {
@@ -25,6 +26,26 @@ pub struct CircProgPriv {
     #[property(get, set, nick = "Clockwise", blurb = "Clockwise", default = true)]
     clockwise: RefCell<bool>,
 
+    #[property(
+        get,
+        set,
+        nick = "Color stops",
+        blurb = "Json array of colors the progress goes through from 0 to 100",
+        default = ""
+    )]
+    color_stops: RefCell<String>,
+
+    #[property(
+        get,
+        set,
+        nick = "Gradient",
+        blurb = "Whether to color the ring with a gradient through the color stops",
+        default = false
+    )]
+    gradient: RefCell<bool>,
+
+    /// The colors of `color-stops`, parsed once whenever it is set rather than on every draw
+    parsed_color_stops: RefCell<Vec<gdk::RGBA>>,
     content: RefCell<Option<gtk::Widget>>,
 }
 
@@ -36,6 +57,9 @@ impl Default for CircProgPriv {
             value: RefCell::new(0.0),
             thickness: RefCell::new(1.0),
             clockwise: RefCell::new(true),
+            color_stops: RefCell::new(String::new()),
+            gradient: RefCell::new(false),
+            parsed_color_stops: RefCell::new(Vec::new()),
             content: RefCell::new(None),
         }
     }
@@ -54,12 +78,29 @@ impl ObjectImpl for CircProgPriv {
             }
             "thickness" => {
                 self.thickness.replace(value.get().unwrap());
+                self.obj().queue_draw();
             }
             "start-at" => {
                 self.start_at.replace(value.get().unwrap());
+                self.obj().queue_draw();
             }
             "clockwise" => {
                 self.clockwise.replace(value.get().unwrap());
+                self.obj().queue_draw();
+            }
+            "color-stops" => {
+                let color_stops: String = value.get().unwrap();
+                let parsed_color_stops = parse_color_stops(&color_stops).unwrap_or_else(|err| {
+                    error_handling_ctx::print_error(err);
+                    Vec::new()
+                });
+                self.parsed_color_stops.replace(parsed_color_stops);
+                self.color_stops.replace(color_stops);
+                self.obj().queue_draw();
+            }
+            "gradient" => {
+                self.gradient.replace(value.get().unwrap());
+                self.obj().queue_draw();
             }
             x => panic!("Tried to set inexistant property of CircProg: {}", x,),
         }
@@ -159,6 +200,8 @@ impl WidgetImpl for CircProgPriv {
             let start_at = *self.start_at.borrow();
             let thickness = *self.thickness.borrow();
             let clockwise = *self.clockwise.borrow();
+            let color_stops = self.parsed_color_stops.borrow();
+            let gradient = *self.gradient.borrow();
 
             let styles = self.obj().style_context();
             let margin = styles.margin(gtk::StateFlags::NORMAL);
@@ -194,13 +237,33 @@ impl WidgetImpl for CircProgPriv {
             cr.fill()?;
 
             // Foreground Ring
-            cr.move_to(center.0, center.1);
-            cr.arc(center.0, center.1, outer_ring, start_angle, end_angle);
-            cr.set_source_rgba(fg_color.red(), fg_color.green(), fg_color.blue(), fg_color.alpha());
-            cr.move_to(center.0, center.1);
-            cr.arc(center.0, center.1, inner_ring, start_angle, end_angle);
-            cr.set_fill_rule(cairo::FillRule::EvenOdd); // Substract one circle from the other
-            cr.fill()?;
+            if gradient && !color_stops.is_empty() {
+                // Draw the ring in small segments, each colored by how far along the progress it is
+                let segments = (value.ceil() as usize).max(1);
+                let segment_angle = (end_angle - start_angle) / segments as f64;
+                for i in 0..segments {
+                    let from = start_angle + segment_angle * i as f64;
+                    // Overlap the segments a little, to not leave gaps between them
+                    let to = (from + segment_angle * 1.5).min(end_angle);
+                    let middle = from + segment_angle / 2.0;
+                    let progress = (if clockwise { middle } else { perc_to_rad(100.0) - middle }) / perc_to_rad(100.0);
+                    let color = color::interpolate_color_stops(&color_stops, progress).unwrap_or(fg_color);
+                    cr.arc(center.0, center.1, outer_ring, from, to);
+                    cr.arc_negative(center.0, center.1, inner_ring, to, from);
+                    cr.close_path();
+                    cr.set_source_rgba(color.red(), color.green(), color.blue(), color.alpha());
+                    cr.fill()?;
+                }
+            } else {
+                let fg_color = color::interpolate_color_stops(&color_stops, value / 100.0).unwrap_or(fg_color);
+                cr.move_to(center.0, center.1);
+                cr.arc(center.0, center.1, outer_ring, start_angle, end_angle);
+                cr.set_source_rgba(fg_color.red(), fg_color.green(), fg_color.blue(), fg_color.alpha());
+                cr.move_to(center.0, center.1);
+                cr.arc(center.0, center.1, inner_ring, start_angle, end_angle);
+                cr.set_fill_rule(cairo::FillRule::EvenOdd); // Substract one circle from the other
+                cr.fill()?;
+            }
             cr.restore()?;
 
             // Draw the children widget, clipping it to the inside
@@ -229,6 +292,14 @@ impl WidgetImpl for CircProgPriv {
     }
 }
 
+/// Parse the json array of colors given as `color-stops`.
+fn parse_color_stops(color_stops: &str) -> Result<Vec<gdk::RGBA>> {
+    if color_stops.is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str::<Vec<String>>(color_stops)?.iter().map(|color| color::parse_color(color)).collect()
+}
+
 fn perc_to_rad(n: f64) -> f64 {
     (n / 100f64) * 2f64 * std::f64::consts::PI
 }
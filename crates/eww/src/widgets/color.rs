@@ -0,0 +1,133 @@
+use anyhow::{anyhow, Result};
+use gtk::gdk;
+
+use crate::regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFormat {
+    Hex,
+    Rgb,
+    Rgba,
+    Hsl,
+}
+
+/// Parse a css color. On top of what gdk supports (names, `#rrggbb`, `rgb()` and `rgba()`), this also accepts `hsl()` and `hsla()`.
+pub fn parse_color(color: &str) -> Result<gdk::RGBA> {
+    let hsl_pattern = regex!(r"^\s*hsla?\(\s*([\d.]+)(?:deg)?\s*,\s*([\d.]+)%\s*,\s*([\d.]+)%\s*(?:,\s*([\d.]+)\s*)?\)\s*$");
+    if let Some(captures) = hsl_pattern.captures(color) {
+        let component = |i: usize| captures.get(i).map(|x| x.as_str().parse::<f64>()).transpose();
+        let (h, s, l) = (component(1)?.unwrap_or_default(), component(2)?.unwrap_or_default(), component(3)?.unwrap_or_default());
+        let alpha = component(4)?.unwrap_or(1.0);
+        let (r, g, b) = hsl_to_rgb(h.rem_euclid(360.0), (s / 100.0).clamp(0.0, 1.0), (l / 100.0).clamp(0.0, 1.0));
+        return Ok(gdk::RGBA::new(r, g, b, alpha.clamp(0.0, 1.0)));
+    }
+    gdk::RGBA::parse(color).map_err(|_| anyhow!("Invalid color: '{}'", color))
+}
+
+pub fn format_color(color: &gdk::RGBA, format: ColorFormat) -> String {
+    let to_byte = |x: f64| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let (r, g, b) = (to_byte(color.red()), to_byte(color.green()), to_byte(color.blue()));
+    match format {
+        ColorFormat::Hex if color.alpha() < 1.0 => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, to_byte(color.alpha())),
+        ColorFormat::Hex => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        ColorFormat::Rgb => format!("rgb({},{},{})", r, g, b),
+        ColorFormat::Rgba => format!("rgba({},{},{},{})", r, g, b, color.alpha()),
+        ColorFormat::Hsl => {
+            let (h, s, l) = rgb_to_hsl(color.red(), color.green(), color.blue());
+            let (h, s, l) = (h.round(), (s * 100.0).round(), (l * 100.0).round());
+            if color.alpha() < 1.0 {
+                format!("hsla({},{}%,{}%,{})", h, s, l, color.alpha())
+            } else {
+                format!("hsl({},{}%,{}%)", h, s, l)
+            }
+        }
+    }
+}
+
+/// Get the color at the given position (between 0 and 1) of a gradient through the given, evenly spaced colors.
+pub fn interpolate_color_stops(stops: &[gdk::RGBA], position: f64) -> Option<gdk::RGBA> {
+    let (first, last) = (stops.first()?, stops.last()?);
+    if stops.len() == 1 || position <= 0.0 {
+        return Some(*first);
+    } else if position >= 1.0 {
+        return Some(*last);
+    }
+    let scaled = position * (stops.len() - 1) as f64;
+    let index = scaled.floor() as usize;
+    let (from, to) = (stops[index], stops[(index + 1).min(stops.len() - 1)]);
+    let t = scaled - index as f64;
+    let mix = |a: f64, b: f64| a + (b - a) * t;
+    Some(gdk::RGBA::new(
+        mix(from.red(), to.red()),
+        mix(from.green(), to.green()),
+        mix(from.blue(), to.blue()),
+        mix(from.alpha(), to.alpha()),
+    ))
+}
+
+fn hsl_to_rgb(h: f64, s: f64, l: f64) -> (f64, f64, f64) {
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = chroma * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match h {
+        h if h < 60.0 => (chroma, x, 0.0),
+        h if h < 120.0 => (x, chroma, 0.0),
+        h if h < 180.0 => (0.0, chroma, x),
+        h if h < 240.0 => (0.0, x, chroma),
+        h if h < 300.0 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = l - chroma / 2.0;
+    (r + m, g + m, b + m)
+}
+
+fn rgb_to_hsl(r: f64, g: f64, b: f64) -> (f64, f64, f64) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let chroma = max - min;
+    if chroma == 0.0 {
+        return (0.0, 0.0, l);
+    }
+    let s = chroma / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        60.0 * ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+    (h, s, l)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_color() {
+        let color = gdk::RGBA::new(1.0, 0.0, 0.0, 1.0);
+        assert_eq!("#ff0000", format_color(&color, ColorFormat::Hex));
+        assert_eq!("rgb(255,0,0)", format_color(&color, ColorFormat::Rgb));
+        assert_eq!("rgba(255,0,0,1)", format_color(&color, ColorFormat::Rgba));
+        assert_eq!("hsl(0,100%,50%)", format_color(&color, ColorFormat::Hsl));
+        assert_eq!("#00ff0080", format_color(&gdk::RGBA::new(0.0, 1.0, 0.0, 0.5), ColorFormat::Hex));
+    }
+
+    #[test]
+    fn test_interpolate_color_stops() {
+        let stops = [parse_color("#00ff00").unwrap(), parse_color("#ffff00").unwrap(), parse_color("#ff0000").unwrap()];
+        let at = |position| format_color(&interpolate_color_stops(&stops, position).unwrap(), ColorFormat::Hex);
+        assert_eq!("#00ff00", at(0.0));
+        assert_eq!("#80ff00", at(0.25));
+        assert_eq!("#ffff00", at(0.5));
+        assert_eq!("#ff0000", at(1.0));
+        assert_eq!("#ff0000", at(1.5));
+        assert!(interpolate_color_stops(&[], 0.5).is_none());
+    }
+
+    #[test]
+    fn test_parse_hsl() {
+        assert_eq!("#0000ff", format_color(&parse_color("hsl(240, 100%, 50%)").unwrap(), ColorFormat::Hex));
+        assert_eq!("rgba(0,255,0,0.5)", format_color(&parse_color("hsla(120deg, 100%, 50%, 0.5)").unwrap(), ColorFormat::Rgba));
+    }
+}
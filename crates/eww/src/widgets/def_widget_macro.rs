@@ -11,7 +11,7 @@ macro_rules! def_widget {
             $(
                 // explicitly box the function to not cause tons of monomorphization related duplications of Vec::retain
                 let retain_fn: Box<dyn Fn(&eww_shared_util::wrappers::AttrName, &mut yuck::config::attributes::AttrEntry) -> bool> =
-                    Box::new(|a, _| &a.0 != &::std::stringify!($attr_name).replace('_', "-"));
+                    Box::new(|a, _| &a.0 != &def_widget!(@attr_name $attr_name));
                 $args.unhandled_attrs.retain(retain_fn);
             )*
 
@@ -23,7 +23,7 @@ macro_rules! def_widget {
                 Ok(::maplit::hashmap! {
                     $(
                         eww_shared_util::AttrName(::std::stringify!($attr_name).to_owned()) =>
-                            def_widget!(@get_value $args, &::std::stringify!($attr_name).replace('_', "-"), $(? $($optional)?)? $(= $default)?)
+                            def_widget!(@get_value $args, &def_widget!(@attr_name $attr_name), $(? $($optional)?)? $(= $default)?)
                     ),*
                 })
             })();
@@ -85,6 +85,11 @@ macro_rules! def_widget {
         })+
     };
 
+    // The name of the attribute in yuck. Raw identifiers allow using keywords such as `loop` as attribute names.
+    (@attr_name $attr_name:ident) => {
+        ::std::stringify!($attr_name).trim_start_matches("r#").replace('_', "-")
+    };
+
     (@unwrap_if_required $value:ident ?) => { };
     (@unwrap_if_required $value:ident) => {
         let $value = $value.unwrap();
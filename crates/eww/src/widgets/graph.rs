@@ -7,6 +7,9 @@ use gtk::{cairo, gdk, prelude::*, subclass::prelude::*};
 
 use crate::error_handling_ctx;
 
+/// How often a continuously scrolling graph is redrawn while no new values arrive
+const SCROLL_REDRAW_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
 // This widget shouldn't be a Bin/Container but I've not been
 //  able to subclass just a gtk::Widget
 wrapper! {
@@ -44,10 +47,39 @@ pub struct GraphPriv {
     flip_y: RefCell<bool>,
     #[property(get, set, nick = "Vertical", blurb = "Exchange the x and y axes", default = false)]
     vertical: RefCell<bool>,
-
-    history: RefCell<VecDeque<(std::time::Instant, f64)>>,
-    extra_point: RefCell<Option<(std::time::Instant, f64)>>,
+    #[property(get, set, nick = "Series", blurb = "JSON array of values, one per line", default = "")]
+    series: RefCell<String>,
+    #[property(get, set, nick = "Colors", blurb = "JSON array of colors, one per line", default = "")]
+    colors: RefCell<String>,
+
+    #[property(get, set, nick = "Continuous", blurb = "Keep scrolling while no new values arrive", default = false)]
+    continuous: RefCell<bool>,
+
+    #[property(get, set, nick = "Log Scale", blurb = "Map values logarithmically", default = false)]
+    log_scale: RefCell<bool>,
+
+    #[property(get, set, nick = "Fill", blurb = "Fill the area between each line and the baseline", default = false)]
+    fill: RefCell<bool>,
+    #[property(get, set, nick = "Fill Color", blurb = "Color of the area below the lines", default = "")]
+    fill_color: RefCell<String>,
+    #[property(
+        get,
+        set,
+        nick = "Fill Gradient",
+        blurb = "JSON array of colors the fill goes through, from the top down to the baseline",
+        default = ""
+    )]
+    fill_gradient: RefCell<String>,
+    #[property(get, set, nick = "Baseline", blurb = "The value the fill extends to", minimum = f64::MIN, maximum = f64::MAX, default = 0f64)]
+    baseline: RefCell<f64>,
+
+    // Every entry holds one value per line in the graph
+    history: RefCell<VecDeque<(std::time::Instant, Vec<f64>)>>,
+    extra_point: RefCell<Option<(std::time::Instant, Vec<f64>)>>,
+    line_colors: RefCell<Vec<gdk::RGBA>>,
+    fill_stops: RefCell<Vec<gdk::RGBA>>,
     last_updated_at: RefCell<std::time::Instant>,
+    scroll_timer: RefCell<Option<glib::SourceId>>,
 }
 
 impl Default for GraphPriv {
@@ -63,16 +95,27 @@ impl Default for GraphPriv {
             flip_x: RefCell::new(true),
             flip_y: RefCell::new(true),
             vertical: RefCell::new(false),
+            series: RefCell::new(String::new()),
+            colors: RefCell::new(String::new()),
+            continuous: RefCell::new(false),
+            log_scale: RefCell::new(false),
+            fill: RefCell::new(false),
+            fill_color: RefCell::new(String::new()),
+            fill_gradient: RefCell::new(String::new()),
+            baseline: RefCell::new(0.0),
             history: RefCell::new(VecDeque::new()),
             extra_point: RefCell::new(None),
+            line_colors: RefCell::new(Vec::new()),
+            fill_stops: RefCell::new(Vec::new()),
             last_updated_at: RefCell::new(std::time::Instant::now()),
+            scroll_timer: RefCell::new(None),
         }
     }
 }
 
 impl GraphPriv {
     // Updates the history, removing points ouside the range
-    fn update_history(&self, v: (std::time::Instant, f64)) {
+    fn update_history(&self, v: (std::time::Instant, Vec<f64>)) {
         let mut history = self.history.borrow_mut();
         let mut last_value = self.extra_point.borrow_mut();
         let mut last_updated_at = self.last_updated_at.borrow_mut();
@@ -87,6 +130,25 @@ impl GraphPriv {
         }
         history.push_back(v);
     }
+
+    // Starts or stops the timer that keeps redrawing the graph while no new values arrive
+    fn update_scroll_timer(&self) {
+        let mut scroll_timer = self.scroll_timer.borrow_mut();
+        if *self.continuous.borrow() {
+            if scroll_timer.is_none() {
+                let graph = self.obj().downgrade();
+                *scroll_timer = Some(glib::timeout_add_local(SCROLL_REDRAW_INTERVAL, move || match graph.upgrade() {
+                    Some(graph) => {
+                        graph.queue_draw();
+                        glib::ControlFlow::Continue
+                    }
+                    None => glib::ControlFlow::Break,
+                }));
+            }
+        } else if let Some(timer) = scroll_timer.take() {
+            timer.remove();
+        }
+    }
     /**
      * Receives normalized (0-1) coordinates `x` and `y` and convert them to the
      * point on the widget.
@@ -109,7 +171,30 @@ impl ObjectImpl for GraphPriv {
             "value" => {
                 let value = value.get().unwrap();
                 self.value.replace(value);
-                self.update_history((std::time::Instant::now(), value));
+                self.update_history((std::time::Instant::now(), vec![value]));
+                self.obj().queue_draw();
+            }
+            "series" => {
+                let series: String = value.get().unwrap();
+                match serde_json::from_str::<Vec<f64>>(&series) {
+                    Ok(values) => self.update_history((std::time::Instant::now(), values)),
+                    Err(err) => error_handling_ctx::print_error(anyhow!("Invalid graph series `{}`: {}", series, err)),
+                }
+                self.series.replace(series);
+                self.obj().queue_draw();
+            }
+            "colors" => {
+                let colors: String = value.get().unwrap();
+                let line_colors = serde_json::from_str::<Vec<String>>(&colors)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|colors| colors.iter().map(|color| super::color::parse_color(color)).collect::<Result<Vec<_>>>());
+                match line_colors {
+                    Ok(line_colors) => {
+                        self.line_colors.replace(line_colors);
+                    }
+                    Err(err) => error_handling_ctx::print_error(anyhow!("Invalid graph colors `{}`: {}", colors, err)),
+                }
+                self.colors.replace(colors);
                 self.obj().queue_draw();
             }
             "thickness" => {
@@ -139,6 +224,46 @@ impl ObjectImpl for GraphPriv {
             "vertical" => {
                 self.vertical.replace(value.get().unwrap());
             }
+            "continuous" => {
+                self.continuous.replace(value.get().unwrap());
+                self.update_scroll_timer();
+                self.obj().queue_draw();
+            }
+            "log-scale" => {
+                self.log_scale.replace(value.get().unwrap());
+                self.obj().queue_draw();
+            }
+            "fill" => {
+                self.fill.replace(value.get().unwrap());
+                self.obj().queue_draw();
+            }
+            "fill-color" | "fill-gradient" => {
+                if pspec.name() == "fill-color" {
+                    self.fill_color.replace(value.get().unwrap());
+                } else {
+                    self.fill_gradient.replace(value.get().unwrap());
+                }
+                // A gradient takes precedence over a single color
+                let fill_stops: Result<Vec<gdk::RGBA>> =
+                    match (self.fill_gradient.borrow().as_str(), self.fill_color.borrow().as_str()) {
+                        ("", "") => Ok(Vec::new()),
+                        ("", color) => super::color::parse_color(color).map(|color| vec![color]),
+                        (gradient, _) => serde_json::from_str::<Vec<String>>(gradient)
+                            .map_err(anyhow::Error::from)
+                            .and_then(|colors| colors.iter().map(|color| super::color::parse_color(color)).collect()),
+                    };
+                match fill_stops {
+                    Ok(fill_stops) => {
+                        self.fill_stops.replace(fill_stops);
+                    }
+                    Err(err) => error_handling_ctx::print_error(anyhow!("Invalid graph fill: {}", err)),
+                }
+                self.obj().queue_draw();
+            }
+            "baseline" => {
+                self.baseline.replace(value.get().unwrap());
+                self.obj().queue_draw();
+            }
             x => panic!("Tried to set inexistant property of Graph: {}", x,),
         }
     }
@@ -146,6 +271,12 @@ impl ObjectImpl for GraphPriv {
     fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
         self.derived_property(id, pspec)
     }
+
+    fn dispose(&self) {
+        if let Some(timer) = self.scroll_timer.take() {
+            timer.remove();
+        }
+    }
 }
 
 #[object_subclass]
@@ -201,7 +332,8 @@ impl WidgetImpl for GraphPriv {
     fn draw(&self, cr: &cairo::Context) -> glib::Propagation {
         let res: Result<()> = (|| {
             let history = &*self.history.borrow();
-            let extra_point = *self.extra_point.borrow();
+            let extra_point = &*self.extra_point.borrow();
+            let line_count = history.iter().chain(extra_point).map(|(_, values)| values.len()).max().unwrap_or(0);
 
             // Calculate the max value
             let (min, max) = {
@@ -209,21 +341,23 @@ impl WidgetImpl for GraphPriv {
                 let min = *self.min.borrow();
                 let dynamic = *self.dynamic.borrow();
                 if dynamic {
-                    // Check for points higher than max
-                    for (_, value) in history {
-                        if *value > max {
-                            max = *value;
-                        }
-                    }
-                    if let Some((_, value)) = extra_point {
-                        if value > max {
-                            max = value;
+                    // Check for points higher than max, in any of the lines
+                    for (_, values) in history.iter().chain(extra_point) {
+                        for value in values {
+                            if *value > max {
+                                max = *value;
+                            }
                         }
                     }
                 }
                 (min, max)
             };
 
+            // In log scale, values are mapped to log10(value + 1), so that 0 stays at the bottom. Negative values are treated as 0.
+            let log_scale = *self.log_scale.borrow();
+            let scale = |value: f64| if log_scale { (value.max(0.0) + 1.0).log10() } else { value };
+            let (min, max) = (scale(min), scale(max));
+
             let styles = self.obj().style_context();
             let (margin_top, margin_right, margin_bottom, margin_left) = {
                 let margin = styles.margin(gtk::StateFlags::NORMAL);
@@ -232,29 +366,48 @@ impl WidgetImpl for GraphPriv {
             let width = self.obj().allocated_width() as f64 - margin_left - margin_right;
             let height = self.obj().allocated_height() as f64 - margin_top - margin_bottom;
 
-            // Calculate graph points once
+            // Calculate graph points once, for every line
             //  Separating this into another function would require pasing a
             //  GraphPriv that would hide interior mutability
-            let points = {
-                let value_range = max - min;
-                let time_range = *self.time_range.borrow() as f64;
-                let last_updated_at = self.last_updated_at.borrow();
-                let mut points = history
-                    .iter()
-                    .map(|(instant, value)| {
+            let lines = (0..line_count)
+                .map(|line| {
+                    let value_range = max - min;
+                    let time_range = *self.time_range.borrow() as f64;
+                    // When scrolling continuously, the graph moves with the current time instead of the last update
+                    let last_updated_at =
+                        if *self.continuous.borrow() { std::time::Instant::now() } else { *self.last_updated_at.borrow() };
+                    let mut points = history
+                        .iter()
+                        .filter_map(|(instant, values)| Some((instant, values.get(line)?)))
+                        .map(|(instant, value)| {
+                            let t = last_updated_at.duration_since(*instant).as_millis() as f64;
+                            self.value_to_point(width, height, t / time_range, (scale(*value) - min) / value_range)
+                        })
+                        .collect::<VecDeque<(f64, f64)>>();
+
+                    // Hold the last value up until now
+                    if *self.continuous.borrow() {
+                        if let Some(value) = history.back().and_then(|(_, values)| values.get(line)) {
+                            points.push_back(self.value_to_point(width, height, 0.0, (scale(*value) - min) / value_range));
+                        }
+                    }
+
+                    // Aad an extra point outside of the graph to extend the line to the left
+                    if let Some((instant, value)) =
+                        extra_point.as_ref().and_then(|(instant, values)| Some((instant, values.get(line)?)))
+                    {
                         let t = last_updated_at.duration_since(*instant).as_millis() as f64;
-                        self.value_to_point(width, height, t / time_range, (value - min) / value_range)
-                    })
-                    .collect::<VecDeque<(f64, f64)>>();
-
-                // Aad an extra point outside of the graph to extend the line to the left
-                if let Some((instant, value)) = extra_point {
-                    let t = last_updated_at.duration_since(instant).as_millis() as f64;
-                    let (x, y) = self.value_to_point(width, height, (t - time_range) / time_range, (value - min) / value_range);
-                    points.push_front(if *self.vertical.borrow() { (x, -y) } else { (-x, y) });
-                }
-                points
-            };
+                        let (x, y) = self.value_to_point(
+                            width,
+                            height,
+                            (t - time_range) / time_range,
+                            (scale(*value) - min) / value_range,
+                        );
+                        points.push_front(if *self.vertical.borrow() { (x, -y) } else { (-x, y) });
+                    }
+                    points
+                })
+                .collect::<Vec<_>>();
 
             // Actually draw the graph
             cr.save()?;
@@ -262,9 +415,9 @@ impl WidgetImpl for GraphPriv {
             cr.rectangle(0.0, 0.0, width, height);
             cr.clip();
 
-            // Draw Background
+            // Draw Background, only below the first line
             let bg_color: gdk::RGBA = styles.style_property_for_state("background-color", gtk::StateFlags::NORMAL).get()?;
-            if bg_color.alpha() > 0.0 {
+            if let Some(points) = lines.first().filter(|_| bg_color.alpha() > 0.0) {
                 if let Some(first_point) = points.front() {
                     cr.line_to(first_point.0, height + margin_bottom);
                 }
@@ -277,19 +430,65 @@ impl WidgetImpl for GraphPriv {
                 cr.fill()?;
             }
 
-            // Draw Line
-            let line_color: gdk::RGBA = styles.color(gtk::StateFlags::NORMAL);
-            let thickness = *self.thickness.borrow();
-            if line_color.alpha() > 0.0 && thickness > 0.0 {
-                for (x, y) in points.iter() {
-                    cr.line_to(*x, *y);
+            let fg_color: gdk::RGBA = styles.color(gtk::StateFlags::NORMAL);
+            let line_colors = self.line_colors.borrow();
+
+            // Fill the area between every line and the baseline, before the lines are drawn on top of it
+            if *self.fill.borrow() {
+                let value_range = max - min;
+                let baseline = self.value_to_point(width, height, 0.0, (scale(*self.baseline.borrow()) - min) / value_range);
+                let top = self.value_to_point(width, height, 0.0, 1.0);
+                let vertical = *self.vertical.borrow();
+                // Moves a point onto the baseline, along the value axis
+                let to_baseline = |(x, y): (f64, f64)| if vertical { (baseline.0, y) } else { (x, baseline.1) };
+                let fill_stops = self.fill_stops.borrow();
+                for (line, points) in lines.iter().enumerate() {
+                    let (Some(first_point), Some(last_point)) = (points.front(), points.back()) else { continue };
+                    let start = to_baseline(*first_point);
+                    cr.move_to(start.0, start.1);
+                    for (x, y) in points.iter() {
+                        cr.line_to(*x, *y);
+                    }
+                    let end = to_baseline(*last_point);
+                    cr.line_to(end.0, end.1);
+                    cr.close_path();
+
+                    match fill_stops.as_slice() {
+                        [] => {
+                            // Without a fill color, use a translucent version of the line's color
+                            let line_color = line_colors.get(line).unwrap_or(&fg_color);
+                            cr.set_source_rgba(line_color.red(), line_color.green(), line_color.blue(), line_color.alpha() * 0.3);
+                        }
+                        [color] => cr.set_source_rgba(color.red(), color.green(), color.blue(), color.alpha()),
+                        stops => {
+                            // Both points are at the same time, so the gradient runs along the value axis
+                            let gradient = cairo::LinearGradient::new(top.0, top.1, baseline.0, baseline.1);
+                            for (i, color) in stops.iter().enumerate() {
+                                let offset = i as f64 / (stops.len() - 1) as f64;
+                                gradient.add_color_stop_rgba(offset, color.red(), color.green(), color.blue(), color.alpha());
+                            }
+                            cr.set_source(&gradient)?;
+                        }
+                    }
+                    cr.fill()?;
                 }
+            }
+
+            // Draw Lines, using the css color for lines that have no color of their own
+            let thickness = *self.thickness.borrow();
+            for (line, points) in lines.iter().enumerate() {
+                let line_color = line_colors.get(line).unwrap_or(&fg_color);
+                if line_color.alpha() > 0.0 && thickness > 0.0 {
+                    for (x, y) in points.iter() {
+                        cr.line_to(*x, *y);
+                    }
 
-                let line_style = &*self.line_style.borrow();
-                apply_line_style(line_style.as_str(), cr)?;
-                cr.set_line_width(thickness);
-                cr.set_source_rgba(line_color.red(), line_color.green(), line_color.blue(), line_color.alpha());
-                cr.stroke()?;
+                    let line_style = &*self.line_style.borrow();
+                    apply_line_style(line_style.as_str(), cr)?;
+                    cr.set_line_width(thickness);
+                    cr.set_source_rgba(line_color.red(), line_color.green(), line_color.blue(), line_color.alpha());
+                    cr.stroke()?;
+                }
             }
 
             cr.reset_clip();
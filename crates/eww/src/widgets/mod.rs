@@ -1,11 +1,12 @@
-use std::process::Command;
-
 pub mod build_widget;
 pub mod circular_progressbar;
+pub mod color;
 pub mod def_widget_macro;
 pub mod graph;
 mod systray;
+pub mod ticker;
 pub mod transform;
+pub mod weighted_box;
 pub mod widget_definitions;
 pub mod window;
 
@@ -13,7 +14,7 @@ pub mod window;
 /// This command may use placeholders which will be replaced by the values of the arguments given.
 /// This can either be the placeholder `{}`, which will be replaced by the first argument,
 /// Or a placeholder like `{0}`, `{1}`, etc, which will refer to the respective argument.
-fn run_command<T>(timeout: std::time::Duration, cmd: &str, args: &[T])
+pub(crate) fn run_command<T>(timeout: std::time::Duration, cmd: &str, args: &[T])
 where
     T: 'static + std::fmt::Display + Send + Sync + Clone,
 {
@@ -23,24 +24,101 @@ where
         .name("command-execution-thread".to_string())
         .spawn(move || {
             log::debug!("Running command from widget [timeout: {}ms]: {}", timeout.as_millis(), cmd);
-            let child = Command::new("/bin/sh").arg("-c").arg(&cmd).spawn();
+            let child = crate::util::shell_command(&cmd).spawn();
             match child {
                 Ok(mut child) => match child.wait_timeout(timeout) {
                     // child timed out
                     Ok(None) => {
-                        log::error!("WARNING: command {} timed out", &cmd);
+                        crate::error_handling_ctx::print_warning(anyhow::anyhow!("command {} timed out", &cmd));
                         let _ = child.kill();
                         let _ = child.wait();
                     }
-                    Err(err) => log::error!("Failed to execute command {}: {}", cmd, err),
+                    Err(err) => crate::error_handling_ctx::print_error(
+                        anyhow::Error::new(err).context(format!("Failed to execute command {}", cmd)),
+                    ),
                     Ok(Some(_)) => {}
                 },
-                Err(err) => log::error!("Failed to launch child process: {}", err),
+                Err(err) => {
+                    crate::error_handling_ctx::print_error(anyhow::Error::new(err).context("Failed to launch child process"))
+                }
+            }
+        })
+        .expect("Failed to start command-execution-thread");
+}
+
+/// Run a command in the background without a timeout, such as one that launches an application.
+/// The command is still waited for on a separate thread, so it doesn't linger as a zombie process once it exits.
+pub(crate) fn run_command_detached(cmd: &str) {
+    let cmd = cmd.to_string();
+    std::thread::Builder::new()
+        .name("command-execution-thread".to_string())
+        .spawn(move || {
+            log::debug!("Running detached command: {}", cmd);
+            match crate::util::shell_command(&cmd).spawn() {
+                Ok(mut child) => {
+                    if let Err(err) = child.wait() {
+                        crate::error_handling_ctx::print_error(
+                            anyhow::Error::new(err).context(format!("Failed to execute command {}", cmd)),
+                        )
+                    }
+                }
+                Err(err) => {
+                    crate::error_handling_ctx::print_error(anyhow::Error::new(err).context("Failed to launch child process"))
+                }
             }
         })
         .expect("Failed to start command-execution-thread");
 }
 
+/// Limits how often something runs, for `:throttle`. Calls within the interval after the last run are collapsed into a
+/// single one, which runs once the interval has passed, such that the latest call is never lost.
+#[derive(Clone, Default)]
+pub(crate) struct Throttle(std::rc::Rc<std::cell::RefCell<ThrottleState>>);
+
+#[derive(Default)]
+struct ThrottleState {
+    last_run: Option<std::time::Instant>,
+    pending: Option<(Box<dyn FnOnce()>, gtk::glib::SourceId)>,
+}
+
+impl Throttle {
+    pub fn run(&self, interval: std::time::Duration, f: impl FnOnce() + 'static) {
+        let mut state = self.0.borrow_mut();
+        if let Some((_, timer)) = state.pending.take() {
+            timer.remove();
+        }
+        match state.last_run.map(|last_run| last_run.elapsed()) {
+            Some(elapsed) if elapsed < interval => {
+                let throttle = self.clone();
+                let timer = gtk::glib::timeout_add_local_once(interval - elapsed, move || throttle.run_pending(false));
+                state.pending = Some((Box::new(f), timer));
+            }
+            _ => {
+                state.last_run = Some(std::time::Instant::now());
+                drop(state);
+                f();
+            }
+        }
+    }
+
+    /// Run the pending call right away, if there is one.
+    pub fn flush(&self) {
+        self.run_pending(true);
+    }
+
+    fn run_pending(&self, remove_timer: bool) {
+        let pending = self.0.borrow_mut().pending.take();
+        if let Some((f, timer)) = pending {
+            // Timers that already fired must not be removed again
+            if remove_timer {
+                timer.remove();
+            }
+            self.0.borrow_mut().last_run = Some(std::time::Instant::now());
+            f();
+        }
+    }
+}
+
 fn replace_placeholders<T>(cmd: &str, args: &[T]) -> String
 where
     T: 'static + std::fmt::Display + Send + Sync + Clone,
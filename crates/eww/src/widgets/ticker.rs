@@ -0,0 +1,223 @@
+use anyhow::{anyhow, Result};
+use gtk::glib::{self, object_subclass, wrapper, Properties};
+use gtk::{cairo, prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+
+use crate::error_handling_ctx;
+
+wrapper! {
+    pub struct Ticker(ObjectSubclass<TickerPriv>)
+    @extends gtk::Bin, gtk::Container, gtk::Widget;
+}
+
+#[derive(Properties)]
+#[properties(wrapper_type = Ticker)]
+pub struct TickerPriv {
+    #[property(get, set, nick = "Speed", blurb = "Scrolling speed in pixels per second", minimum = 0f64, maximum = f64::MAX, default = 30f64)]
+    speed: RefCell<f64>,
+
+    #[property(get, set, nick = "Gap", blurb = "Space between the end of the content and its next repetition", minimum = 0f64, maximum = f64::MAX, default = 32f64)]
+    gap: RefCell<f64>,
+
+    #[property(get, set, nick = "Pause on hover", blurb = "Whether to stop scrolling while hovered", default = true)]
+    pause_on_hover: RefCell<bool>,
+
+    content: RefCell<Option<gtk::Widget>>,
+
+    /// How far the content has scrolled, in pixels
+    offset: RefCell<f64>,
+    /// Frame time of the last tick, in microseconds
+    last_frame_time: RefCell<Option<i64>>,
+    tick_callback: RefCell<Option<gtk::TickCallbackId>>,
+}
+
+// This should match the default values from the ParamSpecs
+impl Default for TickerPriv {
+    fn default() -> Self {
+        TickerPriv {
+            speed: RefCell::new(30.0),
+            gap: RefCell::new(32.0),
+            pause_on_hover: RefCell::new(true),
+            content: RefCell::new(None),
+            offset: RefCell::new(0.0),
+            last_frame_time: RefCell::new(None),
+            tick_callback: RefCell::new(None),
+        }
+    }
+}
+
+impl ObjectImpl for TickerPriv {
+    fn constructed(&self) {
+        self.parent_constructed();
+        let id = self.obj().add_tick_callback(|ticker, clock| {
+            ticker.imp().tick(clock.frame_time());
+            glib::ControlFlow::Continue
+        });
+        self.tick_callback.replace(Some(id));
+    }
+
+    fn dispose(&self) {
+        if let Some(id) = self.tick_callback.take() {
+            id.remove();
+        }
+    }
+
+    fn properties() -> &'static [glib::ParamSpec] {
+        Self::derived_properties()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "speed" => {
+                self.speed.replace(value.get().unwrap());
+            }
+            "gap" => {
+                self.gap.replace(value.get().unwrap());
+                self.obj().queue_draw();
+            }
+            "pause-on-hover" => {
+                self.pause_on_hover.replace(value.get().unwrap());
+            }
+            x => panic!("Tried to set inexistant property of Ticker: {}", x,),
+        }
+    }
+
+    fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        self.derived_property(id, pspec)
+    }
+}
+
+#[object_subclass]
+impl ObjectSubclass for TickerPriv {
+    type ParentType = gtk::Bin;
+    type Type = Ticker;
+
+    const NAME: &'static str = "Ticker";
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_css_name("ticker");
+    }
+}
+
+impl Default for Ticker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ticker {
+    pub fn new() -> Self {
+        glib::Object::new::<Self>()
+    }
+}
+
+impl TickerPriv {
+    /// Advance the content by the time that passed since the last frame, unless paused.
+    fn tick(&self, frame_time: i64) {
+        let elapsed = self.last_frame_time.replace(Some(frame_time)).map_or(0, |last| frame_time - last);
+        if *self.pause_on_hover.borrow() && self.is_hovered() {
+            return;
+        }
+        let content = self.content.borrow();
+        let Some(child) = content.as_ref() else { return };
+        let period = child.allocated_width() as f64 + *self.gap.borrow();
+        let mut offset = self.offset.borrow_mut();
+        *offset = (*offset + *self.speed.borrow() * elapsed as f64 / 1_000_000.0) % period.max(1.0);
+        self.obj().queue_draw();
+    }
+
+    /// Whether the pointer is within the widget. As the ticker doesn't have its own window, it doesn't get crossing events.
+    fn is_hovered(&self) -> bool {
+        let obj = self.obj();
+        let (Some(window), Some(pointer)) = (obj.window(), obj.display().default_seat().and_then(|seat| seat.pointer())) else {
+            return false;
+        };
+        let (_, x, y, _) = window.device_position(&pointer);
+        let allocation = obj.allocation();
+        x >= allocation.x()
+            && x < allocation.x() + allocation.width()
+            && y >= allocation.y()
+            && y < allocation.y() + allocation.height()
+    }
+}
+
+impl ContainerImpl for TickerPriv {
+    fn add(&self, widget: &gtk::Widget) {
+        if let Some(content) = &*self.content.borrow() {
+            // TODO: Handle this error when populating children widgets instead
+            error_handling_ctx::print_error(anyhow!("Error, trying to add multiple children to a ticker widget"));
+            self.parent_remove(content);
+        }
+        self.parent_add(widget);
+        self.content.replace(Some(widget.clone()));
+    }
+}
+
+impl BinImpl for TickerPriv {}
+impl WidgetImpl for TickerPriv {
+    // The ticker can be made as small as needed, as the content scrolls through it
+    fn preferred_width(&self) -> (i32, i32) {
+        match &*self.content.borrow() {
+            Some(child) => (0, child.preferred_width().1),
+            None => (0, 0),
+        }
+    }
+
+    fn preferred_width_for_height(&self, _height: i32) -> (i32, i32) {
+        self.preferred_width()
+    }
+
+    fn preferred_height(&self) -> (i32, i32) {
+        match &*self.content.borrow() {
+            Some(child) => child.preferred_height(),
+            None => (0, 0),
+        }
+    }
+
+    fn preferred_height_for_width(&self, _width: i32) -> (i32, i32) {
+        self.preferred_height()
+    }
+
+    fn size_allocate(&self, allocation: &gtk::Allocation) {
+        self.obj().set_allocation(allocation);
+        if let Some(child) = &*self.content.borrow() {
+            // The content always gets as much space as it wants, and is clipped to the ticker when drawing
+            let width = child.preferred_width().1.max(allocation.width());
+            child.size_allocate(&gtk::Allocation::new(allocation.x(), allocation.y(), width, allocation.height()));
+        }
+    }
+
+    fn draw(&self, cr: &cairo::Context) -> glib::Propagation {
+        let res: Result<()> = (|| {
+            let content = self.content.borrow();
+            let Some(child) = content.as_ref() else { return Ok(()) };
+            let width = self.obj().allocated_width() as f64;
+            let height = self.obj().allocated_height() as f64;
+            let period = child.allocated_width() as f64 + *self.gap.borrow();
+            let offset = *self.offset.borrow();
+
+            cr.save()?;
+            cr.rectangle(0.0, 0.0, width, height);
+            cr.clip();
+
+            // Draw the content as often as needed to fill the ticker, starting with the copy scrolling out on the left
+            let mut x = -offset;
+            while x < width {
+                cr.save()?;
+                cr.translate(x, 0.0);
+                self.obj().propagate_draw(child, cr);
+                cr.restore()?;
+                x += period.max(1.0);
+            }
+
+            cr.restore()?;
+            Ok(())
+        })();
+
+        if let Err(error) = res {
+            error_handling_ctx::print_error(error)
+        };
+
+        glib::Propagation::Proceed
+    }
+}
@@ -1,7 +1,11 @@
 use anyhow::{anyhow, Result};
 use gtk::glib::{self, object_subclass, wrapper, Properties};
 use gtk::{prelude::*, subclass::prelude::*};
-use std::{cell::RefCell, str::FromStr};
+use std::{
+    cell::RefCell,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 use yuck::value::NumWithUnit;
 
 use crate::error_handling_ctx;
@@ -35,7 +39,54 @@ pub struct TransformPriv {
     #[property(get, set, nick = "Scale y", blurb = "The amount to scale in y", default = None)]
     scale_y: RefCell<Option<String>>,
 
+    #[property(
+        get,
+        set,
+        nick = "Transition duration",
+        blurb = "Duration of the transition to new values in milliseconds",
+        default = 0
+    )]
+    transition_duration: RefCell<u64>,
+
     content: RefCell<Option<gtk::Widget>>,
+
+    /// The values used in the last draw call, which a transition starts from
+    last_drawn: RefCell<Option<TransformValues>>,
+    transition: RefCell<Option<Transition>>,
+    tick_callback: RefCell<Option<gtk::TickCallbackId>>,
+}
+
+/// The resolved transformation, with all lengths in pixels
+#[derive(Debug, Clone, Copy)]
+struct TransformValues {
+    rotate: f64,
+    origin_x: f64,
+    origin_y: f64,
+    translate_x: f64,
+    translate_y: f64,
+    scale_x: f64,
+    scale_y: f64,
+}
+
+impl TransformValues {
+    fn interpolate(&self, target: &TransformValues, t: f64) -> TransformValues {
+        let lerp = |from: f64, to: f64| from + (to - from) * t;
+        TransformValues {
+            rotate: lerp(self.rotate, target.rotate),
+            origin_x: lerp(self.origin_x, target.origin_x),
+            origin_y: lerp(self.origin_y, target.origin_y),
+            translate_x: lerp(self.translate_x, target.translate_x),
+            translate_y: lerp(self.translate_y, target.translate_y),
+            scale_x: lerp(self.scale_x, target.scale_x),
+            scale_y: lerp(self.scale_y, target.scale_y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Transition {
+    from: TransformValues,
+    start: Instant,
 }
 
 // This should match the default values from the ParamSpecs
@@ -49,7 +100,11 @@ impl Default for TransformPriv {
             translate_y: RefCell::new(None),
             scale_x: RefCell::new(None),
             scale_y: RefCell::new(None),
+            transition_duration: RefCell::new(0),
             content: RefCell::new(None),
+            last_drawn: RefCell::new(None),
+            transition: RefCell::new(None),
+            tick_callback: RefCell::new(None),
         }
     }
 }
@@ -63,32 +118,42 @@ impl ObjectImpl for TransformPriv {
         match pspec.name() {
             "rotate" => {
                 self.rotate.replace(value.get().unwrap());
+                self.start_transition();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "transform-origin-x" => {
                 self.transform_origin_x.replace(value.get().unwrap());
+                self.start_transition();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "transform-origin-y" => {
                 self.transform_origin_y.replace(value.get().unwrap());
+                self.start_transition();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "translate-x" => {
                 self.translate_x.replace(value.get().unwrap());
+                self.start_transition();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "translate-y" => {
                 self.translate_y.replace(value.get().unwrap());
+                self.start_transition();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "scale-x" => {
                 self.scale_x.replace(value.get().unwrap());
+                self.start_transition();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
             "scale-y" => {
                 self.scale_y.replace(value.get().unwrap());
+                self.start_transition();
                 self.obj().queue_draw(); // Queue a draw call with the updated value
             }
+            "transition-duration" => {
+                self.transition_duration.replace(value.get().unwrap());
+            }
             x => panic!("Tried to set inexistant property of Transform: {}", x,),
         }
     }
@@ -122,6 +187,64 @@ impl Transform {
     }
 }
 
+impl TransformPriv {
+    /// Start transitioning from the currently displayed values to the new ones, if a transition duration is set.
+    fn start_transition(&self) {
+        let Some(from) = *self.last_drawn.borrow() else { return };
+        if *self.transition_duration.borrow() == 0 {
+            return;
+        }
+        self.transition.replace(Some(Transition { from, start: Instant::now() }));
+
+        if self.tick_callback.borrow().is_none() {
+            let id = self.obj().add_tick_callback(|widget, _| {
+                widget.queue_draw();
+                let transform = widget.imp();
+                if transform.transition.borrow().is_some() {
+                    glib::ControlFlow::Continue
+                } else {
+                    transform.tick_callback.replace(None);
+                    glib::ControlFlow::Break
+                }
+            });
+            self.tick_callback.replace(Some(id));
+        }
+    }
+
+    fn target_values(&self, total_width: f64, total_height: f64) -> Result<TransformValues> {
+        let origin_x = match &*self.transform_origin_x.borrow() {
+            Some(rcx) => NumWithUnit::from_str(rcx)?.pixels_relative_to(total_width as i32) as f64,
+            None => 0.0,
+        };
+        let origin_y = match &*self.transform_origin_y.borrow() {
+            Some(rcy) => NumWithUnit::from_str(rcy)?.pixels_relative_to(total_height as i32) as f64,
+            None => 0.0,
+        };
+
+        let translate_x = match &*self.translate_x.borrow() {
+            Some(tx) => NumWithUnit::from_str(tx)?.pixels_relative_to(total_width as i32) as f64,
+            None => 0.0,
+        };
+
+        let translate_y = match &*self.translate_y.borrow() {
+            Some(ty) => NumWithUnit::from_str(ty)?.pixels_relative_to(total_height as i32) as f64,
+            None => 0.0,
+        };
+
+        let scale_x = match &*self.scale_x.borrow() {
+            Some(sx) => NumWithUnit::from_str(sx)?.perc_relative_to(total_width as i32) as f64 / 100.0,
+            None => 1.0,
+        };
+
+        let scale_y = match &*self.scale_y.borrow() {
+            Some(sy) => NumWithUnit::from_str(sy)?.perc_relative_to(total_height as i32) as f64 / 100.0,
+            None => 1.0,
+        };
+
+        Ok(TransformValues { rotate: *self.rotate.borrow(), origin_x, origin_y, translate_x, translate_y, scale_x, scale_y })
+    }
+}
+
 impl ContainerImpl for TransformPriv {
     fn add(&self, widget: &gtk::Widget) {
         if let Some(content) = &*self.content.borrow() {
@@ -138,45 +261,31 @@ impl BinImpl for TransformPriv {}
 impl WidgetImpl for TransformPriv {
     fn draw(&self, cr: &gtk::cairo::Context) -> glib::Propagation {
         let res: Result<()> = (|| {
-            let rotate = *self.rotate.borrow();
             let total_width = self.obj().allocated_width() as f64;
             let total_height = self.obj().allocated_height() as f64;
 
             cr.save()?;
 
-            let transform_origin_x = match &*self.transform_origin_x.borrow() {
-                Some(rcx) => NumWithUnit::from_str(rcx)?.pixels_relative_to(total_width as i32) as f64,
-                None => 0.0,
-            };
-            let transform_origin_y = match &*self.transform_origin_y.borrow() {
-                Some(rcy) => NumWithUnit::from_str(rcy)?.pixels_relative_to(total_height as i32) as f64,
-                None => 0.0,
-            };
-
-            let translate_x = match &*self.translate_x.borrow() {
-                Some(tx) => NumWithUnit::from_str(tx)?.pixels_relative_to(total_width as i32) as f64,
-                None => 0.0,
-            };
-
-            let translate_y = match &*self.translate_y.borrow() {
-                Some(ty) => NumWithUnit::from_str(ty)?.pixels_relative_to(total_height as i32) as f64,
-                None => 0.0,
-            };
-
-            let scale_x = match &*self.scale_x.borrow() {
-                Some(sx) => NumWithUnit::from_str(sx)?.perc_relative_to(total_width as i32) as f64 / 100.0,
-                None => 1.0,
-            };
-
-            let scale_y = match &*self.scale_y.borrow() {
-                Some(sy) => NumWithUnit::from_str(sy)?.perc_relative_to(total_height as i32) as f64 / 100.0,
-                None => 1.0,
+            let target = self.target_values(total_width, total_height)?;
+            let transition = *self.transition.borrow();
+            let values = match transition {
+                Some(Transition { from, start }) => {
+                    let duration = Duration::from_millis(*self.transition_duration.borrow());
+                    let progress = (start.elapsed().as_secs_f64() / duration.as_secs_f64()).min(1.0);
+                    if progress >= 1.0 {
+                        self.transition.replace(None);
+                    }
+                    // ease out, so the transition reacts immediately and then slows down
+                    from.interpolate(&target, 1.0 - (1.0 - progress).powi(3))
+                }
+                None => target,
             };
+            self.last_drawn.replace(Some(values));
 
-            cr.translate(transform_origin_x, transform_origin_y);
-            cr.rotate(perc_to_rad(rotate));
-            cr.translate(translate_x - transform_origin_x, translate_y - transform_origin_y);
-            cr.scale(scale_x, scale_y);
+            cr.translate(values.origin_x, values.origin_y);
+            cr.rotate(perc_to_rad(values.rotate));
+            cr.translate(values.translate_x - values.origin_x, values.translate_y - values.origin_y);
+            cr.scale(values.scale_x, values.scale_y);
 
             // Children widget
             if let Some(child) = &*self.content.borrow() {
@@ -0,0 +1,195 @@
+use gtk::glib::{self, object_subclass, wrapper};
+use gtk::{prelude::*, subclass::prelude::*};
+use std::{cell::RefCell, collections::HashMap};
+
+wrapper! {
+    pub struct WeightedBox(ObjectSubclass<WeightedBoxPriv>)
+    @extends gtk::Box, gtk::Container, gtk::Widget,
+    @implements gtk::Orientable;
+}
+
+/// A box that distributes its leftover space among its children according to their `grow` weights.
+#[derive(Default)]
+pub struct WeightedBoxPriv {
+    /// The `grow` weights of the children that have one
+    grow_weights: RefCell<HashMap<gtk::Widget, f64>>,
+}
+
+impl ObjectImpl for WeightedBoxPriv {}
+
+#[object_subclass]
+impl ObjectSubclass for WeightedBoxPriv {
+    type ParentType = gtk::Box;
+    type Type = WeightedBox;
+
+    const NAME: &'static str = "WeightedBox";
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_css_name("box");
+    }
+}
+
+impl Default for WeightedBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WeightedBox {
+    pub fn new() -> Self {
+        glib::Object::new::<Self>()
+    }
+
+    /// Set how much of the leftover space the given child gets, relative to the weights of the other children.
+    pub fn set_child_grow(&self, child: &gtk::Widget, grow: f64) {
+        self.imp().grow_weights.borrow_mut().insert(child.clone(), grow.max(0.0));
+        self.queue_resize();
+    }
+}
+
+impl ContainerImpl for WeightedBoxPriv {
+    fn remove(&self, widget: &gtk::Widget) {
+        self.grow_weights.borrow_mut().remove(widget);
+        self.parent_remove(widget);
+    }
+}
+
+impl BoxImpl for WeightedBoxPriv {}
+
+impl WidgetImpl for WeightedBoxPriv {
+    fn size_allocate(&self, allocation: &gtk::Allocation) {
+        // GtkBox also allocates its CSS box and baseline here, so it always lays out the children first
+        self.parent_size_allocate(allocation);
+        let obj = self.obj();
+        if obj.is_homogeneous() {
+            return;
+        }
+        let children = obj.children().into_iter().filter(|child| child.is_visible()).collect::<Vec<_>>();
+        let orientation = obj.orientation();
+        let weights = {
+            let grow_weights = self.grow_weights.borrow();
+            if !children.iter().any(|child| grow_weights.contains_key(child)) {
+                return;
+            }
+            // Children that expand without a `grow` weight get as much of the leftover space as a `grow` of 1
+            children
+                .iter()
+                .map(|child| {
+                    grow_weights.get(child).copied().unwrap_or(if child.compute_expand(orientation) { 1.0 } else { 0.0 })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let content = content_area(&obj, allocation);
+        let is_horizontal = orientation == gtk::Orientation::Horizontal;
+        let sizes = children
+            .iter()
+            .map(|child| {
+                if is_horizontal {
+                    child.preferred_width_for_height(content.height())
+                } else {
+                    child.preferred_height_for_width(content.width())
+                }
+            })
+            .collect::<Vec<_>>();
+        let spacing = obj.spacing() * (children.len() as i32 - 1).max(0);
+        let available = if is_horizontal { content.width() } else { content.height() } - spacing;
+        let sizes = distribute_space(available, &sizes, &weights);
+
+        let is_rtl = is_horizontal && obj.direction() == gtk::TextDirection::Rtl;
+        let mut position = 0;
+        let mut clip = obj.clip();
+        for (child, size) in children.iter().zip(sizes) {
+            let child_allocation = if is_rtl {
+                gtk::Allocation::new(content.x() + content.width() - position - size, content.y(), size, content.height())
+            } else if is_horizontal {
+                gtk::Allocation::new(content.x() + position, content.y(), size, content.height())
+            } else {
+                gtk::Allocation::new(content.x(), content.y() + position, content.width(), size)
+            };
+            child.size_allocate(&child_allocation);
+            clip = clip.union(&child.clip());
+            position += size + obj.spacing();
+        }
+        obj.set_clip(&clip);
+    }
+}
+
+/// Get the area GtkBox lays out its children in, which is its allocation without its CSS margin, border and padding.
+fn content_area(gtk_box: &WeightedBox, allocation: &gtk::Allocation) -> gtk::Allocation {
+    let style_context = gtk_box.style_context();
+    let state = style_context.state();
+    let (margin, border, padding) = (style_context.margin(state), style_context.border(state), style_context.padding(state));
+    let left = (margin.left() + border.left() + padding.left()) as i32;
+    let right = (margin.right() + border.right() + padding.right()) as i32;
+    let top = (margin.top() + border.top() + padding.top()) as i32;
+    let bottom = (margin.bottom() + border.bottom() + padding.bottom()) as i32;
+    gtk::Allocation::new(
+        allocation.x() + left,
+        allocation.y() + top,
+        (allocation.width() - left - right).max(0),
+        (allocation.height() - top - bottom).max(0),
+    )
+}
+
+/// Split the available space among children with the given minimum and natural sizes.
+/// Children first get their natural size, and any space left after that is split according to the weights.
+/// If there isn't enough space for that, the children shrink towards their minimum size, proportionally to how much
+/// they can shrink.
+fn distribute_space(available: i32, sizes: &[(i32, i32)], weights: &[f64]) -> Vec<i32> {
+    let total_minimum: i32 = sizes.iter().map(|(minimum, _)| minimum).sum();
+    let total_natural: i32 = sizes.iter().map(|(_, natural)| natural).sum();
+    if available >= total_natural {
+        let extra = available - total_natural;
+        let total_weight: f64 = weights.iter().sum();
+        return split_by_weights(extra, weights, total_weight)
+            .into_iter()
+            .zip(sizes)
+            .map(|(share, (_, natural))| natural + share)
+            .collect();
+    }
+    let shrinkable = sizes.iter().map(|(minimum, natural)| (natural - minimum) as f64).collect::<Vec<_>>();
+    let extra = (available - total_minimum).max(0);
+    split_by_weights(extra, &shrinkable, (total_natural - total_minimum) as f64)
+        .into_iter()
+        .zip(sizes)
+        .map(|(share, (minimum, _))| minimum + share)
+        .collect()
+}
+
+/// Split an amount of pixels according to the given weights, such that the shares add up to the whole amount.
+fn split_by_weights(amount: i32, weights: &[f64], total_weight: f64) -> Vec<i32> {
+    if total_weight <= 0.0 {
+        return vec![0; weights.len()];
+    }
+    let mut weight_so_far = 0.0;
+    let mut given_so_far = 0;
+    weights
+        .iter()
+        .map(|weight| {
+            weight_so_far += weight;
+            let given = (amount as f64 * weight_so_far / total_weight).round() as i32;
+            let share = given - given_so_far;
+            given_so_far = given;
+            share
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_distribute_space() {
+        // leftover space is split by weight
+        assert_eq!(distribute_space(100, &[(10, 20), (10, 20)], &[1.0, 3.0]), vec![35, 65]);
+        // children without a weight keep their natural size
+        assert_eq!(distribute_space(100, &[(10, 20), (10, 20), (10, 20)], &[0.0, 1.0, 1.0]), vec![20, 40, 40]);
+        // the shares always add up to the available space
+        assert_eq!(distribute_space(100, &[(0, 0), (0, 0), (0, 0)], &[1.0, 1.0, 1.0]), vec![33, 34, 33]);
+        // without enough space, children shrink towards their minimum size
+        assert_eq!(distribute_space(30, &[(10, 20), (0, 20)], &[1.0, 1.0]), vec![17, 13]);
+        assert_eq!(distribute_space(5, &[(10, 20), (0, 20)], &[1.0, 1.0]), vec![10, 0]);
+    }
+}
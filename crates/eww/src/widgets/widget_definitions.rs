@@ -1,5 +1,14 @@
 #![allow(clippy::option_map_unit_fn)]
-use super::{build_widget::BuilderArgs, circular_progressbar::*, run_command, transform::*};
+use super::{
+    build_widget::BuilderArgs,
+    circular_progressbar::*,
+    color::{self, ColorFormat},
+    run_command,
+    ticker::Ticker,
+    transform::*,
+    weighted_box::WeightedBox,
+    Throttle,
+};
 use crate::{
     def_widget, enum_parse, error_handling_ctx,
     util::{self, list_difference},
@@ -84,6 +93,8 @@ pub const BUILTIN_WIDGET_NAMES: &[&str] = &[
     WIDGET_NAME_OVERLAY,
     WIDGET_NAME_STACK,
     WIDGET_NAME_SYSTRAY,
+    WIDGET_NAME_SEPARATOR,
+    WIDGET_NAME_TICKER,
 ];
 
 /// widget definitions
@@ -114,6 +125,8 @@ pub(super) fn widget_use_to_gtk_widget(bargs: &mut BuilderArgs) -> Result<gtk::W
         WIDGET_NAME_OVERLAY => build_gtk_overlay(bargs)?.upcast(),
         WIDGET_NAME_STACK => build_gtk_stack(bargs)?.upcast(),
         WIDGET_NAME_SYSTRAY => build_systray(bargs)?.upcast(),
+        WIDGET_NAME_SEPARATOR => build_gtk_separator(bargs)?.upcast(),
+        WIDGET_NAME_TICKER => build_ticker(bargs)?.upcast(),
         _ => {
             return Err(DiagError(gen_diagnostic! {
                 msg = format!("referenced unknown widget `{}`", bargs.widget_use.name),
@@ -168,6 +181,23 @@ pub(super) fn resolve_widget_attrs(bargs: &mut BuilderArgs, gtk_widget: &gtk::Wi
         error_handling_ctx::print_error(err);
     }
 
+    // @prop tooltip-widget - widget to show as the tooltip (on hover) instead of text, i.e.: `:tooltip-widget (box (image :path "preview.png") "Preview")`
+    if let Some(tooltip_attr) = bargs.unhandled_attrs.remove("tooltip-widget") {
+        let tooltip_widget_use = yuck::config::widget_use::WidgetUse::from_ast(tooltip_attr.value)?;
+        let tooltip_content = build_gtk_widget(
+            bargs.scope_graph,
+            bargs.widget_defs.clone(),
+            bargs.calling_scope,
+            tooltip_widget_use,
+            bargs.custom_widget_invocation.clone(),
+        )?;
+        gtk_widget.set_has_tooltip(true);
+        gtk_widget.connect_query_tooltip(move |_this, _x, _y, _keyboard_mode, tooltip| {
+            tooltip.set_custom(Some(&tooltip_content));
+            true
+        });
+    }
+
     def_widget!(bargs, _g, gtk_widget, {
         // @prop class - css class name
         prop(class: as_string) {
@@ -191,6 +221,17 @@ pub(super) fn resolve_widget_attrs(bargs: &mut BuilderArgs, gtk_widget: &gtk::Wi
         prop(vexpand: as_bool = false) { gtk_widget.set_vexpand(vexpand) },
         // @prop hexpand - should this widget expand horizontally. Default: false.
         prop(hexpand: as_bool = false) { gtk_widget.set_hexpand(hexpand) },
+        // @prop grow - when inside a `box` that doesn't space evenly, how much of the leftover space this widget gets, relative to the `grow` of its siblings. I.e. a child with a `grow` of 2 gets twice as much extra space as one with a `grow` of 1. Siblings that `hexpand` or `vexpand` along the box without a `grow` count as a `grow` of 1.
+        prop(grow: as_f64) {
+            // The weight is kept by the box, which this widget may only be added to later
+            let set_grow = move |gtk_widget: &gtk::Widget| {
+                if let Some(gtk_box) = gtk_widget.parent().and_then(|parent| parent.downcast::<WeightedBox>().ok()) {
+                    gtk_box.set_child_grow(gtk_widget, grow);
+                }
+            };
+            set_grow(&gtk_widget);
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_parent_set(move |gtk_widget, _| set_grow(gtk_widget)));
+        },
         // @prop width - width of this element. note that this can not restrict the size if the contents stretch it
         // @prop height - height of this element. note that this can not restrict the size if the contents stretch it
         prop(width: as_i32?, height: as_i32?) {
@@ -235,8 +276,11 @@ pub(super) fn resolve_range_attrs(bargs: &mut BuilderArgs, gtk_widget: &gtk::Ran
         *is_being_dragged.borrow_mut() = true;
         glib::Propagation::Proceed
     }));
-    gtk_widget.connect_button_release_event(glib::clone!(@strong is_being_dragged => move |_, _| {
+    // Commands held back by `:throttle` run as soon as the slider is released
+    let onchange_throttle = Throttle::default();
+    gtk_widget.connect_button_release_event(glib::clone!(@strong is_being_dragged, @strong onchange_throttle => move |_, _| {
         *is_being_dragged.borrow_mut() = false;
+        onchange_throttle.flush();
         glib::Propagation::Proceed
     }));
 
@@ -259,15 +303,18 @@ pub(super) fn resolve_range_attrs(bargs: &mut BuilderArgs, gtk_widget: &gtk::Ran
         // @prop max - the maximum value
         prop(max: as_f64) { gtk_widget.adjustment().set_upper(max)},
         // @prop timeout - timeout of the command. Default: "200ms"
+        // @prop throttle - run `onchange` at most this often while the value changes, i.e. while dragging. The latest value is always passed on, at the latest when the slider is released. Default: "0ms"
         // @prop onchange - command executed once the value is changes. The placeholder `{}`, used in the command will be replaced by the new value.
-        prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
+        prop(timeout: as_duration = Duration::from_millis(200), throttle: as_duration = Duration::ZERO, onchange: as_string) {
             gtk_widget.set_sensitive(true);
             gtk_widget.add_events(gdk::EventMask::PROPERTY_CHANGE_MASK);
             let last_set_value = last_set_value_clone.clone();
+            let onchange_throttle = onchange_throttle.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_value_changed(move |gtk_widget| {
                 let value = gtk_widget.value();
                 if last_set_value.borrow_mut().take() != Some(value) {
-                    run_command(timeout, &onchange, &[value]);
+                    let onchange = onchange.clone();
+                    onchange_throttle.run(throttle, move || run_command(timeout, &onchange, &[value]));
                 }
             }));
         }
@@ -300,10 +347,12 @@ fn build_gtk_combo_box_text(bargs: &mut BuilderArgs) -> Result<gtk::ComboBoxText
             }
         },
         // @prop timeout - timeout of the command: Default: "200ms"
-        // @prop onchange - runs the code when a item was selected, replacing {} with the item as a string
+        // @prop onchange - runs the code when a item was selected, replacing {} (or {0}) with the item as a string, and {1} with the index of the item
         prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
             connect_signal_handler!(gtk_widget, gtk_widget.connect_changed(move |gtk_widget| {
-                run_command(timeout, &onchange, &[gtk_widget.active_text().unwrap_or_else(|| "".into())]);
+                let text = gtk_widget.active_text().map(|text| text.to_string()).unwrap_or_default();
+                let index = gtk_widget.active().map_or(-1, |index| index as i64);
+                run_command(timeout, &onchange, &[text, index.to_string()]);
             }));
         },
     });
@@ -349,11 +398,30 @@ fn build_gtk_expander(bargs: &mut BuilderArgs) -> Result<gtk::Expander> {
         }
     }
 
+    // We keep track of the last value that has been set via the expanded property,
+    // so that onexpand only runs when the user toggles the expander, not when the variable changes.
+    let last_set_expanded = Rc::new(RefCell::new(None));
+    let last_set_expanded_clone = last_set_expanded.clone();
+
     def_widget!(bargs, _g, gtk_widget, {
         // @prop name - name of the expander
         prop(name: as_string) { gtk_widget.set_label(Some(&name)); },
         // @prop expanded - sets if the tree is expanded
-        prop(expanded: as_bool) { gtk_widget.set_expanded(expanded); }
+        prop(expanded: as_bool) {
+            *last_set_expanded.borrow_mut() = Some(expanded);
+            gtk_widget.set_expanded(expanded);
+        },
+        // @prop onexpand - command to run when the user expands or collapses the expander. The placeholder `{}` is replaced by whether it is expanded now.
+        // @prop timeout - timeout of the command. Default: "200ms"
+        prop(timeout: as_duration = Duration::from_millis(200), onexpand: as_string) {
+            let last_set_expanded = last_set_expanded_clone.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_expanded_notify(move |gtk_widget| {
+                let expanded = gtk_widget.is_expanded();
+                if last_set_expanded.borrow_mut().take() != Some(expanded) {
+                    run_command(timeout, &onexpand, &[expanded]);
+                }
+            }));
+        }
     });
 
     Ok(gtk_widget)
@@ -364,11 +432,29 @@ const WIDGET_NAME_REVEALER: &str = "revealer";
 /// @desc A widget that can reveal a child with an animation.
 fn build_gtk_revealer(bargs: &mut BuilderArgs) -> Result<gtk::Revealer> {
     let gtk_widget = gtk::Revealer::new();
+
+    // gtk can't slide and fade at the same time, so for "slidefade" transitions, the fade is animated separately
+    let fade_with_slide = Rc::new(RefCell::new(false));
+    let fade_with_slide_clone = fade_with_slide.clone();
+    let fade_tick_callback: Rc<RefCell<Option<gtk::TickCallbackId>>> = Rc::new(RefCell::new(None));
+
     def_widget!(bargs, _g, gtk_widget, {
-        // @prop transition - the name of the transition. Possible values: $transition
-        prop(transition: as_string = "crossfade") { gtk_widget.set_transition_type(parse_revealer_transition(&transition)?); },
+        // @prop transition - the name of the transition. Possible values: $revealer_transition
+        prop(transition: as_string = "crossfade") {
+            let (transition, fade) = parse_revealer_transition(&transition)?;
+            gtk_widget.set_transition_type(transition);
+            *fade_with_slide.borrow_mut() = fade;
+            if !fade {
+                gtk_widget.set_opacity(1.0);
+            }
+        },
         // @prop reveal - sets if the child is revealed or not
-        prop(reveal: as_bool) { gtk_widget.set_reveal_child(reveal); },
+        prop(reveal: as_bool) {
+            gtk_widget.set_reveal_child(reveal);
+            if *fade_with_slide_clone.borrow() {
+                animate_revealer_fade(&gtk_widget, reveal, &fade_tick_callback);
+            }
+        },
         // @prop duration - the duration of the reveal transition. Default: "500ms"
         prop(duration: as_duration = Duration::from_millis(500)) { gtk_widget.set_transition_duration(duration.as_millis() as u32); },
     });
@@ -382,12 +468,24 @@ fn build_gtk_checkbox(bargs: &mut BuilderArgs) -> Result<gtk::CheckButton> {
     let gtk_widget = gtk::CheckButton::new();
     def_widget!(bargs, _g, gtk_widget, {
         // @prop checked - whether the checkbox is toggled or not when created
+        // @prop state - the state of the checkbox, overriding `checked`: $checkbox_state. A mixed checkbox is shown as neither checked nor unchecked, until it is toggled by the user.
         // @prop timeout - timeout of the command. Default: "200ms"
         // @prop onchecked - action (command) to be executed when checked by the user
         // @prop onunchecked - similar to onchecked but when the widget is unchecked
-        prop(checked: as_bool = false, timeout: as_duration = Duration::from_millis(200), onchecked: as_string = "", onunchecked: as_string = "") {
-            gtk_widget.set_active(checked);
+        prop(checked: as_bool = false, state: as_string?, timeout: as_duration = Duration::from_millis(200), onchecked: as_string = "", onunchecked: as_string = "") {
+            match state.as_deref().map(parse_checkbox_state).transpose()? {
+                Some(None) => gtk_widget.set_inconsistent(true),
+                Some(Some(active)) => {
+                    gtk_widget.set_inconsistent(false);
+                    gtk_widget.set_active(active);
+                }
+                None => {
+                    gtk_widget.set_inconsistent(false);
+                    gtk_widget.set_active(checked);
+                }
+            }
             connect_signal_handler!(gtk_widget, gtk_widget.connect_toggled(move |gtk_widget| {
+                gtk_widget.set_inconsistent(false);
                 run_command(timeout, if gtk_widget.is_active() { &onchecked } else { &onunchecked }, &[] as &[&str]);
             }));
        }
@@ -405,11 +503,16 @@ fn build_gtk_color_button(bargs: &mut BuilderArgs) -> Result<gtk::ColorButton> {
         // @prop use-alpha - bool to whether or not use alpha
         prop(use_alpha: as_bool) {gtk_widget.set_use_alpha(use_alpha);},
 
-        // @prop onchange - runs the code when the color was selected
+        // @prop value - the selected color, as a color name, in hex, or in any of the formats accepted by `format`
+        prop(value: as_string) { gtk_widget.set_rgba(&color::parse_color(&value)?); },
+
+        // @prop onchange - runs the code when the color was selected. The placeholder `{}` will be replaced by the color.
+        // @prop format - the format of the color given to `onchange`: $color_format. By default, this is `rgb` or `rgba`, depending on the alpha of the color.
         // @prop timeout - timeout of the command. Default: "200ms"
-        prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
+        prop(timeout: as_duration = Duration::from_millis(200), format: as_string?, onchange: as_string) {
+            let format = format.as_deref().map(parse_color_format).transpose()?;
             connect_signal_handler!(gtk_widget, gtk_widget.connect_color_set(move |gtk_widget| {
-                run_command(timeout, &onchange, &[gtk_widget.rgba()]);
+                run_command(timeout, &onchange, &[format_color_value(&gtk_widget.rgba(), format)]);
             }));
         }
     });
@@ -417,6 +520,14 @@ fn build_gtk_color_button(bargs: &mut BuilderArgs) -> Result<gtk::ColorButton> {
     Ok(gtk_widget)
 }
 
+/// Format a color for a command, falling back to gdk's representation if no format is given
+fn format_color_value(value: &gdk::RGBA, format: Option<ColorFormat>) -> String {
+    match format {
+        Some(format) => color::format_color(value, format),
+        None => value.to_string(),
+    }
+}
+
 const WIDGET_NAME_COLOR_CHOOSER: &str = "color-chooser";
 /// @widget color-chooser
 /// @desc A color chooser widget
@@ -426,11 +537,16 @@ fn build_gtk_color_chooser(bargs: &mut BuilderArgs) -> Result<gtk::ColorChooserW
         // @prop use-alpha - bool to wether or not use alpha
         prop(use_alpha: as_bool) {gtk_widget.set_use_alpha(use_alpha);},
 
-        // @prop onchange - runs the code when the color was selected
+        // @prop value - the selected color, as a color name, in hex, or in any of the formats accepted by `format`
+        prop(value: as_string) { gtk_widget.set_rgba(&color::parse_color(&value)?); },
+
+        // @prop onchange - runs the code when the color was selected. The placeholder `{}` will be replaced by the color.
+        // @prop format - the format of the color given to `onchange`: $color_format. By default, this is `rgb` or `rgba`, depending on the alpha of the color.
         // @prop timeout - timeout of the command. Default: "200ms"
-        prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
+        prop(timeout: as_duration = Duration::from_millis(200), format: as_string?, onchange: as_string) {
+            let format = format.as_deref().map(parse_color_format).transpose()?;
             connect_signal_handler!(gtk_widget, gtk_widget.connect_color_activated(move |_a, color| {
-                run_command(timeout, &onchange, &[*color]);
+                run_command(timeout, &onchange, &[format_color_value(color, format)]);
             }));
         }
     });
@@ -444,16 +560,39 @@ const WIDGET_NAME_SCALE: &str = "scale";
 fn build_gtk_scale(bargs: &mut BuilderArgs) -> Result<gtk::Scale> {
     let gtk_widget = gtk::Scale::new(gtk::Orientation::Horizontal, Some(&gtk::Adjustment::new(0.0, 0.0, 100.0, 1.0, 1.0, 1.0)));
 
+    // The values of the marks, kept around so the value can be snapped to them
+    let mark_values: Rc<RefCell<Vec<f64>>> = Rc::new(RefCell::new(Vec::new()));
+    let mark_values_clone = mark_values.clone();
+
     def_widget!(bargs, _g, gtk_widget, {
-        // @prop flipped - flip the direction
+        // @prop flipped - flip the direction. Vertical scales go from top to bottom by default, so flip them to have them grow upwards.
         prop(flipped: as_bool) { gtk_widget.set_inverted(flipped) },
 
         // @prop marks - draw marks
         prop(marks: as_string) {
             gtk_widget.clear_marks();
+            let mut values = Vec::new();
             for mark in marks.split(',') {
-                gtk_widget.add_mark(mark.trim().parse()?, gtk::PositionType::Bottom, None)
+                let mark = mark.trim().parse()?;
+                gtk_widget.add_mark(mark, gtk::PositionType::Bottom, None);
+                values.push(mark);
             }
+            *mark_values.borrow_mut() = values;
+        },
+
+        // @prop snap - snap the value to the nearest mark. The `onchange` command receives the snapped value.
+        prop(snap: as_bool = false) {
+            let mark_values = mark_values_clone.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_change_value(move |gtk_widget, _, value| {
+                let nearest_mark = mark_values.borrow().iter().copied().min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()));
+                match nearest_mark {
+                    Some(mark) if snap => {
+                        gtk_widget.set_value(mark);
+                        glib::Propagation::Stop
+                    }
+                    _ => glib::Propagation::Proceed,
+                }
+            }));
         },
 
         // @prop draw-value - draw the value of the property
@@ -474,12 +613,54 @@ const WIDGET_NAME_PROGRESS: &str = "progress";
 /// @desc A progress bar. HINT: for the `width` property to work, you may need to set the `min-width` of `progressbar > trough` in your css.
 fn build_gtk_progress(bargs: &mut BuilderArgs) -> Result<gtk::ProgressBar> {
     let gtk_widget = gtk::ProgressBar::new();
+
+    // The fraction set via value, restored once the bar stops pulsing, and the timer pulsing the bar while it is indeterminate
+    let fraction = Rc::new(RefCell::new(0f64));
+    let pulse_timer: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+    let pulse_timer_value = pulse_timer.clone();
+    let fraction_indeterminate = fraction.clone();
+    // Colors the bar according to its `color-stops`, stays empty without them
+    let color_stops_provider = gtk::CssProvider::new();
+    gtk_widget.style_context().add_provider(&color_stops_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+
     def_widget!(bargs, _g, gtk_widget, {
         // @prop flipped - flip the direction
         prop(flipped: as_bool) { gtk_widget.set_inverted(flipped) },
 
         // @prop value - value of the progress bar (between 0-100)
-        prop(value: as_f64) { gtk_widget.set_fraction(value / 100f64) },
+        // @prop color-stops - a json array of colors the bar goes through as the value rises from 0 to 100, i.e. `'["green", "yellow", "red"]'`. Overrides the css `background` of `progress`.
+        // @prop gradient - fill the bar with a gradient through the `color-stops`, instead of a single color depending on the value
+        prop(value: as_f64, color_stops: as_json_array?, gradient: as_bool = false) {
+            *fraction.borrow_mut() = value / 100f64;
+            if pulse_timer_value.borrow().is_none() {
+                gtk_widget.set_fraction(value / 100f64);
+            }
+            if let Some(color_stops) = color_stops {
+                let css = progress_color_stops_css(&gtk_widget, &parse_color_stops(&color_stops)?, value / 100f64, gradient);
+                color_stops_provider.load_from_data(css.as_bytes())?;
+            }
+        },
+
+        // @prop indeterminate - whether to show a pulsing bar instead of the value, for when the progress is unknown
+        // @prop pulse-interval - how often to move the pulsing bar. Default: "100ms"
+        prop(indeterminate: as_bool = false, pulse_interval: as_duration = Duration::from_millis(100)) {
+            if let Some(timer) = pulse_timer.borrow_mut().take() {
+                timer.remove();
+            }
+            if indeterminate {
+                let gtk_widget = gtk_widget.downgrade();
+                let timer = glib::timeout_add_local(pulse_interval, move || match gtk_widget.upgrade() {
+                    Some(gtk_widget) => {
+                        gtk_widget.pulse();
+                        glib::ControlFlow::Continue
+                    }
+                    None => glib::ControlFlow::Break,
+                });
+                *pulse_timer.borrow_mut() = Some(timer);
+            } else {
+                gtk_widget.set_fraction(*fraction_indeterminate.borrow());
+            }
+        },
 
         // @prop orientation - orientation of the progress bar. possible values: $orientation
         prop(orientation: as_string) { gtk_widget.set_orientation(parse_orientation(&orientation)?) },
@@ -488,11 +669,86 @@ fn build_gtk_progress(bargs: &mut BuilderArgs) -> Result<gtk::ProgressBar> {
     Ok(gtk_widget)
 }
 
+/// Css coloring the filled part of a progress bar according to its `color-stops`
+fn progress_color_stops_css(gtk_widget: &gtk::ProgressBar, color_stops: &[gdk::RGBA], fraction: f64, gradient: bool) -> String {
+    let fraction = fraction.clamp(0.0, 1.0);
+    if !gradient || color_stops.len() < 2 {
+        return match color::interpolate_color_stops(color_stops, fraction) {
+            Some(color) => format!("progress {{ background-color: {}; background-image: none; }}", color),
+            None => String::new(),
+        };
+    }
+    // Only the part of the gradient up to the current value is visible, stretched over the filled part of the bar
+    let mut stops: Vec<String> = color_stops
+        .iter()
+        .enumerate()
+        .map(|(i, color)| (i as f64 / (color_stops.len() - 1) as f64, color))
+        .take_while(|(position, _)| *position <= fraction)
+        .map(|(position, color)| format!("{} {}%", color, position / fraction.max(f64::EPSILON) * 100.0))
+        .collect();
+    if let Some(end_color) = color::interpolate_color_stops(color_stops, fraction) {
+        stops.push(format!("{} 100%", end_color));
+    }
+    let direction = match (gtk_widget.orientation(), gtk_widget.is_inverted()) {
+        (gtk::Orientation::Vertical, false) => "to bottom",
+        (gtk::Orientation::Vertical, true) => "to top",
+        (_, false) => "to right",
+        (_, true) => "to left",
+    };
+    format!("progress {{ background-image: linear-gradient({}, {}); }}", direction, stops.join(", "))
+}
+
+/// Parse a json array of colors, as given to `color-stops`
+fn parse_color_stops(color_stops: &[serde_json::Value]) -> Result<Vec<gdk::RGBA>> {
+    color_stops
+        .iter()
+        .map(|color| {
+            let color = color.as_str().context("color-stops should be an array of strings")?;
+            color::parse_color(color).with_context(|| format!("Invalid color `{color}` in color-stops"))
+        })
+        .collect()
+}
+
+const WIDGET_NAME_SEPARATOR: &str = "separator";
+/// @widget separator
+/// @desc A line dividing widgets, which can be styled via the `separator` css node.
+fn build_gtk_separator(bargs: &mut BuilderArgs) -> Result<gtk::Separator> {
+    let gtk_widget = gtk::Separator::new(gtk::Orientation::Horizontal);
+    def_widget!(bargs, _g, gtk_widget, {
+        // @prop orientation - orientation of the separator. possible values: $orientation
+        // @prop inset-start - space to leave free before the start of the line, in pixels
+        // @prop inset-end - space to leave free after the end of the line, in pixels
+        prop(orientation: as_string = "horizontal", inset_start: as_i32 = 0, inset_end: as_i32 = 0) {
+            let orientation = parse_orientation(&orientation)?;
+            gtk_widget.set_orientation(orientation);
+            let (start, end) = if orientation == gtk::Orientation::Horizontal { (inset_start, inset_end) } else { (0, 0) };
+            gtk_widget.set_margin_start(start);
+            gtk_widget.set_margin_end(end);
+            let (top, bottom) = if orientation == gtk::Orientation::Vertical { (inset_start, inset_end) } else { (0, 0) };
+            gtk_widget.set_margin_top(top);
+            gtk_widget.set_margin_bottom(bottom);
+        },
+    });
+    Ok(gtk_widget)
+}
+
 const WIDGET_NAME_INPUT: &str = "input";
 /// @widget input
 /// @desc An input field. For this to be useful, set `focusable="true"` on the window.
 fn build_gtk_input(bargs: &mut BuilderArgs) -> Result<gtk::Entry> {
     let gtk_widget = gtk::Entry::new();
+
+    // The pattern the text has to match, and whether commands should only run for matching text
+    let validation: Rc<RefCell<Option<(regex::Regex, bool)>>> = Rc::new(RefCell::new(None));
+    let validation_onchange = validation.clone();
+    let validation_onaccept = validation.clone();
+
+    let completion = gtk::EntryCompletion::new();
+    let completion_store = gtk::ListStore::new(&[glib::Type::STRING]);
+    completion.set_model(Some(&completion_store));
+    completion.set_text_column(0);
+    let completion_onaccept = completion.clone();
+
     def_widget!(bargs, _g, gtk_widget, {
         // @prop value - the content of the text field
         prop(value: as_string) {
@@ -500,16 +756,71 @@ fn build_gtk_input(bargs: &mut BuilderArgs) -> Result<gtk::Entry> {
         },
         // @prop onchange - Command to run when the text changes. The placeholder `{}` will be replaced by the value
         // @prop timeout - timeout of the command. Default: "200ms"
-        prop(timeout: as_duration = Duration::from_millis(200), onchange: as_string) {
+        // @prop throttle - run `onchange` at most this often while typing. The latest text is always passed on. Default: "0ms"
+        prop(timeout: as_duration = Duration::from_millis(200), throttle: as_duration = Duration::ZERO, onchange: as_string) {
+            let validation = validation_onchange.clone();
+            let onchange_throttle = Throttle::default();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_changed(move |gtk_widget| {
-                run_command(timeout, &onchange, &[gtk_widget.text().to_string()]);
+                let text = gtk_widget.text();
+                if input_passes_validation(&validation, &text) {
+                    let onchange = onchange.clone();
+                    onchange_throttle.run(throttle, move || run_command(timeout, &onchange, &[text.to_string()]));
+                }
             }));
         },
         // @prop onaccept - Command to run when the user hits return in the input field. The placeholder `{}` will be replaced by the value
         // @prop timeout - timeout of the command. Default: "200ms"
         prop(timeout: as_duration = Duration::from_millis(200), onaccept: as_string) {
+            let validation = validation_onaccept.clone();
             connect_signal_handler!(gtk_widget, gtk_widget.connect_activate(move |gtk_widget| {
-                run_command(timeout, &onaccept, &[gtk_widget.text().to_string()]);
+                let text = gtk_widget.text();
+                if input_passes_validation(&validation, &text) {
+                    run_command(timeout, &onaccept, &[text.to_string()]);
+                }
+            }));
+            // Selecting a completion counts as accepting it
+            let completion = completion_onaccept.clone();
+            connect_signal_handler!(completion, completion.connect_match_selected(move |completion, model, iter| {
+                let value = model.get::<String>(iter, 0);
+                if let Some(entry) = completion.entry().and_then(|entry| entry.downcast::<gtk::Entry>().ok()) {
+                    entry.set_text(&value);
+                    entry.set_position(-1);
+                }
+                run_command(timeout, &onaccept, &[value]);
+                glib::Propagation::Stop
+            }));
+        },
+        // @prop completions - json array of suggestions to show in a dropdown while typing. Can be updated while typing to provide custom filtering.
+        prop(completions: as_json_array) {
+            completion_store.clear();
+            for value in completions {
+                let value = match value {
+                    serde_json::Value::String(value) => value,
+                    other => other.to_string(),
+                };
+                completion_store.insert_with_values(None, &[(0, &value)]);
+            }
+            if gtk_widget.completion().is_none() {
+                gtk_widget.set_completion(Some(&completion));
+            }
+            if gtk_widget.has_focus() {
+                completion.complete();
+            }
+        },
+        // @prop regex - regular expression the whole text has to match. While it doesn't, the input gets the `invalid` css class.
+        // @prop validate-before-submit - only run `onchange` and `onaccept` when the text matches `regex`. Default: false
+        prop(regex: as_string?, validate_before_submit: as_bool = false) {
+            match regex {
+                Some(regex) => {
+                    let regex = regex::Regex::new(&format!("^(?:{})$", regex))?;
+                    *validation.borrow_mut() = Some((regex, validate_before_submit));
+                }
+                None => *validation.borrow_mut() = None,
+            }
+            update_input_validity(&gtk_widget, &validation);
+            let validation = validation.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_changed(move |gtk_widget| {
+                update_input_validity(gtk_widget, &validation);
             }));
         },
         // @prop password - if the input is obscured
@@ -520,6 +831,23 @@ fn build_gtk_input(bargs: &mut BuilderArgs) -> Result<gtk::Entry> {
     Ok(gtk_widget)
 }
 
+/// Whether commands of the input should run for the given text
+fn input_passes_validation(validation: &Rc<RefCell<Option<(regex::Regex, bool)>>>, text: &str) -> bool {
+    match &*validation.borrow() {
+        Some((regex, true)) => regex.is_match(text),
+        _ => true,
+    }
+}
+
+fn update_input_validity(gtk_widget: &gtk::Entry, validation: &Rc<RefCell<Option<(regex::Regex, bool)>>>) {
+    let valid = validation.borrow().as_ref().map_or(true, |(regex, _)| regex.is_match(&gtk_widget.text()));
+    if valid {
+        gtk_widget.style_context().remove_class("invalid");
+    } else {
+        gtk_widget.style_context().add_class("invalid");
+    }
+}
+
 const WIDGET_NAME_BUTTON: &str = "button";
 /// @widget button
 /// @desc A button containing any widget as it's child. Events are triggered on release.
@@ -535,18 +863,35 @@ fn build_gtk_button(bargs: &mut BuilderArgs) -> Result<gtk::Button> {
             // @prop onmiddleclick - command to run when the button is middleclicked
             onmiddleclick: as_string = "",
             // @prop onrightclick - command to run when the button is rightclicked
-            onrightclick: as_string = ""
+            onrightclick: as_string = "",
+            // @prop repeat - whether to keep running `onclick` while the button is held down. When enabled, `onclick` runs when the button is pressed rather than released.
+            repeat: as_bool = false,
+            // @prop repeat-interval - how often to run `onclick` while the button is held down. Default: "100ms"
+            repeat_interval: as_duration = Duration::from_millis(100)
         ) {
+            let repeat_timer: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+            let onclick_ = onclick.clone();
+            let onclick_repeat = onclick.clone();
+            let repeat_timer_ = repeat_timer.clone();
             // animate button upon right-/middleclick (if gtk theme supports it)
             // since we do this, we can't use `connect_clicked` as that would always run `onclick` as well
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_button_press_event(move |button, _| {
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_button_press_event(move |button, evt| {
                 button.emit_activate();
+                if repeat && evt.button() == 1 && evt.event_type() == gdk::EventType::ButtonPress {
+                    run_command(timeout, &onclick_repeat, &[] as &[&str]);
+                    start_button_repeat(&repeat_timer_, timeout, onclick_repeat.clone(), repeat_interval);
+                }
+                glib::Propagation::Proceed
+            }));
+            let repeat_timer_ = repeat_timer.clone();
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_leave_notify_event(move |_, _| {
+                stop_button_repeat(&repeat_timer_);
                 glib::Propagation::Proceed
             }));
-            let onclick_ = onclick.clone();
             // mouse click events
             connect_signal_handler!(gtk_widget, gtk_widget.connect_button_release_event(move |_, evt| {
                 match evt.button() {
+                    1 if repeat => stop_button_repeat(&repeat_timer),
                     1 => run_command(timeout, &onclick, &[] as &[&str]),
                     2 => run_command(timeout, &onmiddleclick, &[] as &[&str]),
                     3 => run_command(timeout, &onrightclick, &[] as &[&str]),
@@ -570,6 +915,36 @@ fn build_gtk_button(bargs: &mut BuilderArgs) -> Result<gtk::Button> {
     Ok(gtk_widget)
 }
 
+/// Delay between pressing a button with `repeat` enabled and the first repetition of its command
+const BUTTON_REPEAT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+/// Run `command` every `interval` once `BUTTON_REPEAT_INITIAL_DELAY` has passed, until [`stop_button_repeat`] is called.
+fn start_button_repeat(
+    repeat_timer: &Rc<RefCell<Option<glib::SourceId>>>,
+    timeout: Duration,
+    command: String,
+    interval: Duration,
+) {
+    stop_button_repeat(repeat_timer);
+    let timer_slot = repeat_timer.clone();
+    let initial_timer = glib::timeout_add_local_once(BUTTON_REPEAT_INITIAL_DELAY, move || {
+        run_command(timeout, &command, &[] as &[&str]);
+        let repeating_timer = glib::timeout_add_local(interval, move || {
+            run_command(timeout, &command, &[] as &[&str]);
+            glib::ControlFlow::Continue
+        });
+        // The initial timer has fired at this point, so it gets replaced without being removed
+        *timer_slot.borrow_mut() = Some(repeating_timer);
+    });
+    *repeat_timer.borrow_mut() = Some(initial_timer);
+}
+
+fn stop_button_repeat(repeat_timer: &Rc<RefCell<Option<glib::SourceId>>>) {
+    if let Some(timer) = repeat_timer.borrow_mut().take() {
+        timer.remove();
+    }
+}
+
 /// @var icon-size - "menu", "small-toolbar", "toolbar", "large-toolbar", "button", "dnd", "dialog"
 fn parse_icon_size(o: &str) -> Result<gtk::IconSize> {
     enum_parse! { "icon-size", o,
@@ -587,20 +962,48 @@ const WIDGET_NAME_IMAGE: &str = "image";
 /// @desc A widget displaying an image
 fn build_gtk_image(bargs: &mut BuilderArgs) -> Result<gtk::Image> {
     let gtk_widget = gtk::Image::new();
+
+    // Animated images are played back manually, so that they can be paused or slowed down
+    let animation: Rc<RefCell<Option<ImageAnimation>>> = Rc::new(RefCell::new(None));
+    let animation_clone = animation.clone();
+    let animation_clone2 = animation.clone();
+    let playback = Rc::new(RefCell::new(AnimationPlayback::default()));
+    let playback_clone = playback.clone();
+
     def_widget!(bargs, _g, gtk_widget, {
-        // @prop path - path to the image file
+        // @prop path - path to the image file, or a `data:` URI containing the image, i.e. `data:image/png;base64,iVBO...`
+        // @prop base64 - base64 encoded image data, used instead of `path`. Useful for images generated by scripts, such as album art.
         // @prop image-width - width of the image
         // @prop image-height - height of the image
         // @prop preserve-aspect-ratio - whether to keep the aspect ratio when resizing an image. Default: true, false doesn't work for all image types
-        // @prop fill-svg - sets the color of svg images
-        prop(path: as_string, image_width: as_i32 = -1, image_height: as_i32 = -1, preserve_aspect_ratio: as_bool = true, fill_svg: as_string = "") {
+        // @prop fill-svg - sets the color of svg images. This replaces all fills of the image, except for ones set to "none". To color icons from css instead, use a symbolic theme icon with `icon`.
+        prop(path: as_string?, base64: as_string?, image_width: as_i32 = -1, image_height: as_i32 = -1, preserve_aspect_ratio: as_bool = true, fill_svg: as_string = "") {
+            let path = match (path, base64) {
+                (_, Some(base64)) => {
+                    set_image_from_data(&gtk_widget, &animation, decode_base64(&base64), image_width, image_height, preserve_aspect_ratio);
+                    return Ok(());
+                }
+                (Some(path), None) => path,
+                (None, None) => return Ok(()),
+            };
+            if let Some(data_uri) = path.strip_prefix("data:") {
+                set_image_from_data(&gtk_widget, &animation, decode_data_uri(data_uri), image_width, image_height, preserve_aspect_ratio);
+                return Ok(());
+            }
+
             if !path.ends_with(".svg") && !fill_svg.is_empty() {
                 log::warn!("Fill attribute ignored, file is not an svg image");
             }
 
+            stop_image_animation(&animation);
             if path.ends_with(".gif") {
                 let pixbuf_animation = gtk::gdk_pixbuf::PixbufAnimation::from_file(std::path::PathBuf::from(path))?;
-                gtk_widget.set_from_animation(&pixbuf_animation);
+                let time = std::time::SystemTime::now();
+                let iter = pixbuf_animation.iter(Some(time));
+                gtk_widget.set_from_pixbuf(Some(&iter.pixbuf()));
+                let cycle_length = animation_cycle_length(&pixbuf_animation, time);
+                *animation.borrow_mut() = Some(ImageAnimation { iter, cycle_length, elapsed: Duration::ZERO, time, timer: None });
+                schedule_image_animation_frame(&gtk_widget, &animation, *playback.borrow());
             } else {
                 let pixbuf;
                 // populate the pixel buffer
@@ -608,8 +1011,11 @@ fn build_gtk_image(bargs: &mut BuilderArgs) -> Result<gtk::Image> {
                     let svg_data = std::fs::read_to_string(std::path::PathBuf::from(path.clone()))?;
                     // The fastest way to add/change fill color
                     let svg_data = if svg_data.contains("fill=") {
-                        let reg = regex::Regex::new(r#"fill="[^"]*""#)?;
-                        reg.replace(&svg_data, &format!("fill=\"{}\"", fill_svg))
+                        let reg = regex::Regex::new(r#"fill="([^"]*)""#)?;
+                        // Keep transparent fills, as these are usually used for outlines
+                        reg.replace_all(&svg_data, |caps: &regex::Captures| {
+                            if &caps[1] == "none" { caps[0].to_string() } else { format!("fill=\"{}\"", fill_svg) }
+                        })
                     } else {
                         let reg = regex::Regex::new(r"<svg")?;
                         reg.replace(&svg_data, &format!("<svg fill=\"{}\"", fill_svg))
@@ -626,31 +1032,271 @@ fn build_gtk_image(bargs: &mut BuilderArgs) -> Result<gtk::Image> {
         // @prop icon - name of a theme icon
         // @prop icon-size - size of the theme icon
         prop(icon: as_string, icon_size: as_string = "button") {
+            stop_image_animation(&animation_clone);
             gtk_widget.set_from_icon_name(Some(&icon), parse_icon_size(&icon_size)?);
         },
+        // @prop playing - whether animated images (gifs) are playing. When paused, the current frame stays visible.
+        // @prop loop - whether animated images should keep looping, or stop on their last frame after playing once
+        // @prop frame-delay-scale - how much longer each frame of an animated image is shown, i.e. 2 plays the animation at half its speed
+        prop(playing: as_bool = true, r#loop: as_bool = true, frame_delay_scale: as_f64 = 1.0) {
+            if frame_delay_scale <= 0.0 {
+                return Err(anyhow!("frame-delay-scale must be greater than 0, got {frame_delay_scale}"));
+            }
+            let new_playback = AnimationPlayback { playing, looping: r#loop, frame_delay_scale };
+            *playback_clone.borrow_mut() = new_playback;
+            schedule_image_animation_frame(&gtk_widget, &animation_clone2, new_playback);
+        },
     });
     Ok(gtk_widget)
 }
 
+/// Show an image loaded from memory, or a placeholder if the data isn't a valid image.
+fn set_image_from_data(
+    gtk_widget: &gtk::Image,
+    animation: &Rc<RefCell<Option<ImageAnimation>>>,
+    data: Result<Vec<u8>>,
+    image_width: i32,
+    image_height: i32,
+    preserve_aspect_ratio: bool,
+) {
+    stop_image_animation(animation);
+    let pixbuf = data.and_then(|data| {
+        let stream = gtk::gio::MemoryInputStream::from_bytes(&gtk::glib::Bytes::from_owned(data));
+        let pixbuf = gtk::gdk_pixbuf::Pixbuf::from_stream_at_scale(
+            &stream,
+            image_width,
+            image_height,
+            preserve_aspect_ratio,
+            None::<&gtk::gio::Cancellable>,
+        )
+        .context("Failed to load image from data");
+        stream.close(None::<&gtk::gio::Cancellable>)?;
+        pixbuf
+    });
+    match pixbuf {
+        Ok(pixbuf) => gtk_widget.set_from_pixbuf(Some(&pixbuf)),
+        Err(err) => {
+            error_handling_ctx::print_error(err);
+            gtk_widget.set_from_icon_name(Some("image-missing"), gtk::IconSize::Button);
+        }
+    }
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>> {
+    let data = data.trim();
+    if data.is_empty() || !data.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '\n' | '\r')) {
+        return Err(anyhow!("Invalid base64 image data"));
+    }
+    Ok(gtk::glib::base64_decode(data))
+}
+
+/// Decode the contents of a `data:` URI (without the `data:` prefix), i.e. `image/png;base64,iVBO...` or `image/svg+xml,<svg...`
+fn decode_data_uri(data_uri: &str) -> Result<Vec<u8>> {
+    let (metadata, data) = data_uri.split_once(',').ok_or_else(|| anyhow!("Invalid data URI, expected a `,` before the data"))?;
+    if metadata.ends_with(";base64") {
+        decode_base64(data)
+    } else {
+        let data =
+            gtk::glib::Uri::unescape_string(data, None::<&str>).ok_or_else(|| anyhow!("Invalid escape sequence in data URI"))?;
+        Ok(data.as_bytes().to_vec())
+    }
+}
+
+/// How an animated image should be played back
+#[derive(Debug, Clone, Copy)]
+struct AnimationPlayback {
+    playing: bool,
+    looping: bool,
+    frame_delay_scale: f64,
+}
+
+impl Default for AnimationPlayback {
+    fn default() -> Self {
+        Self { playing: true, looping: true, frame_delay_scale: 1.0 }
+    }
+}
+
+/// The state of an animated image that is currently displayed
+struct ImageAnimation {
+    iter: gtk::gdk_pixbuf::PixbufAnimationIter,
+    /// How long it takes to play all frames once, or `None` if the animation doesn't repeat or is too long to tell
+    cycle_length: Option<Duration>,
+    /// How long the animation has been playing for
+    elapsed: Duration,
+    /// The time within the animation, which only advances while it's playing
+    time: std::time::SystemTime,
+    timer: Option<glib::SourceId>,
+}
+
+/// Most frames of an animation that are looked at to find out how long it takes to play it once
+const MAX_PROBED_ANIMATION_FRAMES: usize = 128;
+/// How many frames after the point where an animation seems to start over need to match its first frames, to confirm it
+const CONFIRMED_ANIMATION_FRAMES: usize = 8;
+
+/// Find out how long it takes to play all frames of a repeating animation once, by stepping through it with the delays
+/// of its frames until it starts over. Returns `None` for animations that end by themselves, or that are too long to tell.
+fn animation_cycle_length(animation: &gtk::gdk_pixbuf::PixbufAnimation, start: std::time::SystemTime) -> Option<Duration> {
+    use std::hash::{Hash, Hasher};
+
+    let iter = animation.iter(Some(start));
+    let mut time = start;
+    // The pixels of each frame along with how long it is shown
+    let mut frames: Vec<(u64, Duration)> = Vec::new();
+    // Numbers of frames after which the animation seems to start over, along with how long playing them takes
+    let mut candidates: Vec<(usize, Duration)> = Vec::new();
+    while frames.len() < MAX_PROBED_ANIMATION_FRAMES {
+        let delay = iter.delay_time()?;
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        iter.pixbuf().read_pixel_bytes().hash(&mut hasher);
+        let frame = (hasher.finish(), delay);
+
+        // Frames that repeat at the start of the animation would be mistaken for its end if the frames following them
+        // weren't compared as well
+        let index = frames.len();
+        candidates.retain(|(cycle_frames, _)| frames[index - cycle_frames] == frame);
+        if let Some((_, cycle_length)) =
+            candidates.iter().find(|(cycle_frames, _)| index + 1 - cycle_frames >= CONFIRMED_ANIMATION_FRAMES)
+        {
+            return Some(*cycle_length);
+        }
+        if index > 0 && frames[0] == frame {
+            candidates.push((index, time.duration_since(start).ok()?));
+        }
+        frames.push(frame);
+        time += delay;
+        iter.advance(time);
+    }
+    None
+}
+
+fn stop_image_animation(animation: &Rc<RefCell<Option<ImageAnimation>>>) {
+    if let Some(timer) = animation.borrow_mut().take().and_then(|animation| animation.timer) {
+        timer.remove();
+    }
+}
+
+/// (Re)schedule showing the next frame of the current animation of the image, if there is one and it is playing.
+fn schedule_image_animation_frame(
+    gtk_widget: &gtk::Image,
+    animation: &Rc<RefCell<Option<ImageAnimation>>>,
+    playback: AnimationPlayback,
+) {
+    let mut animation_ref = animation.borrow_mut();
+    let Some(state) = animation_ref.as_mut() else { return };
+    if let Some(timer) = state.timer.take() {
+        timer.remove();
+    }
+    // No delay means the animation has ended
+    let Some(delay) = state.iter.delay_time().filter(|_| playback.playing) else { return };
+    // Without looping, the last frame of the current run through the animation stays visible
+    if let Some(cycle_length) = state.cycle_length.filter(|_| !playback.looping) {
+        let elapsed_in_cycle = state.elapsed.as_millis() % cycle_length.as_millis().max(1);
+        if elapsed_in_cycle + delay.as_millis() >= cycle_length.as_millis() {
+            return;
+        }
+    }
+
+    let gtk_widget = gtk_widget.downgrade();
+    let animation = animation.clone();
+    state.timer = Some(glib::timeout_add_local_once(delay.mul_f64(playback.frame_delay_scale), move || {
+        let Some(gtk_widget) = gtk_widget.upgrade() else { return };
+        {
+            let mut animation_ref = animation.borrow_mut();
+            let Some(state) = animation_ref.as_mut() else { return };
+            // This timer is done now, so it must not be removed anymore
+            state.timer = None;
+            state.time += delay;
+            state.elapsed += delay;
+            state.iter.advance(state.time);
+            gtk_widget.set_from_pixbuf(Some(&state.iter.pixbuf()));
+        }
+        schedule_image_animation_frame(&gtk_widget, &animation, playback);
+    }));
+}
+
 const WIDGET_NAME_BOX: &str = "box";
 /// @widget box
 /// @desc the main layout container
 fn build_gtk_box(bargs: &mut BuilderArgs) -> Result<gtk::Box> {
-    let gtk_widget = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    let gtk_widget: gtk::Box = WeightedBox::new().upcast();
+
+    // When the spacing collapses around closed revealers, it is applied as margins of the children instead,
+    // which need to be updated whenever the children or their reveal state change.
+    let collapsed_spacing: Rc<RefCell<Option<i32>>> = Rc::new(RefCell::new(None));
+    gtk_widget.connect_add(glib::clone!(@strong collapsed_spacing => move |gtk_widget, child| {
+        if let Some(revealer) = child.downcast_ref::<gtk::Revealer>() {
+            let gtk_box = gtk_widget.downgrade();
+            let collapsed_spacing = collapsed_spacing.clone();
+            let update = move |_: &gtk::Revealer| {
+                if let (Some(gtk_box), Some(spacing)) = (gtk_box.upgrade(), *collapsed_spacing.borrow()) {
+                    apply_collapsed_spacing(&gtk_box, spacing);
+                }
+            };
+            revealer.connect_reveal_child_notify(update.clone());
+            revealer.connect_child_revealed_notify(update);
+        }
+        if let Some(spacing) = *collapsed_spacing.borrow() {
+            apply_collapsed_spacing(gtk_widget, spacing);
+        }
+    }));
+    gtk_widget.connect_remove(glib::clone!(@strong collapsed_spacing => move |gtk_widget, _| {
+        if let Some(spacing) = *collapsed_spacing.borrow() {
+            apply_collapsed_spacing(gtk_widget, spacing);
+        }
+    }));
+    gtk_widget.connect_orientation_notify(glib::clone!(@strong collapsed_spacing => move |gtk_widget| {
+        if let Some(spacing) = *collapsed_spacing.borrow() {
+            apply_collapsed_spacing(gtk_widget, spacing);
+        }
+    }));
+
     def_widget!(bargs, _g, gtk_widget, {
         // @prop spacing - spacing between elements
-        prop(spacing: as_i32 = 0) { gtk_widget.set_spacing(spacing) },
+        // @prop collapse-spacing - leave out the spacing around `revealer` children that are closed, instead of leaving a double gap where they are
+        prop(spacing: as_i32 = 0, collapse_spacing: as_bool = false) {
+            if collapse_spacing {
+                gtk_widget.set_spacing(0);
+                collapsed_spacing.replace(Some(spacing));
+                apply_collapsed_spacing(&gtk_widget, spacing);
+            } else {
+                // reset the margins set while collapsing was enabled
+                if collapsed_spacing.replace(None).is_some() {
+                    apply_collapsed_spacing(&gtk_widget, 0);
+                }
+                gtk_widget.set_spacing(spacing);
+            }
+        },
         // @prop orientation - orientation of the box. possible values: $orientation
         prop(orientation: as_string) { gtk_widget.set_orientation(parse_orientation(&orientation)?) },
-        // @prop space-evenly - space the widgets evenly.
+        // @prop space-evenly - space the widgets evenly. When enabled, the `grow` of the children is ignored.
         prop(space_evenly: as_bool = true) { gtk_widget.set_homogeneous(space_evenly) },
     });
     Ok(gtk_widget)
 }
 
+/// Apply the spacing of a box as margins of its children, leaving out the spacing around closed revealers.
+fn apply_collapsed_spacing(gtk_box: &gtk::Box, spacing: i32) {
+    let is_horizontal = gtk_box.orientation() == gtk::Orientation::Horizontal;
+    let mut is_first = true;
+    for child in gtk_box.children() {
+        let is_collapsed = child
+            .downcast_ref::<gtk::Revealer>()
+            .map_or(false, |revealer| !revealer.reveals_child() && !revealer.is_child_revealed());
+        let margin = if is_collapsed || is_first { 0 } else { spacing };
+        is_first &= is_collapsed;
+        if is_horizontal {
+            child.set_margin_start(margin);
+            child.set_margin_top(0);
+        } else {
+            child.set_margin_top(margin);
+            child.set_margin_start(0);
+        }
+    }
+}
+
 const WIDGET_NAME_OVERLAY: &str = "overlay";
 /// @widget overlay
-/// @desc a widget that places its children on top of each other. The overlay widget takes the size of its first child.
+/// @desc a widget that places its children on top of each other. The overlay widget takes the size of its first child. The other children are positioned using their own `halign` and `valign`, and let clicks pass through to the children below them, unless they are given `:pass-through false`.
 fn build_gtk_overlay(bargs: &mut BuilderArgs) -> Result<gtk::Overlay> {
     let gtk_widget = gtk::Overlay::new();
 
@@ -662,23 +1308,52 @@ fn build_gtk_overlay(bargs: &mut BuilderArgs) -> Result<gtk::Overlay> {
         }
         Ordering::Greater | Ordering::Equal => {
             let mut children = bargs.widget_use.children.iter().map(|child| {
-                build_gtk_widget(
+                // `pass-through` describes the layer of the overlay the child is placed in, rather than the child itself,
+                // so it is taken out of the child's attributes before building it
+                let mut child = child.clone();
+                let pass_through = child.attrs.ast_optional::<simplexpr::SimplExpr>("pass-through")?;
+                let gtk_child = build_gtk_widget(
                     bargs.scope_graph,
                     bargs.widget_defs.clone(),
                     bargs.calling_scope,
-                    child.clone(),
+                    child,
                     bargs.custom_widget_invocation.clone(),
-                )
+                )?;
+                Ok::<_, anyhow::Error>((gtk_child, pass_through))
             });
             // we have more than one child, we can unwrap
-            let first = children.next().unwrap()?;
+            let (first, _) = children.next().unwrap()?;
             gtk_widget.add(&first);
             first.show();
+            let mut pass_through_exprs = Vec::new();
             for child in children {
-                let child = child?;
+                let (child, pass_through) = child?;
                 gtk_widget.add_overlay(&child);
                 gtk_widget.set_overlay_pass_through(&child, true);
                 child.show();
+                if let Some(pass_through) = pass_through {
+                    pass_through_exprs.push((child, pass_through));
+                }
+            }
+
+            for (child, pass_through) in pass_through_exprs {
+                bargs.scope_graph.register_listener(
+                    bargs.calling_scope,
+                    crate::state::scope::Listener {
+                        needed_variables: pass_through.collect_var_refs(),
+                        f: Box::new({
+                            let gtk_widget = gtk_widget.downgrade();
+                            let child = child.downgrade();
+                            move |_, values| {
+                                let pass_through = pass_through.eval(&values)?.as_bool()?;
+                                if let (Some(gtk_widget), Some(child)) = (gtk_widget.upgrade(), child.upgrade()) {
+                                    gtk_widget.set_overlay_pass_through(&child, pass_through);
+                                }
+                                Ok(())
+                            }
+                        }),
+                    },
+                )?;
             }
             Ok(gtk_widget)
         }
@@ -782,6 +1457,34 @@ fn build_gtk_scrolledwindow(bargs: &mut BuilderArgs) -> Result<gtk::ScrolledWind
     // I don't have single idea of what those two generics are supposed to be, but this works.
     let gtk_widget = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
 
+    // The scroll positions requested via the position properties. These get reapplied whenever the size of the content changes,
+    // as setting them before the content got allocated doesn't have any effect, until the user scrolls manually.
+    let requested_hposition: Rc<RefCell<Option<f64>>> = Rc::new(RefCell::new(None));
+    let requested_vposition: Rc<RefCell<Option<f64>>> = Rc::new(RefCell::new(None));
+    // Set while we change the scroll position ourselves, to differentiate that from the user scrolling
+    let setting_position = Rc::new(RefCell::new(false));
+
+    for (adjustment, requested) in
+        [(gtk_widget.hadjustment(), requested_hposition.clone()), (gtk_widget.vadjustment(), requested_vposition.clone())]
+    {
+        let setting_position_ = setting_position.clone();
+        let requested_ = requested.clone();
+        adjustment.connect_changed(move |adjustment| {
+            if let Some(fraction) = *requested_.borrow() {
+                set_scroll_fraction(adjustment, fraction, &setting_position_);
+            }
+        });
+        let setting_position = setting_position.clone();
+        adjustment.connect_value_changed(move |_| {
+            if !*setting_position.borrow() {
+                requested.borrow_mut().take();
+            }
+        });
+    }
+
+    let setting_position_h = setting_position.clone();
+    let setting_position_v = setting_position.clone();
+
     def_widget!(bargs, _g, gtk_widget, {
         // @prop hscroll - scroll horizontally
         // @prop vscroll - scroll vertically
@@ -791,11 +1494,55 @@ fn build_gtk_scrolledwindow(bargs: &mut BuilderArgs) -> Result<gtk::ScrolledWind
                 if vscroll { gtk::PolicyType::Automatic } else { gtk::PolicyType::Never },
             )
         },
+        // @prop hscroll-position - horizontal scroll position, from 0 (left) to 1 (right)
+        prop(hscroll_position: as_f64) {
+            *requested_hposition.borrow_mut() = Some(hscroll_position);
+            set_scroll_fraction(&gtk_widget.hadjustment(), hscroll_position, &setting_position_h);
+        },
+        // @prop vscroll-position - vertical scroll position, from 0 (top) to 1 (bottom)
+        prop(vscroll_position: as_f64) {
+            *requested_vposition.borrow_mut() = Some(vscroll_position);
+            set_scroll_fraction(&gtk_widget.vadjustment(), vscroll_position, &setting_position_v);
+        },
+        // @prop onscroll - command to run when the user scrolls. The placeholders `{0}` and `{1}` will be replaced by the horizontal and vertical scroll position, from 0 to 1.
+        // @prop timeout - timeout of the command. Default: "200ms"
+        prop(timeout: as_duration = Duration::from_millis(200), onscroll: as_string) {
+            let hadjustment = gtk_widget.hadjustment();
+            let vadjustment = gtk_widget.vadjustment();
+            let run_onscroll = {
+                let setting_position = setting_position.clone();
+                let (hadjustment, vadjustment) = (hadjustment.clone(), vadjustment.clone());
+                move |_: &gtk::Adjustment| {
+                    if !*setting_position.borrow() {
+                        run_command(timeout, &onscroll, &[scroll_fraction(&hadjustment), scroll_fraction(&vadjustment)]);
+                    }
+                }
+            };
+            connect_signal_handler!(hadjustment, hadjustment.connect_value_changed(run_onscroll.clone()));
+            connect_signal_handler!(vadjustment, vadjustment.connect_value_changed(run_onscroll));
+        },
     });
 
     Ok(gtk_widget)
 }
 
+/// Get the position of the adjustment as a fraction of its scrollable range
+fn scroll_fraction(adjustment: &gtk::Adjustment) -> f64 {
+    let range = adjustment.upper() - adjustment.page_size() - adjustment.lower();
+    if range <= 0.0 {
+        0.0
+    } else {
+        (adjustment.value() - adjustment.lower()) / range
+    }
+}
+
+fn set_scroll_fraction(adjustment: &gtk::Adjustment, fraction: f64, setting_position: &Rc<RefCell<bool>>) {
+    let range = adjustment.upper() - adjustment.page_size() - adjustment.lower();
+    *setting_position.borrow_mut() = true;
+    adjustment.set_value(adjustment.lower() + range.max(0.0) * fraction.clamp(0.0, 1.0));
+    *setting_position.borrow_mut() = false;
+}
+
 const WIDGET_NAME_EVENTBOX: &str = "eventbox";
 /// @widget eventbox
 /// @desc a container which can receive events and must contain exactly one child. Supports `:hover` and `:active` css selectors.
@@ -830,14 +1577,15 @@ fn build_gtk_event_box(bargs: &mut BuilderArgs) -> Result<gtk::EventBox> {
 
     def_widget!(bargs, _g, gtk_widget, {
         // @prop timeout - timeout of the command. Default: "200ms"
-        // @prop onscroll - event to execute when the user scrolls with the mouse over the widget. The placeholder `{}` used in the command will be replaced with either `up` or `down`.
+        // @prop onscroll - event to execute when the user scrolls with the mouse over the widget. The placeholder `{}` (or `{0}`) used in the command will be replaced with either `up` or `down`, and `{1}` with the amount scrolled. For mouse wheels, this is usually 1 per step, while touchpads scroll by smaller, fractional amounts. The amount is negative when scrolling up.
         prop(timeout: as_duration = Duration::from_millis(200), onscroll: as_string) {
             gtk_widget.add_events(gdk::EventMask::SCROLL_MASK);
             gtk_widget.add_events(gdk::EventMask::SMOOTH_SCROLL_MASK);
             connect_signal_handler!(gtk_widget, gtk_widget.connect_scroll_event(move |_, evt| {
                 let delta = evt.delta().1;
                 if delta != 0f64 { // Ignore the first event https://bugzilla.gnome.org/show_bug.cgi?id=675959
-                    run_command(timeout, &onscroll, &[if delta < 0f64 { "up" } else { "down" }]);
+                    let direction = if delta < 0f64 { "up" } else { "down" };
+                    run_command(timeout, &onscroll, &[direction.to_string(), delta.to_string()]);
                 }
                 glib::Propagation::Proceed
             }));
@@ -864,25 +1612,35 @@ fn build_gtk_event_box(bargs: &mut BuilderArgs) -> Result<gtk::EventBox> {
                 glib::Propagation::Proceed
             }));
         },
-        // @prop cursor - Cursor to show while hovering (see [gtk3-cursors](https://docs.gtk.org/gdk3/ctor.Cursor.new_from_name.html) for possible names)
+        // @prop cursor - Cursor to show while hovering, i.e. "pointer", "text", "grab", "not-allowed" or "crosshair" (see [gtk3-cursors](https://docs.gtk.org/gdk3/ctor.Cursor.new_from_name.html) for possible names)
         prop(cursor: as_string) {
             gtk_widget.add_events(gdk::EventMask::ENTER_NOTIFY_MASK);
             gtk_widget.add_events(gdk::EventMask::LEAVE_NOTIFY_MASK);
 
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_enter_notify_event(move |widget, _evt| {
-                if _evt.detail() != NotifyType::Inferior {
-                    let display = gdk::Display::default();
-                    let gdk_window = widget.window();
-                    if let (Some(display), Some(gdk_window)) = (display, gdk_window) {
-                        gdk_window.set_cursor(gdk::Cursor::from_name(&display, &cursor).as_ref());
+            let display = gdk::Display::default();
+            let gdk_cursor = display.as_ref().and_then(|display| gdk::Cursor::from_name(display, &cursor));
+            if display.is_some() && gdk_cursor.is_none() {
+                log::warn!("Cursor `{}` is not known to the cursor theme, the default cursor will be shown instead", cursor);
+            }
+            // When the cursor changes while the pointer is already inside, show the new one right away
+            if gtk_widget.state_flags().contains(gtk::StateFlags::PRELIGHT) {
+                if let Some(gdk_window) = gtk_widget.window() {
+                    gdk_window.set_cursor(gdk_cursor.as_ref());
+                }
+            }
+
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_enter_notify_event(move |widget, evt| {
+                if evt.detail() != NotifyType::Inferior {
+                    if let Some(gdk_window) = widget.window() {
+                        gdk_window.set_cursor(gdk_cursor.as_ref());
                     }
                 }
                 glib::Propagation::Proceed
             }));
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_leave_notify_event(move |widget, _evt| {
-                if _evt.detail() != NotifyType::Inferior {
-                    let gdk_window = widget.window();
-                    if let Some(gdk_window) = gdk_window {
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_leave_notify_event(move |widget, evt| {
+                if evt.detail() != NotifyType::Inferior {
+                    // The eventbox has a window of its own, so unsetting its cursor restores the one of the surrounding window
+                    if let Some(gdk_window) = widget.window() {
                         gdk_window.set_cursor(None);
                     }
                 }
@@ -891,20 +1649,30 @@ fn build_gtk_event_box(bargs: &mut BuilderArgs) -> Result<gtk::EventBox> {
         },
         // @prop timeout - timeout of the command. Default: "200ms"
         // @prop ondropped - Command to execute when something is dropped on top of this element. The placeholder `{}` used in the command will be replaced with the uri to the dropped thing.
-        prop(timeout: as_duration = Duration::from_millis(200), ondropped: as_string) {
-            gtk_widget.drag_dest_set(
-                DestDefaults::ALL,
-                &[
-                    TargetEntry::new("text/uri-list", gtk::TargetFlags::OTHER_APP | gtk::TargetFlags::OTHER_WIDGET, 0),
-                    TargetEntry::new("text/plain", gtk::TargetFlags::OTHER_APP | gtk::TargetFlags::OTHER_WIDGET, 0)
-                ],
-                gdk::DragAction::COPY,
-            );
+        // @prop ondropfile - Command to execute when files are dropped on top of this element. The placeholder `{}` used in the command will be replaced with the space separated uris of all the dropped files.
+        prop(timeout: as_duration = Duration::from_millis(200), ondropped: as_string?, ondropfile: as_string?) {
+            if ondropped.is_none() && ondropfile.is_none() {
+                gtk_widget.drag_dest_unset();
+            } else {
+                let mut targets = vec![TargetEntry::new("text/uri-list", gtk::TargetFlags::OTHER_APP | gtk::TargetFlags::OTHER_WIDGET, 0)];
+                if ondropped.is_some() {
+                    targets.push(TargetEntry::new("text/plain", gtk::TargetFlags::OTHER_APP | gtk::TargetFlags::OTHER_WIDGET, 0));
+                }
+                gtk_widget.drag_dest_set(DestDefaults::ALL, &targets, gdk::DragAction::COPY);
+            }
             connect_signal_handler!(gtk_widget, gtk_widget.connect_drag_data_received(move |_, _, _x, _y, selection_data, _target_type, _timestamp| {
-                if let Some(data) = selection_data.uris().first(){
-                    run_command(timeout, &ondropped, &[data.to_string(), "file".to_string()]);
-                } else if let Some(data) = selection_data.text(){
-                    run_command(timeout, &ondropped, &[data.to_string(), "text".to_string()]);
+                let uris = selection_data.uris();
+                if let Some(ondropfile) = &ondropfile {
+                    if !uris.is_empty() {
+                        run_command(timeout, ondropfile, &[uris.iter().join(" ")]);
+                    }
+                }
+                if let Some(ondropped) = &ondropped {
+                    if let Some(data) = uris.first() {
+                        run_command(timeout, ondropped, &[data.to_string(), "file".to_string()]);
+                    } else if let Some(data) = selection_data.text() {
+                        run_command(timeout, ondropped, &[data.to_string(), "text".to_string()]);
+                    }
                 }
             }));
         },
@@ -943,12 +1711,58 @@ fn build_gtk_event_box(bargs: &mut BuilderArgs) -> Result<gtk::EventBox> {
             // @prop onmiddleclick - command to run when the widget is middleclicked
             onmiddleclick: as_string = "",
             // @prop onrightclick - command to run when the widget is rightclicked
-            onrightclick: as_string = ""
+            onrightclick: as_string = "",
+            // @prop ondoubleclick - command to run when the widget is double clicked
+            ondoubleclick: as_string = "",
+            // @prop onlongpress - command to run when the widget is pressed and held. When this fires, the following release doesn't trigger `onclick`.
+            onlongpress: as_string = "",
+            // @prop longpress-timeout - how long the widget needs to be held for `onlongpress` to fire. Default: "500ms"
+            longpress_timeout: as_duration = Duration::from_millis(500)
         ) {
             gtk_widget.add_events(gdk::EventMask::BUTTON_PRESS_MASK);
+
+            let longpress_timer: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+            let long_pressed = Rc::new(RefCell::new(false));
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_button_press_event({
+                let longpress_timer = longpress_timer.clone();
+                let long_pressed = long_pressed.clone();
+                move |_, evt| {
+                    if evt.button() != 1 {
+                        return glib::Propagation::Proceed;
+                    }
+                    match evt.event_type() {
+                        gdk::EventType::DoubleButtonPress => run_command(timeout, &ondoubleclick, &[] as &[&str]),
+                        gdk::EventType::ButtonPress if !onlongpress.is_empty() => {
+                            *long_pressed.borrow_mut() = false;
+                            if let Some(old_timer) = longpress_timer.borrow_mut().take() {
+                                old_timer.remove();
+                            }
+                            let onlongpress = onlongpress.clone();
+                            let timer_slot = longpress_timer.clone();
+                            let long_pressed = long_pressed.clone();
+                            let timer = glib::timeout_add_local_once(longpress_timeout, move || {
+                                // The timer has fired, so it must not be removed on release anymore.
+                                timer_slot.borrow_mut().take();
+                                *long_pressed.borrow_mut() = true;
+                                run_command(timeout, &onlongpress, &[] as &[&str]);
+                            });
+                            *longpress_timer.borrow_mut() = Some(timer);
+                        }
+                        _ => {}
+                    }
+                    glib::Propagation::Proceed
+                }
+            }));
             connect_signal_handler!(gtk_widget, gtk_widget.connect_button_release_event(move |_, evt| {
                 match evt.button() {
-                    1 => run_command(timeout, &onclick, &[] as &[&str]),
+                    1 => {
+                        if let Some(timer) = longpress_timer.borrow_mut().take() {
+                            timer.remove();
+                        }
+                        if !long_pressed.replace(false) {
+                            run_command(timeout, &onclick, &[] as &[&str]);
+                        }
+                    }
                     2 => run_command(timeout, &onmiddleclick, &[] as &[&str]),
                     3 => run_command(timeout, &onrightclick, &[] as &[&str]),
                     _ => {},
@@ -966,14 +1780,22 @@ const WIDGET_NAME_LABEL: &str = "label";
 fn build_gtk_label(bargs: &mut BuilderArgs) -> Result<gtk::Label> {
     let gtk_widget = gtk::Label::new(None);
 
+    // How far the text has currently scrolled, in pixels, and the tick callback updating it while `scroll` is enabled
+    let scroll_offset = Rc::new(RefCell::new(0.0));
+    let scroll_tick_callback: Rc<RefCell<Option<gtk::TickCallbackId>>> = Rc::new(RefCell::new(None));
+    // Layout of the full text and its width while `scroll` is enabled, cached until the text or style changes
+    let scroll_layout: Rc<RefCell<Option<(pango::Layout, i32)>>> = Rc::new(RefCell::new(None));
+    let scroll_handlers: Rc<RefCell<Vec<glib::SignalHandlerId>>> = Rc::new(RefCell::new(Vec::new()));
+
     def_widget!(bargs, _g, gtk_widget, {
         // @prop text - the text to display
-        // @prop truncate - whether to truncate text (or pango markup). If `show-truncated` is `false`, or if `limit-width` has a value, this property has no effect and truncation is enabled.
+        // @prop truncate - whether to truncate text (or pango markup). Either a boolean, or where to truncate the text: $truncate. If `show-truncated` is `false`, or if `limit-width` has a value, truncation is always enabled.
         // @prop limit-width - maximum count of characters to display
-        // @prop truncate-left - whether to truncate on the left side
+        // @prop truncate-left - whether to truncate on the left side. Same as setting `truncate` to "start".
         // @prop show-truncated - show whether the text was truncated. Disabling it will also disable dynamic truncation (the labels won't be truncated more than `limit-width`, even if there is not enough space for them), and will completly disable truncation on pango markup.
         // @prop unindent - whether to remove leading spaces
-        prop(text: as_string, truncate: as_bool = false, limit_width: as_i32 = i32::MAX, truncate_left: as_bool = false, show_truncated: as_bool = true, unindent: as_bool = true) {
+        prop(text: as_string, truncate: as_string = "false", limit_width: as_i32 = i32::MAX, truncate_left: as_bool = false, show_truncated: as_bool = true, unindent: as_bool = true) {
+            let (truncate, truncate_mode) = parse_truncate(&truncate, truncate_left)?;
             let text = if show_truncated {
                 // gtk does weird thing if we set max_width_chars to i32::MAX
                 if limit_width == i32::MAX {
@@ -982,11 +1804,7 @@ fn build_gtk_label(bargs: &mut BuilderArgs) -> Result<gtk::Label> {
                     gtk_widget.set_max_width_chars(limit_width);
                 }
                 if truncate || limit_width != i32::MAX {
-                    if truncate_left {
-                        gtk_widget.set_ellipsize(pango::EllipsizeMode::Start);
-                    } else {
-                        gtk_widget.set_ellipsize(pango::EllipsizeMode::End);
-                    }
+                    gtk_widget.set_ellipsize(truncate_mode);
                 } else {
                     gtk_widget.set_ellipsize(pango::EllipsizeMode::None);
                 }
@@ -998,10 +1816,13 @@ fn build_gtk_label(bargs: &mut BuilderArgs) -> Result<gtk::Label> {
                 let limit_width = limit_width as usize;
                 let char_count = text.chars().count();
                 if char_count > limit_width {
-                    if truncate_left {
-                        text.chars().skip(char_count - limit_width).collect()
-                    } else {
-                        text.chars().take(limit_width).collect()
+                    match truncate_mode {
+                        pango::EllipsizeMode::Start => text.chars().skip(char_count - limit_width).collect(),
+                        pango::EllipsizeMode::Middle => {
+                            let (start, end) = (limit_width.div_ceil(2), limit_width / 2);
+                            text.chars().take(start).chain(text.chars().skip(char_count - end)).collect()
+                        }
+                        _ => text.chars().take(limit_width).collect(),
                     }
                 } else {
                     text
@@ -1013,11 +1834,12 @@ fn build_gtk_label(bargs: &mut BuilderArgs) -> Result<gtk::Label> {
             gtk_widget.set_text(&text);
         },
         // @prop markup - Pango markup to display
-        // @prop truncate - whether to truncate text (or pango markup). If `show-truncated` is `false`, or if `limit-width` has a value, this property has no effect and truncation is enabled.
+        // @prop truncate - whether to truncate text (or pango markup). Either a boolean, or where to truncate the text: $truncate. If `show-truncated` is `false`, or if `limit-width` has a value, truncation is always enabled.
         // @prop limit-width - maximum count of characters to display
-        // @prop truncate-left - whether to truncate on the left side
+        // @prop truncate-left - whether to truncate on the left side. Same as setting `truncate` to "start".
         // @prop show-truncated - show whether the text was truncated. Disabling it will also disable dynamic truncation (the labels won't be truncated more than `limit-width`, even if there is not enough space for them), and will completly disable truncation on pango markup.
-        prop(markup: as_string, truncate: as_bool = false, limit_width: as_i32 = i32::MAX, truncate_left: as_bool = false, show_truncated: as_bool = true) {
+        prop(markup: as_string, truncate: as_string = "false", limit_width: as_i32 = i32::MAX, truncate_left: as_bool = false, show_truncated: as_bool = true) {
+            let (truncate, truncate_mode) = parse_truncate(&truncate, truncate_left)?;
             if (truncate || limit_width != i32::MAX) && show_truncated {
                 // gtk does weird thing if we set max_width_chars to i32::MAX
                 if limit_width == i32::MAX {
@@ -1026,17 +1848,28 @@ fn build_gtk_label(bargs: &mut BuilderArgs) -> Result<gtk::Label> {
                     gtk_widget.set_max_width_chars(limit_width);
                 }
 
-                if truncate_left {
-                    gtk_widget.set_ellipsize(pango::EllipsizeMode::Start);
-                } else {
-                    gtk_widget.set_ellipsize(pango::EllipsizeMode::End);
-                }
+                gtk_widget.set_ellipsize(truncate_mode);
             } else {
                 gtk_widget.set_ellipsize(pango::EllipsizeMode::None);
             }
 
             gtk_widget.set_markup(&markup);
         },
+        // @prop activate-links - whether links (`<a href="...">`) in the `markup` can be clicked. Unless `onlink` is given, they are opened with the default application. Default: false
+        // @prop onlink - command to run when a link is clicked, instead of opening it. `{}` is replaced by the href of the link.
+        // @prop timeout - timeout of the command. Default: "200ms"
+        prop(activate_links: as_bool = false, onlink: as_string = "", timeout: as_duration = Duration::from_millis(200)) {
+            connect_signal_handler!(gtk_widget, gtk_widget.connect_activate_link(move |_, uri| {
+                if !activate_links {
+                    return glib::Propagation::Stop;
+                }
+                if onlink.is_empty() {
+                    return glib::Propagation::Proceed;
+                }
+                run_command(timeout, &onlink, &[uri.to_string()]);
+                glib::Propagation::Stop
+            }));
+        },
         // @prop wrap - Wrap the text. This mainly makes sense if you set the width of this widget.
         prop(wrap: as_bool) { gtk_widget.set_line_wrap(wrap) },
         // @prop angle - the angle of rotation for the label (between 0 - 360)
@@ -1060,11 +1893,96 @@ fn build_gtk_label(bargs: &mut BuilderArgs) -> Result<gtk::Label> {
         // @prop lines - maximum number of lines to display (only works when `limit-width` has a value). A value of -1 (default) disables the limit.
         prop(lines: as_i32 = -1) {
             gtk_widget.set_lines(lines);
+        },
+        // @prop scroll - scroll text that doesn't fit into the label instead of truncating it. The label only gets smaller than its text when `truncate` or `limit-width` are used.
+        // @prop scroll-speed - how fast the text scrolls, in pixels per second
+        prop(scroll: as_bool = false, scroll_speed: as_f64 = 30) {
+            if let Some(tick_callback) = scroll_tick_callback.take() {
+                tick_callback.remove();
+            }
+            for handler_id in scroll_handlers.take() {
+                gtk_widget.disconnect(handler_id);
+            }
+            *scroll_offset.borrow_mut() = 0.0;
+            scroll_layout.take();
+            if !scroll {
+                return Ok(());
+            }
+
+            let tick_callback = gtk_widget.add_tick_callback({
+                let scroll_offset = scroll_offset.clone();
+                let scroll_layout = scroll_layout.clone();
+                let started_at = RefCell::new(None);
+                move |gtk_widget, frame_clock| {
+                    // frame times are given in microseconds
+                    let frame_time = frame_clock.frame_time();
+                    let started_at = *started_at.borrow_mut().get_or_insert(frame_time);
+                    *scroll_offset.borrow_mut() = (frame_time - started_at) as f64 / 1_000_000.0 * scroll_speed;
+                    if scrolling_label_layout(gtk_widget, &scroll_layout).1 > gtk_widget.allocated_width() {
+                        gtk_widget.queue_draw();
+                    }
+                    glib::ControlFlow::Continue
+                }
+            });
+            scroll_tick_callback.replace(Some(tick_callback));
+
+            let mut handlers = scroll_handlers.borrow_mut();
+            handlers.push(gtk_widget.connect_notify_local(None, {
+                let scroll_layout = scroll_layout.clone();
+                move |_, _| {
+                    scroll_layout.take();
+                }
+            }));
+            handlers.push(gtk_widget.connect_style_updated({
+                let scroll_layout = scroll_layout.clone();
+                move |_| {
+                    scroll_layout.take();
+                }
+            }));
+            let scroll_offset = scroll_offset.clone();
+            let scroll_layout = scroll_layout.clone();
+            handlers.push(gtk_widget.connect_draw(move |gtk_widget, cr| {
+                let (layout, text_width) = scrolling_label_layout(gtk_widget, &scroll_layout);
+                if text_width <= gtk_widget.allocated_width() {
+                    return glib::Propagation::Proceed;
+                }
+                // The layout offsets are relative to the parent window, but we draw relative to the label itself
+                let y = gtk_widget.layout_offsets().1 - gtk_widget.allocation().y();
+                let loop_width = (text_width + LABEL_SCROLL_GAP) as f64;
+                let x = -(*scroll_offset.borrow() % loop_width);
+                let styles = gtk_widget.style_context();
+                gtk::render_layout(&styles, cr, x, y as f64, &layout);
+                gtk::render_layout(&styles, cr, x + loop_width, y as f64, &layout);
+                glib::Propagation::Stop
+            }));
         }
     });
     Ok(gtk_widget)
 }
 
+/// Space between the end and the start of the text of a scrolling label, in pixels
+const LABEL_SCROLL_GAP: i32 = 32;
+
+/// Get the layout of a label as it would look if it were neither truncated nor limited in width
+fn untruncated_label_layout(label: &gtk::Label) -> pango::Layout {
+    let layout = label.layout().copy();
+    layout.set_ellipsize(pango::EllipsizeMode::None);
+    layout.set_width(-1);
+    layout
+}
+
+/// Get the untruncated layout of a scrolling label and the width of its text, only building it again once the cache got cleared
+fn scrolling_label_layout(label: &gtk::Label, cache: &RefCell<Option<(pango::Layout, i32)>>) -> (pango::Layout, i32) {
+    cache
+        .borrow_mut()
+        .get_or_insert_with(|| {
+            let layout = untruncated_label_layout(label);
+            let text_width = layout.pixel_size().0;
+            (layout, text_width)
+        })
+        .clone()
+}
+
 const WIDGET_NAME_LITERAL: &str = "literal";
 /// @widget literal
 /// @desc A widget that allows you to render arbitrary yuck.
@@ -1149,15 +2067,37 @@ fn build_gtk_calendar(bargs: &mut BuilderArgs) -> Result<gtk::Calendar> {
         // @prop show-week-numbers - show week numbers
         prop(show_week_numbers: as_bool) { gtk_widget.set_show_week_numbers(show_week_numbers) },
         // @prop onclick - command to run when the user selects a date. The `{0}` placeholder will be replaced by the selected day, `{1}` will be replaced by the month, and `{2}` by the year.
-        // @prop timeout - timeout of the command. Default: "200ms"
-        prop(timeout: as_duration = Duration::from_millis(200), onclick: as_string) {
-            connect_signal_handler!(gtk_widget, gtk_widget.connect_day_selected(move |w| {
-                run_command(
-                    timeout,
-                    &onclick,
-                    &[w.day(), w.month(), w.year()]
-                )
-            }));
+        // @prop onmonthchange - command to run when the shown month changes. The `{0}` placeholder will be replaced by the month (1 - 12), and `{1}` by the year.
+        // @prop timeout - timeout of the commands. Default: "200ms"
+        prop(timeout: as_duration = Duration::from_millis(200), onclick: as_string?, onmonthchange: as_string?) {
+            if let Some(onclick) = onclick {
+                connect_signal_handler!(gtk_widget, gtk_widget.connect_day_selected(move |w| {
+                    run_command(
+                        timeout,
+                        &onclick,
+                        &[w.day(), w.month(), w.year()]
+                    )
+                }));
+            }
+            if let Some(onmonthchange) = onmonthchange {
+                connect_signal_handler!(gtk_widget, gtk_widget.connect_month_changed(move |w| {
+                    run_command(
+                        timeout,
+                        &onmonthchange,
+                        &[w.month() + 1, w.year()]
+                    )
+                }));
+            }
+        },
+        // @prop marked-days - json array of the days of the month that should be marked. The marks stay when the shown month changes, so use `onmonthchange` to update them.
+        prop(marked_days: as_json_array) {
+            gtk_widget.clear_marks();
+            for day in marked_days {
+                match day.as_u64() {
+                    Some(day @ 1..=31) => gtk_widget.mark_day(day as u32),
+                    _ => log::warn!("Calendar marked day {} is not a number between 1 and 31", day),
+                }
+            }
         }
 
     });
@@ -1194,8 +2134,10 @@ fn build_gtk_stack(bargs: &mut BuilderArgs) -> Result<gtk::Stack> {
     def_widget!(bargs, _g, gtk_widget, {
         // @prop selected - index of child which should be shown
         prop(selected: as_i32) { gtk_widget.set_visible_child_name(&selected.to_string()); },
-        // @prop transition - the name of the transition. Possible values: $transition
+        // @prop transition - the name of the transition used when the selected child changes. Possible values: $stack_transition
         prop(transition: as_string = "crossfade") { gtk_widget.set_transition_type(parse_stack_transition(&transition)?); },
+        // @prop transition-duration - the duration of the transition. Default: "200ms"
+        prop(transition_duration: as_duration = Duration::from_millis(200)) { gtk_widget.set_transition_duration(transition_duration.as_millis() as u32); },
         // @prop same-size - sets whether all children should be the same size
         prop(same_size: as_bool = false) { gtk_widget.set_homogeneous(same_size); }
     });
@@ -1223,6 +2165,24 @@ fn build_transform(bargs: &mut BuilderArgs) -> Result<Transform> {
         prop(scale_x: as_string) { w.set_property("scale-x", scale_x); },
         // @prop scale-y - the amount to scale in the y direction (px or %)
         prop(scale_y: as_string) { w.set_property("scale-y", scale_y); },
+        // @prop transition-duration - how long to transition to new values for, instead of changing instantly. Default: "0ms"
+        prop(transition_duration: as_duration) { w.set_property("transition-duration", transition_duration.as_millis() as u64); },
+    });
+    Ok(w)
+}
+
+const WIDGET_NAME_TICKER: &str = "ticker";
+/// @widget ticker
+/// @desc A widget that continuously scrolls its child from right to left, repeating it once it scrolled out.
+fn build_ticker(bargs: &mut BuilderArgs) -> Result<Ticker> {
+    let w = Ticker::new();
+    def_widget!(bargs, _g, w, {
+        // @prop speed - how fast to scroll, in pixels per second. Default: 30
+        prop(speed: as_f64) { w.set_property("speed", speed.max(0.0)); },
+        // @prop gap - space between the end of the child and its next repetition, in pixels. Default: 32
+        prop(gap: as_f64) { w.set_property("gap", gap.max(0.0)); },
+        // @prop pause-on-hover - whether to stop scrolling while the pointer is over the ticker. Default: true
+        prop(pause_on_hover: as_bool) { w.set_property("pause-on-hover", pause_on_hover); },
     });
     Ok(w)
 }
@@ -1235,12 +2195,19 @@ fn build_circular_progress_bar(bargs: &mut BuilderArgs) -> Result<CircProg> {
     def_widget!(bargs, _g, w, {
         // @prop value - the value, between 0 - 100
         prop(value: as_f64) { w.set_property("value", value.clamp(0.0, 100.0)); },
-        // @prop start-at - the percentage that the circle should start at
-        prop(start_at: as_f64) { w.set_property("start-at", start_at.clamp(0.0, 100.0)); },
+        // @prop start-at - the percentage that the circle should start at. Values outside of 0 - 100 wrap around, so -25 and 75 are the same.
+        prop(start_at: as_f64) { w.set_property("start-at", start_at.rem_euclid(100.0)); },
         // @prop thickness - the thickness of the circle
         prop(thickness: as_f64) { w.set_property("thickness", thickness); },
         // @prop clockwise - wether the progress bar spins clockwise or counter clockwise
         prop(clockwise: as_bool) { w.set_property("clockwise", clockwise); },
+        // @prop color-stops - a json array of colors the ring goes through as the value rises from 0 to 100, i.e. `'["green", "yellow", "red"]'`. Overrides the css `color`.
+        // @prop gradient - color the ring with a gradient through the `color-stops`, instead of a single color depending on the value
+        prop(color_stops: as_json_array, gradient: as_bool = false) {
+            parse_color_stops(&color_stops)?;
+            w.set_property("color-stops", serde_json::to_string(&color_stops)?);
+            w.set_property("gradient", gradient);
+        },
     });
     Ok(w)
 }
@@ -1249,6 +2216,17 @@ const WIDGET_NAME_GRAPH: &str = "graph";
 /// @widget graph
 /// @desc A widget that displays a graph showing how a given value changes over time
 fn build_graph(bargs: &mut BuilderArgs) -> Result<super::graph::Graph> {
+    // Both would add their values to the same history
+    if let (Some(value), Some(series)) = (bargs.widget_use.attrs.attrs.get("value"), bargs.widget_use.attrs.attrs.get("series")) {
+        return Err(DiagError(
+            gen_diagnostic! {
+                msg = "graph can't have both a `value` and a `series`",
+                label = series.key_span => "`series` given here",
+            }
+            .with_label(span_to_secondary_label(value.key_span).with_message("`value` given here")),
+        )
+        .into());
+    }
     let w = super::graph::Graph::new();
     def_widget!(bargs, _g, w, {
         // @prop value - the value, between 0 - 100
@@ -1260,6 +2238,25 @@ fn build_graph(bargs: &mut BuilderArgs) -> Result<super::graph::Graph> {
             }
             w.set_property("value", value);
         },
+        // @prop series - a json array of values, each drawn as its own line. Use this instead of `value` to draw multiple lines, the two can't be combined.
+        prop(series: as_json_array) {
+            let series = series
+                .iter()
+                .map(|value| value.as_f64().filter(|value| value.is_finite()))
+                .collect::<Option<Vec<f64>>>()
+                .ok_or_else(|| DiagError(gen_diagnostic!(
+                    format!("Graph's series should be an array of numbers, none of which may be NaN or infinite")
+                )))?;
+            w.set_property("series", serde_json::to_string(&series)?);
+        },
+        // @prop colors - a json array of colors for the lines in `series`. Lines without a color use the css `color`.
+        prop(colors: as_json_array) {
+            for color in &colors {
+                let color = color.as_str().context("Graph's colors should be an array of strings")?;
+                color::parse_color(color).with_context(|| format!("Invalid color `{color}` in graph's colors"))?;
+            }
+            w.set_property("colors", serde_json::to_string(&colors)?);
+        },
         // @prop thickness - the thickness of the line
         prop(thickness: as_f64) { w.set_property("thickness", thickness); },
         // @prop time-range - the range of time to show
@@ -1286,6 +2283,31 @@ fn build_graph(bargs: &mut BuilderArgs) -> Result<super::graph::Graph> {
         prop(flip_y: as_bool) { w.set_property("flip-y", flip_y); },
         // @prop vertical - if set to true, the x and y axes will be exchanged
         prop(vertical: as_bool) { w.set_property("vertical", vertical); },
+        // @prop continuous - whether the graph should keep scrolling, holding the last value, while no new values arrive
+        prop(continuous: as_bool) { w.set_property("continuous", continuous); },
+        // @prop scale-mode - how values are mapped to the height of the graph: $graph_scale_mode. In `log` mode, values are mapped to `log10(value + 1)`, keeping small values visible next to large ones.
+        prop(scale_mode: as_string) { w.set_property("log-scale", parse_graph_scale_mode(&scale_mode)?); },
+        // @prop fill - whether to fill the area between each line and the `baseline`
+        prop(fill: as_bool) { w.set_property("fill", fill); },
+        // @prop fill-color - the color of the fill. Defaults to a translucent version of the line's color.
+        prop(fill_color: as_string) {
+            color::parse_color(&fill_color).with_context(|| format!("Invalid fill-color `{fill_color}` in graph"))?;
+            w.set_property("fill-color", fill_color);
+        },
+        // @prop fill-gradient - a json array of colors the fill goes through, from the top of the graph down to the `baseline`, i.e. `'["#89b4fa", "transparent"]'`. Overrides `fill-color`.
+        prop(fill_gradient: as_json_array) {
+            parse_color_stops(&fill_gradient)?;
+            w.set_property("fill-gradient", serde_json::to_string(&fill_gradient)?);
+        },
+        // @prop baseline - the value the fill extends to (default: 0)
+        prop(baseline: as_f64) {
+            if baseline.is_nan() || baseline.is_infinite() {
+                return Err(DiagError(gen_diagnostic!(
+                    format!("Graph's baseline should never be NaN or infinite")
+                )).into());
+            }
+            w.set_property("baseline", baseline);
+        },
     });
     Ok(w)
 }
@@ -1346,25 +2368,95 @@ fn parse_dragtype(o: &str) -> Result<DragEntryType> {
     }
 }
 
-/// @var transition - "slideright", "slideleft", "slideup", "slidedown", "crossfade", "none"
-fn parse_revealer_transition(t: &str) -> Result<gtk::RevealerTransitionType> {
+/// @var color_format - "hex", "rgb", "rgba", "hsl"
+fn parse_color_format(o: &str) -> Result<ColorFormat> {
+    enum_parse! { "color format", o,
+        "hex" => ColorFormat::Hex,
+        "rgb" => ColorFormat::Rgb,
+        "rgba" => ColorFormat::Rgba,
+        "hsl" => ColorFormat::Hsl,
+    }
+}
+
+/// @var graph_scale_mode - "linear", "log"
+/// Returns whether the graph should use a logarithmic scale
+fn parse_graph_scale_mode(o: &str) -> Result<bool> {
+    enum_parse! { "scale mode", o,
+        "linear" => false,
+        "log" => true,
+    }
+}
+
+/// @var checkbox_state - "on", "off", "mixed"
+/// Returns whether the checkbox is active, or `None` if it's inconsistent
+fn parse_checkbox_state(o: &str) -> Result<Option<bool>> {
+    enum_parse! { "checkbox state", o,
+        "on" => Some(true),
+        "off" => Some(false),
+        "mixed" => None,
+    }
+}
+
+/// @var revealer_transition - "slideright", "slideleft", "slideup", "slidedown", "slidefade-right", "slidefade-left", "slidefade-up", "slidefade-down", "crossfade", "none"
+/// Returns the gtk transition, and whether the revealer should additionally fade while sliding
+fn parse_revealer_transition(t: &str) -> Result<(gtk::RevealerTransitionType, bool)> {
     enum_parse! { "transition", t,
-        "slideright" => gtk::RevealerTransitionType::SlideRight,
-        "slideleft" => gtk::RevealerTransitionType::SlideLeft,
-        "slideup" => gtk::RevealerTransitionType::SlideUp,
-        "slidedown" => gtk::RevealerTransitionType::SlideDown,
-        "fade" | "crossfade" => gtk::RevealerTransitionType::Crossfade,
-        "none" => gtk::RevealerTransitionType::None,
+        "slideright" => (gtk::RevealerTransitionType::SlideRight, false),
+        "slideleft" => (gtk::RevealerTransitionType::SlideLeft, false),
+        "slideup" => (gtk::RevealerTransitionType::SlideUp, false),
+        "slidedown" => (gtk::RevealerTransitionType::SlideDown, false),
+        "slidefade-right" => (gtk::RevealerTransitionType::SlideRight, true),
+        "slidefade-left" => (gtk::RevealerTransitionType::SlideLeft, true),
+        "slidefade-up" => (gtk::RevealerTransitionType::SlideUp, true),
+        "slidefade-down" => (gtk::RevealerTransitionType::SlideDown, true),
+        "fade" | "crossfade" => (gtk::RevealerTransitionType::Crossfade, false),
+        "none" => (gtk::RevealerTransitionType::None, false),
     }
 }
 
-/// @var transition - "slideright", "slideleft", "slideup", "slidedown", "crossfade", "none"
+/// Fade a revealer in or out over the duration of its transition, starting from its current opacity.
+fn animate_revealer_fade(revealer: &gtk::Revealer, reveal: bool, tick_callback: &Rc<RefCell<Option<gtk::TickCallbackId>>>) {
+    if let Some(tick_callback) = tick_callback.take() {
+        tick_callback.remove();
+    }
+    let start_opacity = revealer.opacity();
+    let target_opacity = if reveal { 1.0 } else { 0.0 };
+    // frame times are given in microseconds
+    let duration = revealer.transition_duration() as f64 * 1000.0;
+    let started_at = RefCell::new(None);
+    let new_tick_callback = revealer.add_tick_callback(move |revealer, frame_clock| {
+        let frame_time = frame_clock.frame_time();
+        let started_at = *started_at.borrow_mut().get_or_insert(frame_time);
+        let progress = if duration > 0.0 { ((frame_time - started_at) as f64 / duration).min(1.0) } else { 1.0 };
+        revealer.set_opacity(start_opacity + (target_opacity - start_opacity) * progress);
+        if progress < 1.0 {
+            glib::ControlFlow::Continue
+        } else {
+            glib::ControlFlow::Break
+        }
+    });
+    tick_callback.replace(Some(new_tick_callback));
+}
+
+/// @var stack_transition - "slideright", "slideleft", "slideup", "slidedown", "slide-left-right", "slide-up-down", "over-up", "over-down", "over-left", "over-right", "over-up-down", "over-left-right", "under-up", "under-down", "under-left", "under-right", "crossfade", "none"
 fn parse_stack_transition(t: &str) -> Result<gtk::StackTransitionType> {
     enum_parse! { "transition", t,
         "slideright" => gtk::StackTransitionType::SlideRight,
         "slideleft" => gtk::StackTransitionType::SlideLeft,
         "slideup" => gtk::StackTransitionType::SlideUp,
         "slidedown" => gtk::StackTransitionType::SlideDown,
+        "slide-left-right" => gtk::StackTransitionType::SlideLeftRight,
+        "slide-up-down" => gtk::StackTransitionType::SlideUpDown,
+        "over-up" => gtk::StackTransitionType::OverUp,
+        "over-down" => gtk::StackTransitionType::OverDown,
+        "over-left" => gtk::StackTransitionType::OverLeft,
+        "over-right" => gtk::StackTransitionType::OverRight,
+        "over-up-down" => gtk::StackTransitionType::OverUpDown,
+        "over-left-right" => gtk::StackTransitionType::OverLeftRight,
+        "under-up" => gtk::StackTransitionType::UnderUp,
+        "under-down" => gtk::StackTransitionType::UnderDown,
+        "under-left" => gtk::StackTransitionType::UnderLeft,
+        "under-right" => gtk::StackTransitionType::UnderRight,
         "fade" | "crossfade" => gtk::StackTransitionType::Crossfade,
         "none" => gtk::StackTransitionType::None,
     }
@@ -1421,6 +2513,20 @@ fn parse_wrap_mode(w: &str) -> Result<gtk::pango::WrapMode> {
     }
 }
 
+/// Parse the `truncate` property of labels into whether truncation is enabled, and where text should be truncated.
+/// A plain boolean truncates at the end, or at the start if `truncate_left` is set.
+/// @var truncate - "start", "middle", "end"
+fn parse_truncate(t: &str, truncate_left: bool) -> Result<(bool, pango::EllipsizeMode)> {
+    let side = if truncate_left { pango::EllipsizeMode::Start } else { pango::EllipsizeMode::End };
+    enum_parse! { "truncate", t,
+        "false" => (false, side),
+        "true" => (true, side),
+        "start" => (true, pango::EllipsizeMode::Start),
+        "middle" => (true, pango::EllipsizeMode::Middle),
+        "end" => (true, pango::EllipsizeMode::End),
+    }
+}
+
 /// Connect a function to the first map event of a widget. After that first map, the handler will get disconnected.
 fn connect_first_map<W: IsA<gtk::Widget>, F: Fn(&W) + 'static>(widget: &W, func: F) {
     let signal_handler_id = std::rc::Rc::new(std::cell::RefCell::new(None));
@@ -1,6 +1,6 @@
 use gtk::glib::{self, object_subclass, wrapper, Properties};
 use gtk::{prelude::*, subclass::prelude::*};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 wrapper! {
     pub struct Window(ObjectSubclass<WindowPriv>)
@@ -15,12 +15,15 @@ pub struct WindowPriv {
 
     #[property(get, name = "y", nick = "Y", blurb = "Global y coordinate", default = 0)]
     y: RefCell<i32>,
+
+    /// Offset from the window's regular position while it slides in or out, see `:open-transition`
+    slide_offset: Cell<(i32, i32)>,
 }
 
 // This should match the default values from the ParamSpecs
 impl Default for WindowPriv {
     fn default() -> Self {
-        WindowPriv { x: RefCell::new(0), y: RefCell::new(0) }
+        WindowPriv { x: RefCell::new(0), y: RefCell::new(0), slide_offset: Cell::new((0, 0)) }
     }
 }
 
@@ -46,6 +49,14 @@ impl Window {
         priv_.y.replace(y_);
         w
     }
+
+    pub fn slide_offset(&self) -> (i32, i32) {
+        self.imp().slide_offset.get()
+    }
+
+    pub fn set_slide_offset(&self, offset: (i32, i32)) {
+        self.imp().slide_offset.set(offset);
+    }
 }
 
 impl ObjectImpl for WindowPriv {
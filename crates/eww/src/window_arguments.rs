@@ -1,4 +1,4 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use eww_shared_util::VarName;
 use simplexpr::dynval::DynVal;
 use std::{
@@ -7,6 +7,8 @@ use std::{
 };
 use yuck::{
     config::{monitor::MonitorIdentifier, window_definition::WindowDefinition, window_geometry::AnchorPoint},
+    error::DiagError,
+    format_diagnostic::ToDiagnostic,
     value::Coords,
 };
 
@@ -71,13 +73,20 @@ impl WindowArguments {
 
         for attr in &window_def.expected_args {
             let name = VarName::from(attr.name.clone());
-            if !local_variables.contains_key(&name) && !attr.optional {
+            if local_variables.contains_key(&name) {
+                continue;
+            }
+            if let Some(default) = &attr.default {
+                let value = default.eval_no_vars().map_err(|err| anyhow!(DiagError(err.to_diagnostic())))?;
+                local_variables.insert(name, value);
+            } else if !attr.optional {
                 bail!("Error, missing argument '{}' when creating window with id '{}'", attr.name, self.instance_id);
             }
         }
 
-        if local_variables.len() != window_def.expected_args.len() {
-            let unexpected_vars: Vec<_> = local_variables.keys().filter(|&n| !expected_args.contains(&n.0)).cloned().collect();
+        // Optional arguments without a default that weren't given stay undefined, so the number of variables may be lower
+        let unexpected_vars: Vec<_> = local_variables.keys().filter(|&n| !expected_args.contains(&n.0)).cloned().collect();
+        if !unexpected_vars.is_empty() {
             bail!(
                 "variables {} unexpectedly defined when creating window with id '{}'",
                 unexpected_vars.join(", "),
@@ -88,3 +97,42 @@ impl WindowArguments {
         Ok(local_variables)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use yuck::parser::{from_ast::FromAst, parse_string};
+
+    fn window_definition(code: &str) -> WindowDefinition {
+        WindowDefinition::from_ast(parse_string(0, code).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_default_arguments() {
+        let def = window_definition(r#"(defwindow foo [a ?b c: "default" d: {1 + 1}] "content")"#);
+        let args = maplit::hashmap! {
+            VarName::from("a") => DynVal::from("given"),
+            VarName::from("c") => DynVal::from("overridden"),
+        };
+        let window_args = WindowArguments::new_from_args("foo".to_string(), "foo".to_string(), args).unwrap();
+        let vars = window_args.get_local_window_variables(&def).unwrap();
+        assert_eq!(
+            maplit::hashmap! {
+                VarName::from("a") => DynVal::from("given"),
+                VarName::from("c") => DynVal::from("overridden"),
+                VarName::from("d") => DynVal::from(2),
+            },
+            vars
+        );
+
+        let window_args = WindowArguments::new_from_args("foo".to_string(), "foo".to_string(), HashMap::new()).unwrap();
+        assert!(window_args.get_local_window_variables(&def).is_err());
+
+        let args = maplit::hashmap! {
+            VarName::from("a") => DynVal::from("given"),
+            VarName::from("e") => DynVal::from("unexpected"),
+        };
+        let window_args = WindowArguments::new_from_args("foo".to_string(), "foo".to_string(), args).unwrap();
+        assert!(window_args.get_local_window_variables(&def).is_err());
+    }
+}
@@ -1,46 +1,87 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use eww_shared_util::{AttrName, VarName};
 use simplexpr::dynval::DynVal;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 use yuck::config::{
-    backend_window_options::BackendWindowOptions,
+    backend_window_options::{BackendWindowOptions, WlWindowFocusable},
     monitor::MonitorIdentifier,
-    window_definition::{WindowDefinition, WindowStacking},
+    window_definition::{WindowDefinition, WindowStacking, WindowTransition},
     window_geometry::WindowGeometry,
 };
 
-use crate::window_arguments::WindowArguments;
+use crate::{keybinds::Keybind, window_arguments::WindowArguments};
 
 /// This stores all the information required to create a window and is created
 /// via combining information from the [`WindowDefinition`] and the [`WindowInitiator`]
 #[derive(Debug, Clone)]
 pub struct WindowInitiator {
+    /// Id of the window this window is positioned relative to
+    pub anchor_to: Option<String>,
     pub backend_options: BackendWindowOptions,
+    pub close_on_unfocus: bool,
     pub geometry: Option<WindowGeometry>,
+    pub keybinds: Vec<Keybind>,
     pub local_variables: HashMap<VarName, DynVal>,
+    /// Names of the variables declared in `:locals`, as opposed to the arguments of the window
+    pub local_names: HashSet<VarName>,
     pub monitor: Option<MonitorIdentifier>,
     pub name: String,
     pub resizable: bool,
     pub stacking: WindowStacking,
+    pub open_transition: WindowTransition,
+    pub close_transition: WindowTransition,
+    pub transition_duration: Duration,
+    pub z_index: i32,
 }
 
 impl WindowInitiator {
     pub fn new(window_def: &WindowDefinition, args: &WindowArguments) -> Result<Self> {
-        let vars = args.get_local_window_variables(window_def)?;
+        let mut vars = args.get_local_window_variables(window_def)?;
+        let mut local_names = HashSet::new();
+        for (name, value) in window_def.eval_locals(&vars)? {
+            if vars.contains_key(&name) {
+                bail!("Window-local variable `{}` of window {} has the same name as one of its arguments", name, window_def.name);
+            }
+            local_names.insert(name.clone());
+            vars.insert(name, value);
+        }
 
         let geometry = match &window_def.geometry {
             Some(geo) => Some(geo.eval(&vars)?.override_if_given(args.anchor, args.pos, args.size)),
             None => None,
         };
         let monitor = if args.monitor.is_none() { window_def.eval_monitor(&vars)? } else { args.monitor.clone() };
+        let anchor_to = window_def.geometry.as_ref().map(|geo| geo.eval_anchor_to(&vars)).transpose()?.flatten();
+        let keybinds = window_def
+            .eval_keybinds(&vars)?
+            .into_iter()
+            .map(|(combo, action)| Keybind::parse(&combo, &action))
+            .collect::<Result<_>>()?;
+        let close_on_unfocus = window_def.eval_close_on_unfocus(&vars)?;
+        let mut backend_options = window_def.backend_options.eval(&vars)?;
+        // On Wayland, a window only loses focus if it could receive it in the first place
+        if close_on_unfocus && backend_options.wayland.focusable == WlWindowFocusable::None {
+            backend_options.wayland.focusable = WlWindowFocusable::OnDemand;
+        }
         Ok(WindowInitiator {
-            backend_options: window_def.backend_options.eval(&vars)?,
+            anchor_to,
+            backend_options,
+            close_on_unfocus,
             geometry,
+            keybinds,
             monitor,
             name: window_def.name.clone(),
             resizable: window_def.eval_resizable(&vars)?,
             stacking: window_def.eval_stacking(&vars)?,
+            open_transition: window_def.eval_open_transition(&vars)?,
+            close_transition: window_def.eval_close_transition(&vars)?,
+            transition_duration: window_def.eval_transition_duration(&vars)?,
+            z_index: window_def.eval_z_index(&vars)?,
             local_variables: vars,
+            local_names,
         })
     }
 
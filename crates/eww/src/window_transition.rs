@@ -0,0 +1,90 @@
+//! Animations shown when opening or closing a window, see `:open-transition` and `:close-transition`.
+
+use std::{cell::RefCell, time::Duration};
+
+use gtk::{glib, prelude::*};
+use yuck::config::window_definition::WindowTransition;
+
+use crate::widgets::window::Window;
+
+/// Animate the window into view. Must be called once the window has been shown.
+pub fn animate_open(window: &Window, transition: WindowTransition, duration: Duration) {
+    animate(window, transition, duration, false, |_| {});
+}
+
+/// Animate the window out of view, calling `on_done` once the animation has finished.
+pub fn animate_close(window: &Window, transition: WindowTransition, duration: Duration, on_done: impl FnOnce(&Window) + 'static) {
+    animate(window, transition, duration, true, on_done);
+}
+
+/// Slides are only supported where eww can position windows itself, i.e. on X11.
+/// Elsewhere, windows fade instead, provided the compositor supports transparency.
+pub fn supported_transition(transition: WindowTransition, is_x11: bool) -> WindowTransition {
+    if transition.is_slide() && !is_x11 {
+        WindowTransition::Fade
+    } else {
+        transition
+    }
+}
+
+fn animate(
+    window: &Window,
+    transition: WindowTransition,
+    duration: Duration,
+    closing: bool,
+    on_done: impl FnOnce(&Window) + 'static,
+) {
+    if transition == WindowTransition::None || duration.is_zero() {
+        on_done(window);
+        return;
+    }
+    // Position the window would have without any slide offset
+    let (offset_x, offset_y) = window.slide_offset();
+    let base_position = window.window().map(|gdk_window| {
+        let (_, x, y) = gdk_window.origin();
+        (x - offset_x, y - offset_y)
+    });
+
+    // progress goes from 0 (out of view) to 1 (fully shown)
+    let apply = {
+        let window = window.clone();
+        move |progress: f64| {
+            if transition == WindowTransition::Fade {
+                window.set_opacity(progress);
+                return;
+            }
+            let (width, height) = window.size();
+            let remaining = 1.0 - progress;
+            let offset = match transition {
+                WindowTransition::SlideUp => (0, (height as f64 * remaining) as i32),
+                WindowTransition::SlideDown => (0, -(height as f64 * remaining) as i32),
+                WindowTransition::SlideLeft => ((width as f64 * remaining) as i32, 0),
+                WindowTransition::SlideRight => (-(width as f64 * remaining) as i32, 0),
+                WindowTransition::None | WindowTransition::Fade => (0, 0),
+            };
+            window.set_slide_offset(offset);
+            if let (Some(gdk_window), Some((x, y))) = (window.window(), base_position) {
+                gdk_window.move_(x + offset.0, y + offset.1);
+            }
+        }
+    };
+
+    apply(if closing { 1.0 } else { 0.0 });
+    let started_at = RefCell::new(None);
+    let on_done = RefCell::new(Some(on_done));
+    window.add_tick_callback(move |window, frame_clock| {
+        // frame times are given in microseconds
+        let frame_time = frame_clock.frame_time();
+        let started_at = *started_at.borrow_mut().get_or_insert(frame_time);
+        let t = ((frame_time - started_at) as f64 / duration.as_micros() as f64).min(1.0);
+        let eased = 1.0 - (1.0 - t).powi(3);
+        apply(if closing { 1.0 - eased } else { eased });
+        if t < 1.0 {
+            return glib::ControlFlow::Continue;
+        }
+        if let Some(on_done) = on_done.take() {
+            on_done(window);
+        }
+        glib::ControlFlow::Break
+    });
+}
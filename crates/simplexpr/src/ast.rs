@@ -52,6 +52,8 @@ pub enum SimplExpr {
     IfElse(Span, Box<SimplExpr>, Box<SimplExpr>, Box<SimplExpr>),
     JsonAccess(Span, AccessType, Box<SimplExpr>, Box<SimplExpr>),
     FunctionCall(Span, String, Vec<SimplExpr>),
+    /// A function taking a single parameter, i.e. `x => x * 2`. Only valid as an argument to `map` and `filter`.
+    Lambda(Span, VarName, Box<SimplExpr>),
 }
 
 impl std::fmt::Display for SimplExpr {
@@ -77,6 +79,7 @@ impl std::fmt::Display for SimplExpr {
             SimplExpr::FunctionCall(_, function_name, args) => {
                 write!(f, "{}({})", function_name, args.iter().join(", "))
             }
+            SimplExpr::Lambda(_, param, body) => write!(f, "{} => {}", param, body),
             SimplExpr::JsonArray(_, values) => write!(f, "[{}]", values.iter().join(", ")),
             SimplExpr::JsonObject(_, entries) => {
                 write!(f, "{{{}}}", entries.iter().map(|(k, v)| format!("{}: {}", k, v)).join(", "))
@@ -112,6 +115,7 @@ impl SimplExpr {
             JsonAccess(_, _, a, b) | BinOp(_, a, _, b) => a.references_var(var) || b.references_var(var),
             UnaryOp(_, _, x) => x.references_var(var),
             IfElse(_, a, b, c) => a.references_var(var) || b.references_var(var) || c.references_var(var),
+            Lambda(_, param, body) => param != var && body.references_var(var),
             VarRef(_, x) => x == var,
         }
     }
@@ -135,6 +139,11 @@ impl SimplExpr {
                 k.collect_var_refs_into(dest);
                 v.collect_var_refs_into(dest);
             }),
+            Lambda(_, param, body) => {
+                let mut body_refs = body.collect_var_refs();
+                body_refs.retain(|x| x != param);
+                dest.extend(body_refs);
+            }
             Literal(_) => {}
         };
     }
@@ -159,6 +168,7 @@ impl Spanned for SimplExpr {
             SimplExpr::IfElse(span, ..) => *span,
             SimplExpr::JsonAccess(span, ..) => *span,
             SimplExpr::FunctionCall(span, ..) => *span,
+            SimplExpr::Lambda(span, ..) => *span,
         }
     }
 }
@@ -96,7 +96,7 @@ macro_rules! impl_dynval_from {
     };
 }
 
-impl_dynval_from!(bool, i32, u32, f32, u8, f64, &str);
+impl_dynval_from!(bool, i32, u32, i64, f32, u8, f64, &str);
 
 impl TryFrom<serde_json::Value> for DynVal {
     type Error = serde_json::Error;
@@ -259,6 +259,69 @@ impl DynVal {
     }
 }
 
+/// Type a variable can be declared with. Values are checked and normalized with [`VarType::convert`]
+/// when they are produced, so errors are reported once instead of wherever the value is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VarType {
+    #[default]
+    String,
+    Number,
+    Bool,
+    Json,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown variable type `{0}`, must be one of string, number, bool, json")]
+pub struct VarTypeParseError(String);
+
+impl FromStr for VarType {
+    type Err = VarTypeParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(VarType::String),
+            "number" => Ok(VarType::Number),
+            "bool" => Ok(VarType::Bool),
+            "json" => Ok(VarType::Json),
+            _ => Err(VarTypeParseError(s.to_string())),
+        }
+    }
+}
+
+impl VarType {
+    /// Value of a variable of this type that didn't get a value yet
+    pub fn default_value(&self) -> DynVal {
+        DynVal::from(match self {
+            VarType::String => "",
+            VarType::Number => "0",
+            VarType::Bool => "false",
+            VarType::Json => "null",
+        })
+    }
+
+    /// Check that the value is of this type, and normalize it:
+    /// - numbers have surrounding whitespace removed
+    /// - `true`, `yes`, `on` and `1` become `true`, `false`, `no`, `off` and `0` become `false`, ignoring case
+    /// - json is reformatted compactly
+    pub fn convert(&self, value: DynVal) -> Result<DynVal> {
+        let normalized = match self {
+            VarType::String => return Ok(value),
+            VarType::Number => {
+                let trimmed = value.0.trim();
+                trimmed.parse::<f64>().map_err(|e| ConversionError::new(value.clone(), "number", e))?;
+                trimmed.to_string()
+            }
+            VarType::Bool => match value.0.trim().to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => "true".to_string(),
+                "false" | "no" | "off" | "0" => "false".to_string(),
+                _ => return Err(ConversionError { value, target_type: "bool", source: None }),
+            },
+            VarType::Json => value.as_json_value()?.to_string(),
+        };
+        Ok(DynVal(normalized, value.1))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -285,4 +348,19 @@ mod test {
         insta::assert_debug_snapshot!(DynVal::from("1h").as_duration());
         insta::assert_debug_snapshot!(DynVal::from("0.5h").as_duration());
     }
+
+    #[test]
+    fn test_var_type_convert() {
+        assert_eq!(VarType::Number.convert(DynVal::from(" 42\n")).unwrap().0, "42");
+        assert!(VarType::Number.convert(DynVal::from("42%")).is_err());
+        assert_eq!(VarType::Bool.convert(DynVal::from("Yes")).unwrap().0, "true");
+        assert_eq!(VarType::Bool.convert(DynVal::from("0")).unwrap().0, "false");
+        assert!(VarType::Bool.convert(DynVal::from("maybe")).is_err());
+        assert_eq!(VarType::Json.convert(DynVal::from("{ \"a\": [1, 2] }")).unwrap().0, r#"{"a":[1,2]}"#);
+        assert!(VarType::Json.convert(DynVal::from("{")).is_err());
+        assert_eq!(VarType::String.convert(DynVal::from(" x ")).unwrap().0, " x ");
+        for var_type in [VarType::String, VarType::Number, VarType::Bool, VarType::Json] {
+            assert!(var_type.convert(var_type.default_value()).is_ok());
+        }
+    }
 }
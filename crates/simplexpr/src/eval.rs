@@ -46,6 +46,12 @@ pub enum EvalError {
     #[error("Unknown function {0}")]
     UnknownFunction(String),
 
+    #[error("Invalid range for clamp: the minimum {0} is greater than the maximum {1}")]
+    InvalidClampRange(f64, f64),
+
+    #[error("Lambdas can only be passed to map and filter")]
+    UnexpectedLambda,
+
     #[error("Unable to index into value {0}")]
     CannotIndex(String),
 
@@ -58,6 +64,9 @@ pub enum EvalError {
     #[error(transparent)]
     JaqParseError(Box<JaqParseError>),
 
+    #[error("Invalid json path `{0}`, expected something like `.a.b[0]`")]
+    InvalidJsonPath(String),
+
     #[error("Error parsing date: {0}")]
     ChronoError(String),
 
@@ -112,6 +121,13 @@ impl SimplExpr {
                 FunctionCall(span, name, args.into_iter().map(|x| x.try_map_var_refs(f)).collect::<Result<_, _>>()?)
             }
             VarRef(span, name) => f(span, name)?,
+            Lambda(span, param, body) => {
+                // The parameter of the lambda shadows any variable of the same name
+                let map_unless_param = |span, name: VarName| if name == param { Ok(VarRef(span, name)) } else { f(span, name) };
+                let map_unless_param: &dyn Fn(Span, VarName) -> Result<SimplExpr, E> = &map_unless_param;
+                let body = body.try_map_var_refs(map_unless_param)?;
+                Lambda(span, param, Box::new(body))
+            }
             JsonArray(span, values) => {
                 JsonArray(span, values.into_iter().map(|x| x.try_map_var_refs(f)).collect::<Result<_, _>>()?)
             }
@@ -168,6 +184,7 @@ impl SimplExpr {
                 refs
             }
             FunctionCall(_, _, args) => args.iter().flat_map(|a| a.var_refs_with_span()).collect(),
+            Lambda(_, param, body) => body.var_refs_with_span().into_iter().filter(|(_, name)| *name != param).collect(),
             JsonArray(_, values) => values.iter().flat_map(|v| v.var_refs_with_span()).collect(),
             JsonObject(_, entries) => {
                 entries.iter().flat_map(|(k, v)| k.var_refs_with_span().into_iter().chain(v.var_refs_with_span())).collect()
@@ -239,7 +256,7 @@ impl SimplExpr {
                             BinOp::GE => DynVal::from(a.as_f64()? >= b.as_f64()?),
                             BinOp::LE => DynVal::from(a.as_f64()? <= b.as_f64()?),
                             BinOp::RegexMatch => {
-                                let regex = regex::Regex::new(&b.as_string()?)?;
+                                let regex = compile_regex(b.as_string()?)?;
                                 DynVal::from(regex.is_match(&a.as_string()?))
                             }
                             _ => unreachable!("Lazy operators already handled"),
@@ -289,6 +306,16 @@ impl SimplExpr {
                     _ => Err(EvalError::CannotIndex(format!("{}", val)).at(*span)),
                 }
             }
+            SimplExpr::FunctionCall(span, function_name, args) if args.iter().any(|x| matches!(x, SimplExpr::Lambda(..))) => {
+                match args.as_slice() {
+                    [array, SimplExpr::Lambda(_, param, body)] => {
+                        let array = array.eval(values)?;
+                        call_lambda_function(function_name, array, param, body, values).map_err(|e| e.at(*span))
+                    }
+                    _ => Err(EvalError::UnexpectedLambda.at(*span)),
+                }
+            }
+            SimplExpr::Lambda(span, ..) => Err(EvalError::UnexpectedLambda.at(*span)),
             SimplExpr::FunctionCall(span, function_name, args) => {
                 let args = args.iter().map(|a| a.eval(values)).collect::<Result<_, EvalError>>()?;
                 call_expr_function(function_name, args).map(|x| x.at(*span)).map_err(|e| e.at(*span))
@@ -312,6 +339,44 @@ impl SimplExpr {
     }
 }
 
+/// Call a function that takes an array and a lambda, which gets evaluated for every element of the array with its
+/// parameter set to that element.
+fn call_lambda_function(
+    name: &str,
+    array: DynVal,
+    param: &VarName,
+    body: &SimplExpr,
+    values: &HashMap<VarName, DynVal>,
+) -> Result<DynVal, EvalError> {
+    use serde_json::Value;
+    let mut lambda_values = values.clone();
+    let mut call_lambda = |element: &Value| {
+        lambda_values.insert(param.clone(), DynVal::from(element));
+        body.eval(&lambda_values)
+    };
+    let elements = array.as_json_array()?;
+    let result = match name {
+        "map" => elements
+            .iter()
+            .map(|element| {
+                let result = call_lambda(element)?;
+                Ok(result.as_json_value().unwrap_or(Value::String(result.0)))
+            })
+            .collect::<Result<_, EvalError>>()?,
+        "filter" => {
+            let mut kept = Vec::new();
+            for element in elements {
+                if call_lambda(&element)?.as_bool()? {
+                    kept.push(element);
+                }
+            }
+            kept
+        }
+        _ => return Err(EvalError::UnexpectedLambda),
+    };
+    Ok(DynVal::try_from(Value::Array(result))?)
+}
+
 fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError> {
     match name {
         "get_env" => match args.as_slice() {
@@ -321,11 +386,39 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
             }
             _ => Err(EvalError::WrongArgCount(name.to_string())),
         },
+        "get_hostname" => match args.as_slice() {
+            [] => Ok(DynVal::from(get_hostname())),
+            _ => Err(EvalError::WrongArgCount(name.to_string())),
+        },
         "round" => match args.as_slice() {
             [num, digits] => {
                 let num = num.as_f64()?;
                 let digits = digits.as_i32()?;
-                Ok(DynVal::from(format!("{:.1$}", num, digits as usize)))
+                if digits < 0 {
+                    // Negative amounts of digits round to tens, hundreds, etc.
+                    let factor = 10f64.powi(-digits);
+                    Ok(DynVal::from((num / factor).round() * factor))
+                } else {
+                    Ok(DynVal::from(format!("{:.1$}", num, digits as usize)))
+                }
+            }
+            _ => Err(EvalError::WrongArgCount(name.to_string())),
+        },
+        "safediv" => match args.as_slice() {
+            [a, b, default] => {
+                let result = a.as_f64()? / b.as_f64()?;
+                Ok(if result.is_finite() { DynVal::from(result) } else { default.clone() })
+            }
+            _ => Err(EvalError::WrongArgCount(name.to_string())),
+        },
+        "clamp" => match args.as_slice() {
+            [num, min, max] => {
+                let (num, min, max) = (num.as_f64()?, min.as_f64()?, max.as_f64()?);
+                if min > max {
+                    return Err(EvalError::InvalidClampRange(min, max));
+                }
+                // Unlike f64::clamp, this turns NaN into the minimum
+                Ok(DynVal::from(num.max(min).min(max)))
             }
             _ => Err(EvalError::WrongArgCount(name.to_string())),
         },
@@ -420,7 +513,7 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
         "matches" => match args.as_slice() {
             [string, pattern] => {
                 let string = string.as_string()?;
-                let pattern = regex::Regex::new(&pattern.as_string()?)?;
+                let pattern = compile_regex(pattern.as_string()?)?;
                 Ok(DynVal::from(pattern.is_match(&string)))
             }
             _ => Err(EvalError::WrongArgCount(name.to_string())),
@@ -428,7 +521,7 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
         "replace" => match args.as_slice() {
             [string, pattern, replacement] => {
                 let string = string.as_string()?;
-                let pattern = regex::Regex::new(&pattern.as_string()?)?;
+                let pattern = compile_regex(pattern.as_string()?)?;
                 let replacement = replacement.as_string()?;
                 Ok(DynVal::from(pattern.replace_all(&string, replacement.replace('$', "$$").replace('\\', "$")).into_owned()))
             }
@@ -450,7 +543,7 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
             [string, pattern] => {
                 use serde_json::Value;
                 let string = string.as_string()?;
-                let pattern = regex::Regex::new(&pattern.as_string()?)?;
+                let pattern = compile_regex(pattern.as_string()?)?;
                 Ok(Value::Array(pattern.find_iter(&string).map(|x| Value::String(x.as_str().to_string())).collect())
                     .try_into()?)
             }
@@ -460,7 +553,7 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
             [string, pattern] => {
                 use serde_json::Value;
                 let string = string.as_string()?;
-                let pattern = regex::Regex::new(&pattern.as_string()?)?;
+                let pattern = compile_regex(pattern.as_string()?)?;
                 Ok(Value::Array(
                     pattern
                         .captures_iter(&string)
@@ -473,6 +566,14 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
             }
             _ => Err(EvalError::WrongArgCount(name.to_string())),
         },
+        "sum" => match args.as_slice() {
+            [json] => {
+                let elements = json.as_json_array()?;
+                let sum = elements.iter().map(|x| DynVal::from(x).as_f64()).sum::<Result<f64, _>>()?;
+                Ok(DynVal::from(sum))
+            }
+            _ => Err(EvalError::WrongArgCount(name.to_string())),
+        },
         "strlength" => match args.as_slice() {
             [string] => Ok(DynVal::from(string.as_string()?.len() as i32)),
             _ => Err(EvalError::WrongArgCount(name.to_string())),
@@ -492,11 +593,19 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
                 .map_err(|e| EvalError::Spanned(code.span(), Box::new(e))),
             _ => Err(EvalError::WrongArgCount(name.to_string())),
         },
+        "jsonget" => match args.as_slice() {
+            [json, path] => {
+                let json = json.as_json_value()?;
+                Ok(json_path_get(&json, &path.as_string()?)?.map(DynVal::from).unwrap_or_else(|| DynVal::from("")))
+            }
+            _ => Err(EvalError::WrongArgCount(name.to_string())),
+        },
         "formattime" => match args.as_slice() {
             [timestamp, format, timezone] => {
-                let timezone = match chrono_tz::Tz::from_str(&timezone.as_string()?) {
+                let timezone_name = timezone.as_string()?;
+                let timezone = match chrono_tz::Tz::from_str(&timezone_name) {
                     Ok(x) => x,
-                    Err(_) => return Err(EvalError::ChronoError("Invalid timezone".to_string())),
+                    Err(_) => return Err(EvalError::ChronoError(format!("Invalid timezone: {}", timezone_name))),
                 };
 
                 Ok(DynVal::from(match timezone.timestamp_opt(timestamp.as_i64()?, 0) {
@@ -526,6 +635,10 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
             })),
             _ => Err(EvalError::WrongArgCount(name.to_string())),
         },
+        "now" => match args.as_slice() {
+            [] => Ok(DynVal::from(Local::now().timestamp())),
+            _ => Err(EvalError::WrongArgCount(name.to_string())),
+        },
         "log" => match args.as_slice() {
             [num, n] => {
                 let num = num.as_f64()?;
@@ -539,6 +652,22 @@ fn call_expr_function(name: &str, args: Vec<DynVal>) -> Result<DynVal, EvalError
     }
 }
 
+/// Get the hostname of this machine, or an empty string if it can't be determined.
+fn get_hostname() -> String {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|x| x.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Compile a regex, reusing the compiled regex for patterns that were used recently.
+#[cached(size = 32, result = true, sync_writes = true)]
+fn compile_regex(pattern: String) -> Result<regex::Regex, EvalError> {
+    Ok(regex::Regex::new(&pattern)?)
+}
+
 #[cached(size = 10, result = true, sync_writes = true)]
 fn prepare_jaq_filter(code: String) -> Result<Arc<jaq_interpret::Filter>, EvalError> {
     let (filter, mut errors) = jaq_parse::parse(&code, jaq_parse::main());
@@ -574,6 +703,70 @@ fn run_jaq_function(json: serde_json::Value, code: String, args: &str) -> Result
         .map_err(|e| EvalError::JaqError(e.to_string()))
 }
 
+/// A single step of a path as used by `jsonget`.
+enum JsonPathSegment {
+    Key(String),
+    Index(i64),
+}
+
+/// Parse a path like `.a.b[0]` or `.["some key"][-1]`. The path `.` refers to the value itself.
+fn parse_json_path(path: &str) -> Result<Vec<JsonPathSegment>, EvalError> {
+    let invalid = || EvalError::InvalidJsonPath(path.to_string());
+    let mut segments = Vec::new();
+    let mut rest = path.trim();
+    if rest == "." {
+        return Ok(segments);
+    }
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            if after_dot.starts_with('[') {
+                rest = after_dot;
+                continue;
+            }
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            segments.push(JsonPathSegment::Key(after_dot[..end].to_string()));
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket.find(']').ok_or_else(invalid)?;
+            let inner = after_bracket[..end].trim();
+            segments.push(if inner.starts_with('"') {
+                JsonPathSegment::Key(serde_json::from_str(inner).map_err(|_| invalid())?)
+            } else {
+                JsonPathSegment::Index(inner.parse().map_err(|_| invalid())?)
+            });
+            rest = &after_bracket[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+    Ok(segments)
+}
+
+/// Look up the value at the given path, returning `None` if any part of the path doesn't exist.
+/// Negative indices count from the end of an array.
+fn json_path_get<'a>(json: &'a serde_json::Value, path: &str) -> Result<Option<&'a serde_json::Value>, EvalError> {
+    use serde_json::Value;
+    let mut current = json;
+    for segment in parse_json_path(path)? {
+        let next = match (segment, current) {
+            (JsonPathSegment::Key(key), Value::Object(object)) => object.get(&key),
+            (JsonPathSegment::Index(index), Value::Array(array)) => {
+                let index = if index < 0 { array.len() as i64 + index } else { index };
+                usize::try_from(index).ok().and_then(|index| array.get(index))
+            }
+            _ => None,
+        };
+        match next {
+            Some(next) => current = next,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::dynval::DynVal;
@@ -636,5 +829,39 @@ mod tests {
         jq_empty_arg(r#"jq("[ \"foo\" ]", ".[0]", "")"#) => Ok(DynVal::from(r#""foo""#)),
         jq_invalid_arg(r#"jq("[ \"foo\" ]", ".[0]", "hello")"#) => Ok(DynVal::from(r#""foo""#)),
         jq_no_arg(r#"jq("[ \"foo\" ]", ".[0]")"#) => Ok(DynVal::from(r#""foo""#)),
+        jq_nested_path(r#"jq("{\"a\": {\"b\": [3, 4]}}", ".a.b[1]")"#) => Ok(DynVal::from(4)),
+        formattime_with_timezone(r#"formattime(90000, "%d %H:%M", "UTC")"#) => Ok(DynVal::from("02 01:00")),
+        formattime_invalid_timezone(r#"formattime(0, "%H", "Mars/Olympus")"#) => Err(super::EvalError::ChronoError("Invalid timezone: Mars/Olympus".to_string())),
+        formattime_invalid_format(r#"formattime(0, "%Q", "UTC")"#) => Err(super::EvalError::ChronoError("Invalid time formatting string: %Q".to_string())),
+        now_is_after_epoch(r#"now() > 1700000000"#) => Ok(DynVal::from(true)),
+        jsonget_nested_path(r#"jsonget("{\"a\": {\"b\": [3, {\"c\": \"x\"}]}}", ".a.b[1].c")"#) => Ok(DynVal::from("x")),
+        jsonget_object(r#"jsonget("{\"a\": {\"b\": 1}}", ".a")"#) => Ok(DynVal::from(r#"{"b":1}"#)),
+        jsonget_quoted_key_negative_index(r#"jsonget("{\"a b\": [1, 2]}", ".[\"a b\"][-1]")"#) => Ok(DynVal::from(2)),
+        jsonget_missing_path(r#"jsonget("{\"a\": {}}", ".a.b[0]")"#) => Ok(DynVal::from("")),
+        jsonget_invalid_path(r#"jsonget("{}", "a..b")"#) => Err(super::EvalError::InvalidJsonPath("a..b".to_string())),
+        round_digits(r#"round(1234.5678, 2)"#) => Ok(DynVal::from("1234.57")),
+        round_negative_digits(r#"round(1250, -2)"#) => Ok(DynVal::from(1300)),
+        round_infinity(r#"round("inf", 2)"#) => Ok(DynVal::from("inf")),
+        safediv_normal(r#"safediv(6, 3, 0)"#) => Ok(DynVal::from(2)),
+        safediv_by_zero(r#"safediv(1, 0, "n/a")"#) => Ok(DynVal::from("n/a")),
+        safediv_zero_by_zero(r#"safediv(0, 0, 0)"#) => Ok(DynVal::from(0)),
+        safediv_nan(r#"safediv("NaN", 2, -1)"#) => Ok(DynVal::from(-1)),
+        safediv_infinity(r#"safediv("inf", 2, -1)"#) => Ok(DynVal::from(-1)),
+        clamp_above(r#"clamp(5, 0, 3)"#) => Ok(DynVal::from(3)),
+        clamp_below(r#"clamp(-1, 0, 3)"#) => Ok(DynVal::from(0)),
+        clamp_inside(r#"clamp(1.5, 0, 3)"#) => Ok(DynVal::from(1.5)),
+        clamp_nan(r#"clamp("NaN", 0, 3)"#) => Ok(DynVal::from(0)),
+        clamp_infinity(r#"clamp("-inf", 0, 3)"#) => Ok(DynVal::from(0)),
+        clamp_invalid_range(r#"clamp(1, 3, 0)"#) => Err(super::EvalError::InvalidClampRange(3.0, 0.0)),
+        map_numbers(r#"map("[1, 2, 3]", x => x * 2)"#) => Ok(DynVal::from("[2,4,6]")),
+        map_objects(r#"map("[{\"a\": 1}, {\"a\": 2}]", item => item.a)"#) => Ok(DynVal::from("[1,2]")),
+        filter_numbers(r#"filter("[-1, 0, 3]", x => x > 0)"#) => Ok(DynVal::from("[3]")),
+        sum_of_map(r#"sum(map("[1, 2, 3]", x => x * x))"#) => Ok(DynVal::from(14)),
+        sum_empty(r#"sum("[]")"#) => Ok(DynVal::from(0)),
+        lambda_outside_map(r#"strlength(x => x)"#) => Err(super::EvalError::UnexpectedLambda),
+        regex_matches(r#"matches("volume: 42%", "\\d+%")"#) => Ok(DynVal::from(true)),
+        regex_replace(r#"replace("a-b-c", "-", "+")"#) => Ok(DynVal::from("a+b+c")),
+        regex_captures(r#"captures("a=1", "(\\w)=(\\d)")"#) => Ok(DynVal::from(r#"[["a=1","a","1"]]"#)),
+        regex_invalid(r#"matches("a", "(")"#) => Err(super::compile_regex("(".to_string()).unwrap_err()),
     }
 }
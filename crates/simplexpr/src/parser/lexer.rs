@@ -28,6 +28,7 @@ pub enum Token {
     Elvis,
     SafeAccess,
     RegexMatch,
+    Arrow,
 
     Not,
     Negative,
@@ -89,6 +90,7 @@ regex_rules! {
     r"\?:"    => |_| Token::Elvis,
     r"\?\."    => |_| Token::SafeAccess,
     r"=~"    => |_| Token::RegexMatch,
+    r"=>"    => |_| Token::Arrow,
 
     r"!"     => |_| Token::Not,
     r"-"     => |_| Token::Negative,
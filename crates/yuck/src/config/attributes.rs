@@ -4,6 +4,7 @@ use simplexpr::{dynval::FromDynVal, eval::EvalError, SimplExpr};
 
 use crate::{
     error::{DiagError, DiagResult},
+    gen_diagnostic,
     parser::{ast::Ast, from_ast::FromAst},
 };
 use eww_shared_util::{AttrName, Span, Spanned};
@@ -115,14 +116,48 @@ impl Attributes {
 pub struct AttrSpec {
     pub name: AttrName,
     pub optional: bool,
+    /// Value used when the argument isn't given, declared as `name: value`. Arguments with a default are optional.
+    pub default: Option<SimplExpr>,
     pub span: Span,
 }
 
+impl AttrSpec {
+    /// Parse an argument list like `[a ?b c: "default"]`.
+    pub fn from_ast_list(asts: Vec<Ast>) -> DiagResult<Vec<Self>> {
+        let mut specs = Vec::new();
+        let mut asts = asts.into_iter();
+        while let Some(ast) = asts.next() {
+            match ast {
+                Ast::Symbol(span, symbol) if symbol.ends_with(':') => {
+                    let name = symbol.trim_end_matches(':');
+                    let name = name.strip_prefix('?').unwrap_or(name).to_string();
+                    let default_ast = asts.next().ok_or_else(|| {
+                        DiagError(gen_diagnostic! {
+                            msg = format!("Missing default value for argument `{}`", name),
+                            label = span => "Expected a value after this",
+                        })
+                    })?;
+                    let default_span = default_ast.span();
+                    let default = default_ast.as_simplexpr()?;
+                    specs.push(Self {
+                        name: AttrName(name),
+                        optional: true,
+                        default: Some(default),
+                        span: span.to(default_span),
+                    });
+                }
+                other => specs.push(Self::from_ast(other)?),
+            }
+        }
+        Ok(specs)
+    }
+}
+
 impl FromAst for AttrSpec {
     fn from_ast(e: Ast) -> DiagResult<Self> {
         let span = e.span();
         let symbol = e.as_symbol()?;
         let (name, optional) = if let Some(name) = symbol.strip_prefix('?') { (name.to_string(), true) } else { (symbol, false) };
-        Ok(Self { name: AttrName(name), optional, span })
+        Ok(Self { name: AttrName(name), optional, default: None, span })
     }
 }
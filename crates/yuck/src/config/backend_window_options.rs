@@ -37,17 +37,24 @@ pub enum Error {
 pub struct BackendWindowOptionsDef {
     pub wayland: WlBackendWindowOptionsDef,
     pub x11: X11BackendWindowOptionsDef,
+    pub reserve_follows_content: Option<SimplExpr>,
 }
 
 impl BackendWindowOptionsDef {
     pub fn eval(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<BackendWindowOptions, Error> {
-        Ok(BackendWindowOptions { wayland: self.wayland.eval(local_variables)?, x11: self.x11.eval(local_variables)? })
+        Ok(BackendWindowOptions {
+            wayland: self.wayland.eval(local_variables)?,
+            x11: self.x11.eval(local_variables)?,
+            reserve_follows_content: eval_opt_expr_as_bool(&self.reserve_follows_content, false, local_variables)?,
+        })
     }
 
     pub fn from_attrs(attrs: &mut Attributes) -> DiagResult<Self> {
         let struts = attrs.ast_optional("reserve")?;
         let window_type = attrs.ast_optional("windowtype")?;
-        let focusable = attrs.ast_optional("focusable")?;
+        // `:keyboard` is the name of this option in the layer-shell protocol, so it's accepted as an alternative
+        let keyboard = attrs.ast_optional("keyboard")?;
+        let focusable = keyboard.or(attrs.ast_optional("focusable")?);
         let x11 = X11BackendWindowOptionsDef {
             sticky: attrs.ast_optional("sticky")?,
             struts,
@@ -56,11 +63,14 @@ impl BackendWindowOptionsDef {
         };
         let wayland = WlBackendWindowOptionsDef {
             exclusive: attrs.ast_optional("exclusive")?,
+            exclusive_edge: attrs.ast_optional("exclusive-edge")?,
             focusable,
             namespace: attrs.ast_optional("namespace")?,
         };
 
-        Ok(Self { wayland, x11 })
+        let reserve_follows_content = attrs.ast_optional("reserve-follows-content")?;
+
+        Ok(Self { wayland, x11, reserve_follows_content })
     }
 }
 
@@ -69,6 +79,9 @@ impl BackendWindowOptionsDef {
 pub struct BackendWindowOptions {
     pub x11: X11BackendWindowOptions,
     pub wayland: WlBackendWindowOptions,
+    /// Whether the reserved space shrinks to the size the content of the window currently needs, i.e. when a revealer is
+    /// hidden, rather than staying at the size given by `:reserve` or `:exclusive`
+    pub reserve_follows_content: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize)]
@@ -111,7 +124,9 @@ impl X11BackendWindowOptionsDef {
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct WlBackendWindowOptions {
-    pub exclusive: bool,
+    pub exclusive: WlExclusiveZone,
+    /// Edge the exclusive zone is reserved at, instead of the one derived from the anchor
+    pub exclusive_edge: Option<Side>,
     pub focusable: WlWindowFocusable,
     pub namespace: Option<String>,
 }
@@ -120,6 +135,7 @@ pub struct WlBackendWindowOptions {
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct WlBackendWindowOptionsDef {
     pub exclusive: Option<SimplExpr>,
+    pub exclusive_edge: Option<SimplExpr>,
     pub focusable: Option<SimplExpr>,
     pub namespace: Option<SimplExpr>,
 }
@@ -127,7 +143,14 @@ pub struct WlBackendWindowOptionsDef {
 impl WlBackendWindowOptionsDef {
     fn eval(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<WlBackendWindowOptions, Error> {
         Ok(WlBackendWindowOptions {
-            exclusive: eval_opt_expr_as_bool(&self.exclusive, false, local_variables)?,
+            exclusive: match &self.exclusive {
+                Some(expr) => WlExclusiveZone::from_dynval(&expr.eval(local_variables)?)?,
+                None => WlExclusiveZone::default(),
+            },
+            exclusive_edge: match &self.exclusive_edge {
+                Some(expr) => Some(Side::from_dynval(&expr.eval(local_variables)?)?),
+                None => None,
+            },
             focusable: match &self.focusable {
                 Some(expr) => WlWindowFocusable::from_dynval(&expr.eval(local_variables)?)?,
                 None => WlWindowFocusable::default(),
@@ -151,6 +174,31 @@ fn eval_opt_expr_as_bool(
     })
 }
 
+/// Exclusive zone of a layer-shell surface, i.e. the space the compositor reserves for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, smart_default::SmartDefault, serde::Serialize)]
+pub enum WlExclusiveZone {
+    #[default]
+    Disabled,
+    /// Reserve the size of the window
+    Auto,
+    /// Reserve a fixed amount of pixels. A negative value makes the window ignore the exclusive zones of other surfaces
+    Fixed(i32),
+}
+
+impl WlExclusiveZone {
+    pub fn from_dynval(x: &DynVal) -> Result<Self, ConversionError> {
+        match x.as_bool() {
+            Ok(true) => Ok(Self::Auto),
+            Ok(false) => Ok(Self::Disabled),
+            Err(_) => Ok(Self::Fixed(x.as_i32()?)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::Disabled
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, smart_default::SmartDefault, serde::Serialize)]
 pub enum WlWindowFocusable {
     #[default]
@@ -165,7 +213,7 @@ impl FromStr for WlWindowFocusable {
         enum_parse! { "focusable", s,
             "none" => Self::None,
             "exclusive" => Self::Exclusive,
-            "ondemand" => Self::OnDemand,
+            "ondemand" | "on-demand" => Self::OnDemand,
             // legacy support
             "true" => Self::Exclusive,
             "false" => Self::None,
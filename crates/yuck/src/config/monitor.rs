@@ -13,6 +13,8 @@ pub enum MonitorIdentifier {
     List(Vec<MonitorIdentifier>),
     Numeric(i32),
     Name(String),
+    /// A regex matched against the connector names and models of the monitors, written as `/regex/`
+    Regex(String),
     Primary,
 }
 
@@ -40,6 +42,7 @@ impl From<&MonitorIdentifier> for DynVal {
             MonitorIdentifier::List(l) => l.iter().map(|x| x.into()).collect::<Vec<_>>().into(),
             MonitorIdentifier::Numeric(n) => DynVal::from(*n),
             MonitorIdentifier::Name(n) => DynVal::from(n.clone()),
+            MonitorIdentifier::Regex(r) => DynVal::from(format!("/{}/", r)),
             MonitorIdentifier::Primary => DynVal::from("<primary>"),
         }
     }
@@ -51,6 +54,7 @@ impl fmt::Display for MonitorIdentifier {
             Self::List(l) => write!(f, "[{}]", l.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ")),
             Self::Numeric(n) => write!(f, "{}", n),
             Self::Name(n) => write!(f, "{}", n),
+            Self::Regex(r) => write!(f, "/{}/", r),
             Self::Primary => write!(f, "<primary>"),
         }
     }
@@ -65,6 +69,8 @@ impl str::FromStr for MonitorIdentifier {
             Err(_) => {
                 if &s.to_lowercase() == "<primary>" {
                     Ok(Self::Primary)
+                } else if let Some(pattern) = s.strip_prefix('/').and_then(|x| x.strip_suffix('/')) {
+                    Ok(Self::Regex(pattern.to_owned()))
                 } else {
                     Ok(Self::Name(s.to_owned()))
                 }
@@ -1,4 +1,7 @@
-use simplexpr::{dynval::DynVal, SimplExpr};
+use simplexpr::{
+    dynval::{DynVal, VarType},
+    SimplExpr,
+};
 
 use crate::{
     error::{DiagError, DiagResult, DiagResultExt},
@@ -28,6 +31,13 @@ impl ScriptVarDefinition {
         }
     }
 
+    pub fn var_type(&self) -> VarType {
+        match self {
+            ScriptVarDefinition::Poll(x) => x.var_type,
+            ScriptVarDefinition::Listen(x) => x.var_type,
+        }
+    }
+
     pub fn command_span(&self) -> Option<Span> {
         match self {
             ScriptVarDefinition::Poll(x) => match x.command {
@@ -52,8 +62,15 @@ pub struct PollScriptVar {
     pub name: VarName,
     pub run_while_expr: SimplExpr,
     pub command: VarSource,
+    /// Value before the first poll. Only `None` for inbuilt variables, which are computed once at startup instead.
     pub initial_value: Option<DynVal>,
     pub interval: std::time::Duration,
+    /// Whether to randomize when the first scheduled poll happens within the interval
+    pub jitter: bool,
+    /// Whether to schedule polls on multiples of the interval since the unix epoch
+    pub align: bool,
+    /// Type the output of the command is checked against, see [`VarType::convert`]
+    pub var_type: VarType,
     pub name_span: Span,
 }
 
@@ -64,9 +81,17 @@ impl FromAstElementContent for PollScriptVar {
         let result: DiagResult<_> = (move || {
             let (name_span, name) = iter.expect_symbol()?;
             let mut attrs = iter.expect_key_values()?;
-            let initial_value = Some(attrs.primitive_optional("initial")?.unwrap_or_else(|| DynVal::from_string(String::new())));
+            let var_type: VarType = attrs.primitive_optional("type")?.unwrap_or_default();
+            // Without an initial value, the variable starts out as the default of its type until the poll handler
+            // first runs the command, which happens as soon as the variable is used.
+            let initial_value = match attrs.primitive_optional::<DynVal, _>("initial")? {
+                Some(value) => var_type.convert(value).map_err(|e| DiagError(e.to_diagnostic()))?,
+                None => var_type.default_value(),
+            };
             let interval =
                 attrs.primitive_required::<DynVal, _>("interval")?.as_duration().map_err(|e| DiagError(e.to_diagnostic()))?;
+            let jitter = attrs.primitive_optional("jitter")?.unwrap_or(false);
+            let align = attrs.primitive_optional("align")?.unwrap_or(false);
             let (script_span, script) = iter.expect_literal()?;
 
             let run_while_expr =
@@ -78,8 +103,11 @@ impl FromAstElementContent for PollScriptVar {
                 name: VarName(name),
                 run_while_expr,
                 command: VarSource::Shell(script_span, script.to_string()),
-                initial_value,
+                initial_value: Some(initial_value),
                 interval,
+                jitter,
+                align,
+                var_type,
             })
         })();
         result.note(r#"Expected format: `(defpoll name :interval "10s" "echo 'a shell script'")`"#)
@@ -91,6 +119,10 @@ pub struct ListenScriptVar {
     pub name: VarName,
     pub command: String,
     pub initial_value: DynVal,
+    /// Whether to restart the command when it fails
+    pub restart: bool,
+    /// Type the output of the command is checked against, see [`VarType::convert`]
+    pub var_type: VarType,
     pub command_span: Span,
     pub name_span: Span,
 }
@@ -101,10 +133,23 @@ impl FromAstElementContent for ListenScriptVar {
         let result: DiagResult<_> = (move || {
             let (name_span, name) = iter.expect_symbol()?;
             let mut attrs = iter.expect_key_values()?;
-            let initial_value = attrs.primitive_optional("initial")?.unwrap_or_else(|| DynVal::from_string(String::new()));
+            let var_type: VarType = attrs.primitive_optional("type")?.unwrap_or_default();
+            let initial_value = match attrs.primitive_optional::<DynVal, _>("initial")? {
+                Some(value) => var_type.convert(value).map_err(|e| DiagError(e.to_diagnostic()))?,
+                None => var_type.default_value(),
+            };
+            let restart = attrs.primitive_optional("restart")?.unwrap_or(true);
             let (command_span, script) = iter.expect_literal()?;
             iter.expect_done()?;
-            Ok(Self { name_span, name: VarName(name), command: script.to_string(), initial_value, command_span })
+            Ok(Self {
+                name_span,
+                name: VarName(name),
+                command: script.to_string(),
+                initial_value,
+                restart,
+                var_type,
+                command_span,
+            })
         })();
         result.note(r#"Expected format: `(deflisten name :initial "0" "tail -f /tmp/example")`"#)
     }
@@ -1,6 +1,7 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use itertools::Itertools;
@@ -15,6 +16,7 @@ use super::{
 use crate::{
     config::script_var_definition::{ListenScriptVar, PollScriptVar},
     error::{DiagError, DiagResult},
+    format_diagnostic::{span_to_secondary_label, DiagnosticExt, ToDiagnostic},
     gen_diagnostic,
     parser::{
         ast::Ast,
@@ -31,6 +33,8 @@ static TOP_LEVEL_DEFINITION_NAMES: &[&str] = &[
     ListenScriptVar::ELEMENT_NAME,
     PollScriptVar::ELEMENT_NAME,
     Include::ELEMENT_NAME,
+    MagicVarInterval::ELEMENT_NAME,
+    When::ELEMENT_NAME,
 ];
 
 #[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
@@ -49,8 +53,70 @@ impl FromAstElementContent for Include {
     }
 }
 
+/// The shortest interval a magic variable can be updated at
+pub const MIN_MAGIC_VAR_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Overrides the interval a built-in magic variable is updated at, i.e. `(magic-interval EWW_CPU "500ms")`
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct MagicVarInterval {
+    pub name: VarName,
+    pub name_span: Span,
+    pub interval: Duration,
+}
+
+impl FromAstElementContent for MagicVarInterval {
+    const ELEMENT_NAME: &'static str = "magic-interval";
+
+    fn from_tail<I: Iterator<Item = Ast>>(_span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let (name_span, name) = iter.expect_symbol()?;
+        let (interval_span, interval) = iter.expect_literal()?;
+        let interval = interval.as_duration().map_err(|e| DiagError(e.to_diagnostic()))?;
+        if interval < MIN_MAGIC_VAR_INTERVAL {
+            return Err(DiagError(gen_diagnostic! {
+                msg = format!("The interval of a magic variable must be at least {}ms", MIN_MAGIC_VAR_INTERVAL.as_millis()),
+                label = interval_span => "Too short",
+            }));
+        }
+        iter.expect_done()?;
+        Ok(MagicVarInterval { name: VarName(name), name_span, interval })
+    }
+}
+
+/// Only includes the definitions in its body if the condition is true, i.e. `(when {get_hostname() == "laptop"} (defwindow ...))`.
+/// The body is only parsed if the condition holds, so it may contain definitions that don't work on other machines.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct When {
+    pub condition: simplexpr::SimplExpr,
+    pub condition_span: Span,
+    pub body: Vec<Ast>,
+}
+
+impl FromAstElementContent for When {
+    const ELEMENT_NAME: &'static str = "when";
+
+    fn from_tail<I: Iterator<Item = Ast>>(_span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let (condition_span, condition) = iter.expect_simplexpr()?;
+        Ok(When { condition, condition_span, body: iter.collect() })
+    }
+}
+
+impl When {
+    /// Evaluate the condition. It can't refer to any variables, as it is evaluated before they exist.
+    pub fn is_enabled(&self) -> DiagResult<bool> {
+        let value = self.condition.eval_no_vars().map_err(|err| DiagError(err.to_diagnostic()))?;
+        value.as_bool().map_err(|err| {
+            DiagError(gen_diagnostic! {
+                msg = err,
+                label = self.condition_span => "Expected a boolean here",
+            })
+        })
+    }
+}
+
 pub enum TopLevel {
     Include(Include),
+    When(When),
+    MagicVarInterval(MagicVarInterval),
     VarDefinition(VarDefinition),
     ScriptVarDefinition(ScriptVarDefinition),
     WidgetDefinition(WidgetDefinition),
@@ -64,6 +130,8 @@ impl FromAst for TopLevel {
         let (sym_span, element_name) = iter.expect_symbol()?;
         Ok(match element_name.as_str() {
             x if x == Include::ELEMENT_NAME => Self::Include(Include::from_tail(span, iter)?),
+            x if x == When::ELEMENT_NAME => Self::When(When::from_tail(span, iter)?),
+            x if x == MagicVarInterval::ELEMENT_NAME => Self::MagicVarInterval(MagicVarInterval::from_tail(span, iter)?),
             x if x == WidgetDefinition::ELEMENT_NAME => Self::WidgetDefinition(WidgetDefinition::from_tail(span, iter)?),
             x if x == VarDefinition::ELEMENT_NAME => Self::VarDefinition(VarDefinition::from_tail(span, iter)?),
             x if x == PollScriptVar::ELEMENT_NAME => {
@@ -90,59 +158,85 @@ pub struct Config {
     pub window_definitions: HashMap<String, WindowDefinition>,
     pub var_definitions: HashMap<VarName, VarDefinition>,
     pub script_vars: HashMap<VarName, ScriptVarDefinition>,
+    pub magic_var_intervals: HashMap<VarName, MagicVarInterval>,
 }
 
 impl Config {
     fn append_toplevel(&mut self, files: &mut impl YuckFileProvider, toplevel: TopLevel) -> DiagResult<()> {
         match toplevel {
             TopLevel::VarDefinition(x) => {
-                if self.var_definitions.contains_key(&x.name) || self.script_vars.contains_key(&x.name) {
-                    return Err(DiagError(gen_diagnostic! {
-                        msg = format!("Variable {} defined twice", x.name),
-                        label = x.span => "defined again here",
-                    }));
+                if let Some(previous_span) = self.variable_definition_span(&x.name) {
+                    return Err(defined_twice_error("Variable", &x.name, x.span, previous_span));
                 } else {
                     self.var_definitions.insert(x.name.clone(), x);
                 }
             }
             TopLevel::ScriptVarDefinition(x) => {
-                if self.var_definitions.contains_key(x.name()) || self.script_vars.contains_key(x.name()) {
+                if let Some(previous_span) = self.variable_definition_span(x.name()) {
+                    return Err(defined_twice_error("Variable", x.name(), x.name_span(), previous_span));
+                } else {
+                    self.script_vars.insert(x.name().clone(), x);
+                }
+            }
+            TopLevel::MagicVarInterval(x) => {
+                if self.magic_var_intervals.contains_key(&x.name) {
                     return Err(DiagError(gen_diagnostic! {
-                        msg = format!("Variable {} defined twice", x.name()),
-                        label = x.name_span() => "defined again here",
+                        msg = format!("Interval of {} set twice", x.name),
+                        label = x.name_span => "set again here",
                     }));
                 } else {
-                    self.script_vars.insert(x.name().clone(), x);
+                    self.magic_var_intervals.insert(x.name.clone(), x);
                 }
             }
             TopLevel::WidgetDefinition(x) => {
+                if let Some(previous) = self.widget_definitions.get(&x.name) {
+                    return Err(defined_twice_error("Widget", &x.name, x.span, previous.span));
+                }
                 self.widget_definitions.insert(x.name.clone(), x);
             }
             TopLevel::WindowDefinition(x) => {
+                if let Some(previous) = self.window_definitions.get(&x.name) {
+                    return Err(defined_twice_error("Window", &x.name, x.span, previous.span));
+                }
                 self.window_definitions.insert(x.name.clone(), x);
             }
+            TopLevel::When(when) => {
+                if when.is_enabled()? {
+                    for element in when.body {
+                        self.append_toplevel(files, TopLevel::from_ast(element)?)?;
+                    }
+                }
+            }
             TopLevel::Include(include) => {
-                let (_, toplevels) = files.load_yuck_file(PathBuf::from(&include.path)).map_err(|err| match err {
-                    FilesError::IoError(_) => DiagError(gen_diagnostic! {
-                        msg = format!("Included file `{}` not found", include.path),
-                        label = include.path_span => "Included here",
-                    }),
-                    FilesError::DiagError(x) => x,
-                })?;
-                for element in toplevels {
-                    self.append_toplevel(files, TopLevel::from_ast(element)?)?;
+                for path in resolve_include_paths(&include)? {
+                    let (_, toplevels) = files.load_yuck_file(path.clone()).map_err(|err| match err {
+                        FilesError::IoError(_) => DiagError(gen_diagnostic! {
+                            msg = format!("Included file `{}` not found", path.display()),
+                            label = include.path_span => "Included here",
+                        }),
+                        FilesError::DiagError(x) => x,
+                    })?;
+                    for element in toplevels {
+                        self.append_toplevel(files, TopLevel::from_ast(element)?)?;
+                    }
                 }
             }
         }
         Ok(())
     }
 
+    /// Get the span of the existing definition of the variable with the given name, if there is one.
+    fn variable_definition_span(&self, name: &VarName) -> Option<Span> {
+        self.var_definitions.get(name).map(|x| x.span).or_else(|| self.script_vars.get(name).map(|x| x.name_span()))
+    }
+
     pub fn generate(files: &mut impl YuckFileProvider, elements: Vec<Ast>) -> DiagResult<Self> {
         let mut config = Self {
             widget_definitions: HashMap::new(),
             window_definitions: HashMap::new(),
             var_definitions: HashMap::new(),
             script_vars: HashMap::new(),
+            magic_var_intervals: HashMap::new(),
         };
         for element in elements {
             config.append_toplevel(files, TopLevel::from_ast(element)?)?;
@@ -158,3 +252,193 @@ impl Config {
         Self::generate(files, top_levels)
     }
 }
+
+fn defined_twice_error(kind: &str, name: impl std::fmt::Display, span: Span, previous_span: Span) -> DiagError {
+    DiagError(
+        gen_diagnostic! {
+            msg = format!("{} {} defined twice", kind, name),
+            label = span => "defined again here",
+        }
+        .with_label(span_to_secondary_label(previous_span).with_message("first defined here")),
+    )
+}
+
+/// Get the files an include refers to. The file name of the path may contain the wildcards `*` and `?`,
+/// in which case all matching files are included in alphabetical order.
+fn resolve_include_paths(include: &Include) -> DiagResult<Vec<PathBuf>> {
+    let is_pattern = |x: &str| x.contains(['*', '?']);
+    if !is_pattern(&include.path) {
+        return Ok(vec![PathBuf::from(&include.path)]);
+    }
+    let path = Path::new(&include.path);
+    let directory = path.parent().filter(|x| !x.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_pattern = path.file_name().map(|x| x.to_string_lossy()).unwrap_or_default();
+    if is_pattern(&directory.to_string_lossy()) {
+        return Err(DiagError(gen_diagnostic! {
+            msg = "Wildcards in includes are only supported in the file name",
+            label = include.path_span,
+        }));
+    }
+
+    let file_regex = format!("^{}$", regex::escape(&file_pattern).replace(r"\*", ".*").replace(r"\?", "."));
+    let file_regex = regex::Regex::new(&file_regex).expect("Escaped include pattern is not a valid regex");
+    let entries = std::fs::read_dir(directory).map_err(|err| {
+        DiagError(gen_diagnostic! {
+            msg = format!("Failed to read directory `{}`: {}", directory.display(), err),
+            label = include.path_span => "Included here",
+        })
+    })?;
+    let mut file_names: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|x| !x.is_dir()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| file_regex.is_match(name))
+        .collect();
+    if file_names.is_empty() {
+        return Err(DiagError(gen_diagnostic! {
+            msg = format!("No files match `{}`", include.path),
+            label = include.path_span => "Included here",
+        }));
+    }
+    file_names.sort();
+    Ok(file_names.into_iter().map(|name| directory.join(name)).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_toplevel;
+    use codespan_reporting::diagnostic::LabelStyle;
+
+    /// Provides no files at all, for configs without includes
+    struct NoFiles;
+
+    impl YuckFileProvider for NoFiles {
+        fn load_yuck_file(&mut self, path: PathBuf) -> Result<(Span, Vec<Ast>), FilesError> {
+            Err(FilesError::IoError(std::io::Error::new(std::io::ErrorKind::NotFound, path.display().to_string())))
+        }
+
+        fn load_yuck_str(&mut self, _name: String, content: String) -> Result<(Span, Vec<Ast>), DiagError> {
+            parse_toplevel(0, content)
+        }
+
+        fn unload(&mut self, _id: usize) {}
+    }
+
+    fn generate_config(code: &str) -> DiagResult<Config> {
+        let (_, toplevels) = parse_toplevel(0, code.to_string())?;
+        Config::generate(&mut NoFiles, toplevels)
+    }
+
+    /// An empty directory only used by the given test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yuck-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn include(path: impl AsRef<Path>) -> Include {
+        Include { path: path.as_ref().to_string_lossy().into_owned(), path_span: Span(1, 2, 0) }
+    }
+
+    #[test]
+    fn test_include_without_wildcards() {
+        assert_eq!(vec![PathBuf::from("./foo.yuck")], resolve_include_paths(&include("./foo.yuck")).unwrap());
+    }
+
+    #[test]
+    fn test_include_glob_is_sorted() {
+        let dir = test_dir("include-glob");
+        for file in ["b.yuck", "a.yuck", "c.txt", "ab.yuck"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        // Directories are never included, even if their name matches
+        std::fs::create_dir(dir.join("d.yuck")).unwrap();
+
+        let paths = resolve_include_paths(&include(dir.join("*.yuck"))).unwrap();
+        assert_eq!(vec![dir.join("a.yuck"), dir.join("ab.yuck"), dir.join("b.yuck")], paths);
+        let paths = resolve_include_paths(&include(dir.join("?.yuck"))).unwrap();
+        assert_eq!(vec![dir.join("a.yuck"), dir.join("b.yuck")], paths);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_glob_without_matches() {
+        let dir = test_dir("include-no-matches");
+        std::fs::write(dir.join("a.yuck"), "").unwrap();
+
+        let err = resolve_include_paths(&include(dir.join("*.scss"))).unwrap_err();
+        assert!(err.0.message.starts_with("No files match"), "unexpected error: {}", err.0.message);
+        assert_eq!(Span(1, 2, 0), Span(err.0.labels[0].range.start, err.0.labels[0].range.end, err.0.labels[0].file_id));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_wildcard_in_directory() {
+        let err = resolve_include_paths(&include("widgets/*/bar.yuck")).unwrap_err();
+        assert_eq!("Wildcards in includes are only supported in the file name", err.0.message);
+    }
+
+    #[test]
+    fn test_defined_twice_error() {
+        let err = defined_twice_error("Widget", "foo", Span(10, 13, 1), Span(2, 5, 0));
+        assert_eq!("Widget foo defined twice", err.0.message);
+        let labels: Vec<_> =
+            err.0.labels.iter().map(|label| (label.style, label.file_id, label.range.clone(), label.message.as_str())).collect();
+        assert_eq!(
+            vec![(LabelStyle::Primary, 1, 10..13, "defined again here"), (LabelStyle::Secondary, 0, 2..5, "first defined here")],
+            labels
+        );
+    }
+
+    #[test]
+    fn test_duplicate_definitions() {
+        let code = r#"
+            (defwindow foo (box))
+            (defwindow foo (label))
+        "#;
+        let err = generate_config(code).unwrap_err();
+        assert_eq!("Window foo defined twice", err.0.message);
+        let labels: Vec<_> = err.0.labels.iter().map(|label| &code[label.range.clone()]).collect();
+        assert_eq!(vec!["(defwindow foo (label))", "(defwindow foo (box))"], labels);
+
+        let err = generate_config("(defwidget foo [] (box)) (defwidget foo [] (label))").unwrap_err();
+        assert_eq!("Widget foo defined twice", err.0.message);
+        let err = generate_config(r#"(defvar foo 1) (defpoll foo :interval "1s" "date")"#).unwrap_err();
+        assert_eq!("Variable foo defined twice", err.0.message);
+        let err = generate_config(r#"(deflisten foo "date") (defvar foo 1)"#).unwrap_err();
+        assert_eq!("Variable foo defined twice", err.0.message);
+    }
+
+    #[test]
+    fn test_when() {
+        let config = generate_config(
+            r#"
+            (when {1 == 2}
+              (defvar skipped 1)
+              (this isn't (a valid toplevel)))
+            (when true
+              (defvar included 1))
+            "#,
+        )
+        .unwrap();
+        assert!(!config.var_definitions.contains_key(&VarName::from("skipped")));
+        assert!(config.var_definitions.contains_key(&VarName::from("included")));
+    }
+
+    #[test]
+    fn test_when_with_invalid_body() {
+        let err = generate_config(r#"(when true (this isn't (a valid toplevel)))"#).unwrap_err();
+        assert_eq!("Unknown toplevel declaration `this`", err.0.message);
+    }
+
+    #[test]
+    fn test_when_non_boolean_condition() {
+        let code = r#"(when "maybe" (defvar foo 1))"#;
+        let err = generate_config(code).unwrap_err();
+        let labels: Vec<_> = err.0.labels.iter().map(|label| label.message.as_str()).collect();
+        assert_eq!(vec!["Expected a boolean here"], labels);
+        assert_eq!(r#""maybe""#, &code[err.0.labels[0].range.clone()]);
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::{HashMap, HashSet};
 use simplexpr::SimplExpr;
 
 use super::{widget_definition::WidgetDefinition, widget_use::WidgetUse, Config};
+use crate::parser::from_ast::FromAst;
 use eww_shared_util::{AttrName, Span, Spanned, VarName};
 
 #[derive(Debug, thiserror::Error)]
@@ -13,6 +14,9 @@ pub enum ValidationError {
     #[error("Missing attribute `{arg_name}` in use of widget `{widget_name}`")]
     MissingAttr { widget_name: String, arg_name: AttrName, arg_list_span: Option<Span>, use_span: Span },
 
+    #[error("The let binding `{1}` is never used")]
+    UnusedLetBinding(Span, VarName),
+
     #[error("No variable named `{name}` in scope")]
     UnknownVariable {
         span: Span,
@@ -28,6 +32,7 @@ impl Spanned for ValidationError {
             ValidationError::MissingAttr { use_span, .. } => *use_span,
             ValidationError::UnknownVariable { span, .. } => *span,
             ValidationError::AccidentalBuiltinOverride(span, ..) => *span,
+            ValidationError::UnusedLetBinding(span, ..) => *span,
         }
     }
 }
@@ -42,6 +47,7 @@ pub fn validate(config: &Config, additional_globals: Vec<VarName>) -> Result<(),
         let local_var_names: HashSet<VarName> = std::iter::empty()
             .chain(var_names.iter().cloned())
             .chain(window.expected_args.iter().map(|x| VarName::from(x.name.clone())))
+            .chain(window.local_names())
             .collect();
         validate_variables_in_widget_use(&config.widget_definitions, &local_var_names, &window.widget, false)?;
     }
@@ -56,6 +62,17 @@ pub fn validate_widget_definition(
     globals: &HashSet<VarName>,
     def: &WidgetDefinition,
 ) -> Result<(), ValidationError> {
+    // Defaults are evaluated where the widget is used, so they can only refer to global variables
+    let unknown_var = def
+        .expected_args
+        .iter()
+        .filter_map(|arg| arg.default.as_ref())
+        .flat_map(|default| default.var_refs_with_span())
+        .find(|(_, var_ref)| !globals.contains(*var_ref));
+    if let Some((span, var)) = unknown_var {
+        return Err(ValidationError::UnknownVariable { span, name: var.clone(), in_definition: true });
+    }
+
     let mut variables_in_scope = globals.clone();
     for arg in def.expected_args.iter() {
         variables_in_scope.insert(VarName(arg.name.to_string()));
@@ -114,8 +131,89 @@ pub fn validate_variables_in_widget_use(
         }
         let mut variables = variables.clone();
         variables.insert(widget.element_name.clone());
+        variables.extend(widget.index_name.clone());
+        validate_variables_in_widget_use(defs, &variables, &widget.body, is_in_definition)?;
+    } else if let WidgetUse::Let(widget) = widget {
+        let mut variables = variables.clone();
+        for (i, binding) in widget.bindings.iter().enumerate() {
+            let unknown_var = binding.value.var_refs_with_span().into_iter().find(|(_, var_ref)| !variables.contains(*var_ref));
+            if let Some((span, var)) = unknown_var {
+                return Err(ValidationError::UnknownVariable { span, name: var.clone(), in_definition: is_in_definition });
+            }
+            let used_later = widget.bindings[i + 1..].iter().any(|later| later.value.references_var(&binding.name));
+            if !used_later && !widget_use_references_var(&widget.body, &binding.name) {
+                return Err(ValidationError::UnusedLetBinding(binding.name_span, binding.name.clone()));
+            }
+            variables.insert(binding.name.clone());
+        }
         validate_variables_in_widget_use(defs, &variables, &widget.body, is_in_definition)?;
     }
 
     Ok(())
 }
+
+/// Check if a variable is referenced anywhere within a widget use, including its children.
+fn widget_use_references_var(widget: &WidgetUse, var: &VarName) -> bool {
+    match widget {
+        WidgetUse::Basic(widget) => {
+            widget.attrs.attrs.values().any(|value| match value.value.as_simplexpr() {
+                Ok(expr) => expr.references_var(var),
+                // Attributes such as `:tooltip-widget` take a widget rather than an expression
+                Err(_) => WidgetUse::from_ast(value.value.clone()).is_ok_and(|widget| widget_use_references_var(&widget, var)),
+            }) || widget.children.iter().any(|child| widget_use_references_var(child, var))
+        }
+        WidgetUse::Loop(widget) => widget.elements_expr.references_var(var) || widget_use_references_var(&widget.body, var),
+        WidgetUse::Children(widget) => widget.nth_expr.as_ref().is_some_and(|expr| expr.references_var(var)),
+        WidgetUse::Let(widget) => {
+            widget.bindings.iter().any(|binding| binding.value.references_var(var))
+                || widget_use_references_var(&widget.body, var)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_string;
+
+    fn parse_widget_use(code: &str) -> WidgetUse {
+        WidgetUse::from_ast(parse_string(0, code).unwrap()).unwrap()
+    }
+
+    fn validate_widget_use(code: &str) -> Result<(), ValidationError> {
+        validate_variables_in_widget_use(&HashMap::new(), &HashSet::new(), &parse_widget_use(code), false)
+    }
+
+    #[test]
+    fn test_let_chained_bindings() {
+        let code = r#"(let [(a {1 + 1}) (b {a * 2})] (label :text b))"#;
+        let WidgetUse::Let(widget) = parse_widget_use(code) else { panic!("expected a let") };
+        let names: Vec<_> = widget.bindings.iter().map(|binding| binding.name.clone()).collect();
+        assert_eq!(vec![VarName::from("a"), VarName::from("b")], names);
+        assert!(matches!(*widget.body, WidgetUse::Basic(_)));
+        validate_widget_use(code).unwrap();
+    }
+
+    #[test]
+    fn test_let_unused_binding() {
+        let result = validate_widget_use(r#"(let [(a 1) (b 2)] (label :text b))"#);
+        assert!(matches!(result, Err(ValidationError::UnusedLetBinding(_, name)) if name == VarName::from("a")));
+    }
+
+    #[test]
+    fn test_let_used_in_widget_attr() {
+        validate_widget_use(r#"(let [(a "preview")] (button :tooltip-widget (label :text a) "hover"))"#).unwrap();
+    }
+
+    #[test]
+    fn test_let_forward_reference() {
+        let result = validate_widget_use(r#"(let [(a {b}) (b 1)] (label :text a))"#);
+        assert!(matches!(result, Err(ValidationError::UnknownVariable { name, .. }) if name == VarName::from("b")));
+    }
+
+    #[test]
+    fn test_let_malformed_binding() {
+        assert!(WidgetUse::from_ast(parse_string(0, r#"(let [(a 1 2)] (label :text a))"#).unwrap()).is_err());
+        assert!(WidgetUse::from_ast(parse_string(0, r#"(let [(a 1)])"#).unwrap()).is_err());
+    }
+}
@@ -39,7 +39,7 @@ impl FromAstElementContent for WidgetDefinition {
                 })
             })
             .note(EXPECTED_WIDGET_DEF_FORMAT)?;
-        let expected_args = expected_args.into_iter().map(AttrSpec::from_ast).collect::<DiagResult<_>>()?;
+        let expected_args = AttrSpec::from_ast_list(expected_args)?;
         let widget = iter.expect_any().map_err(DiagError::from).note(EXPECTED_WIDGET_DEF_FORMAT).and_then(WidgetUse::from_ast)?;
         iter.expect_done().map_err(|e| {
             DiagError(gen_diagnostic! {
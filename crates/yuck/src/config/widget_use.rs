@@ -19,17 +19,35 @@ pub enum WidgetUse {
     Basic(BasicWidgetUse),
     Loop(LoopWidgetUse),
     Children(ChildrenWidgetUse),
+    Let(LetWidgetUse),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
 pub struct LoopWidgetUse {
     pub element_name: VarName,
+    /// Name the index of the current element is bound to, given as `(for [index element] in ...)`
+    pub index_name: Option<VarName>,
     pub elements_expr: SimplExpr,
     pub elements_expr_span: Span,
     pub body: Box<WidgetUse>,
     pub span: Span,
 }
 
+/// Local bindings, given as `(let [(name expr) ...] body)`. Each binding can refer to the ones before it.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct LetWidgetUse {
+    pub bindings: Vec<LetBinding>,
+    pub body: Box<WidgetUse>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct LetBinding {
+    pub name: VarName,
+    pub name_span: Span,
+    pub value: SimplExpr,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
 pub struct ChildrenWidgetUse {
     pub span: Span,
@@ -70,7 +88,18 @@ impl FromAstElementContent for LoopWidgetUse {
     const ELEMENT_NAME: &'static str = "for";
 
     fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
-        let (_element_name_span, element_name) = iter.expect_symbol()?;
+        let (index_name, element_name) = match iter.expect_array() {
+            Ok((span, names)) => match names.as_slice() {
+                [Ast::Symbol(_, index_name), Ast::Symbol(_, element_name)] => (Some(index_name.clone()), element_name.clone()),
+                _ => {
+                    return Err(DiagError(gen_diagnostic! {
+                        msg = "Expected the names of the index and the element, like `[index element]`",
+                        label = span
+                    }))
+                }
+            },
+            Err(_) => (None, iter.expect_symbol()?.1),
+        };
         let (in_string_span, in_string) = iter.expect_symbol()?;
         if in_string != "in" {
             return Err(DiagError(gen_diagnostic! {
@@ -83,6 +112,7 @@ impl FromAstElementContent for LoopWidgetUse {
         iter.expect_done()?;
         Ok(Self {
             element_name: VarName(element_name),
+            index_name: index_name.map(VarName),
             elements_expr,
             body: Box::new(body),
             span,
@@ -91,6 +121,36 @@ impl FromAstElementContent for LoopWidgetUse {
     }
 }
 
+impl FromAstElementContent for LetWidgetUse {
+    const ELEMENT_NAME: &'static str = "let";
+
+    fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+        let (_, binding_asts) =
+            iter.expect_array().map_err(DiagError::from).note("Expected a list of bindings, like `[(name value)]`")?;
+        let bindings = binding_asts
+            .into_iter()
+            .map(|binding| {
+                let binding_span = binding.span();
+                let mut binding_iter = binding.try_ast_iter()?;
+                let (name_span, name) = binding_iter.expect_symbol()?;
+                let (_, value) =
+                    binding_iter.expect_simplexpr().map_err(DiagError::from).note("Expected a value for the binding")?;
+                binding_iter.expect_done().map_err(|_| {
+                    DiagError(gen_diagnostic! {
+                        msg = "A binding must consist of exactly a name and a value",
+                        label = binding_span
+                    })
+                })?;
+                Ok(LetBinding { name: VarName(name), name_span, value })
+            })
+            .collect::<DiagResult<Vec<_>>>()?;
+        let body =
+            iter.expect_any().map_err(DiagError::from).note("Expected a body for the let").and_then(WidgetUse::from_ast)?;
+        iter.expect_done()?;
+        Ok(Self { bindings, body: Box::new(body), span })
+    }
+}
+
 impl FromAstElementContent for ChildrenWidgetUse {
     const ELEMENT_NAME: &'static str = "children";
 
@@ -113,6 +173,7 @@ impl FromAst for WidgetUse {
             match name.as_ref() {
                 LoopWidgetUse::ELEMENT_NAME => Ok(WidgetUse::Loop(LoopWidgetUse::from_tail(span, iter)?)),
                 ChildrenWidgetUse::ELEMENT_NAME => Ok(WidgetUse::Children(ChildrenWidgetUse::from_tail(span, iter)?)),
+                LetWidgetUse::ELEMENT_NAME => Ok(WidgetUse::Let(LetWidgetUse::from_tail(span, iter)?)),
                 _ => Ok(WidgetUse::Basic(BasicWidgetUse::from_iter(span, name, name_span, iter)?)),
             }
         }
@@ -147,4 +208,4 @@ macro_rules! impl_spanned {
         }
     }
 }
-impl_spanned!(Basic => BasicWidgetUse, Loop => LoopWidgetUse, Children => ChildrenWidgetUse);
+impl_spanned!(Basic => BasicWidgetUse, Loop => LoopWidgetUse, Children => ChildrenWidgetUse, Let => LetWidgetUse);
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, time::Duration};
 
 use crate::{
     config::monitor::MonitorIdentifier,
@@ -29,16 +29,37 @@ pub enum WindowStackingConversionError {
     EnumParseError(#[from] EnumParseError),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum WindowTransitionConversionError {
+    #[error(transparent)]
+    EvalError(#[from] EvalError),
+    #[error(transparent)]
+    EnumParseError(#[from] EnumParseError),
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
 pub struct WindowDefinition {
     pub name: String,
     pub expected_args: Vec<AttrSpec>,
     pub args_span: Span,
+    pub span: Span,
     pub geometry: Option<WindowGeometryDef>,
     pub stacking: Option<SimplExpr>,
+    pub z_index: Option<SimplExpr>,
     pub monitor: Option<SimplExpr>,
     pub widget: WidgetUse,
     pub resizable: Option<SimplExpr>,
+    /// Whether the window closes as soon as it loses focus or the user clicks outside of it
+    pub close_on_unfocus: Option<SimplExpr>,
+    /// Path of a (s)css file, relative to the config directory, whose rules only apply within this window
+    pub stylesheet: Option<String>,
+    /// Object mapping key combinations to the command to run (or `close`) when they are pressed within this window
+    pub keybinds: Option<SimplExpr>,
+    /// Object mapping the names of window-local variables to their initial values
+    pub locals: Option<SimplExpr>,
+    pub open_transition: Option<SimplExpr>,
+    pub close_transition: Option<SimplExpr>,
+    pub transition_duration: Option<SimplExpr>,
     pub backend_options: BackendWindowOptionsDef,
 }
 
@@ -59,6 +80,14 @@ impl WindowDefinition {
         })
     }
 
+    /// Evaluate the `close-on-unfocus` field of the window definition
+    pub fn eval_close_on_unfocus(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<bool, EvalError> {
+        Ok(match &self.close_on_unfocus {
+            Some(expr) => expr.eval(local_variables)?.as_bool()?,
+            None => false,
+        })
+    }
+
     /// Evaluate the `stacking` field of the window definition
     pub fn eval_stacking(
         &self,
@@ -72,24 +101,130 @@ impl WindowDefinition {
             None => Ok(WindowStacking::Foreground),
         }
     }
+
+    /// Evaluate the `z-index` field of the window definition
+    pub fn eval_z_index(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<i32, EvalError> {
+        Ok(match &self.z_index {
+            Some(expr) => expr.eval(local_variables)?.as_i32()?,
+            None => 0,
+        })
+    }
+
+    /// Evaluate the `open-transition` or `close-transition` field of the window definition
+    fn eval_transition(
+        expr: &Option<SimplExpr>,
+        local_variables: &HashMap<VarName, DynVal>,
+    ) -> Result<WindowTransition, WindowTransitionConversionError> {
+        Ok(match expr {
+            Some(expr) => WindowTransition::from_dynval(&expr.eval(local_variables)?)?,
+            None => WindowTransition::None,
+        })
+    }
+
+    /// Evaluate the `open-transition` field of the window definition
+    pub fn eval_open_transition(
+        &self,
+        local_variables: &HashMap<VarName, DynVal>,
+    ) -> Result<WindowTransition, WindowTransitionConversionError> {
+        Self::eval_transition(&self.open_transition, local_variables)
+    }
+
+    /// Evaluate the `close-transition` field of the window definition
+    pub fn eval_close_transition(
+        &self,
+        local_variables: &HashMap<VarName, DynVal>,
+    ) -> Result<WindowTransition, WindowTransitionConversionError> {
+        Self::eval_transition(&self.close_transition, local_variables)
+    }
+
+    /// Evaluate the `transition-duration` field of the window definition
+    pub fn eval_transition_duration(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<Duration, EvalError> {
+        Ok(match &self.transition_duration {
+            Some(expr) => expr.eval(local_variables)?.as_duration()?,
+            None => Duration::from_millis(200),
+        })
+    }
+
+    /// Evaluate the `keybinds` field of the window definition into pairs of key combination and action
+    pub fn eval_keybinds(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<Vec<(String, String)>, EvalError> {
+        let Some(expr) = &self.keybinds else { return Ok(Vec::new()) };
+        let keybinds = expr.eval(local_variables)?.as_json_object()?;
+        Ok(keybinds
+            .into_iter()
+            .map(|(combo, action)| {
+                let action = action.as_str().map(str::to_string).unwrap_or_else(|| action.to_string());
+                (combo, action)
+            })
+            .collect())
+    }
+
+    /// Evaluate the `locals` field of the window definition into the initial values of the window-local variables
+    pub fn eval_locals(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<HashMap<VarName, DynVal>, EvalError> {
+        let Some(expr) = &self.locals else { return Ok(HashMap::new()) };
+        let locals = expr.eval(local_variables)?.as_json_object()?;
+        Ok(locals.into_iter().map(|(name, value)| (VarName(name), DynVal::from(&value))).collect())
+    }
+
+    /// Names of the window-local variables, as far as they can be known without evaluating any variables
+    pub fn local_names(&self) -> Vec<VarName> {
+        match &self.locals {
+            Some(SimplExpr::JsonObject(_, entries)) => {
+                entries.iter().filter_map(|(key, _)| key.eval_no_vars().ok()?.as_string().ok()).map(VarName).collect()
+            }
+            Some(expr) => expr
+                .eval_no_vars()
+                .ok()
+                .and_then(|locals| locals.as_json_object().ok())
+                .map(|locals| locals.into_iter().map(|(name, _)| VarName(name)).collect())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
 }
 
 impl FromAstElementContent for WindowDefinition {
     const ELEMENT_NAME: &'static str = "defwindow";
 
-    fn from_tail<I: Iterator<Item = Ast>>(_span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
+    fn from_tail<I: Iterator<Item = Ast>>(span: Span, mut iter: AstIterator<I>) -> DiagResult<Self> {
         let (_, name) = iter.expect_symbol()?;
         let (args_span, expected_args) = iter.expect_array().unwrap_or((Span::DUMMY, Vec::new()));
-        let expected_args = expected_args.into_iter().map(AttrSpec::from_ast).collect::<DiagResult<_>>()?;
+        let expected_args = AttrSpec::from_ast_list(expected_args)?;
         let mut attrs = iter.expect_key_values()?;
         let monitor = attrs.ast_optional("monitor")?;
         let resizable = attrs.ast_optional("resizable")?;
+        let close_on_unfocus = attrs.ast_optional("close-on-unfocus")?;
         let stacking = attrs.ast_optional("stacking")?;
+        let z_index = attrs.ast_optional("z-index")?;
         let geometry = attrs.ast_optional("geometry")?;
+        let stylesheet = attrs.primitive_optional("stylesheet")?;
+        let keybinds = attrs.ast_optional("keybinds")?;
+        let locals = attrs.ast_optional("locals")?;
+        let open_transition = attrs.ast_optional("open-transition")?;
+        let close_transition = attrs.ast_optional("close-transition")?;
+        let transition_duration = attrs.ast_optional("transition-duration")?;
         let backend_options = BackendWindowOptionsDef::from_attrs(&mut attrs)?;
         let widget = iter.expect_any().map_err(DiagError::from).and_then(WidgetUse::from_ast)?;
         iter.expect_done()?;
-        Ok(Self { name, expected_args, args_span, monitor, resizable, widget, stacking, geometry, backend_options })
+        Ok(Self {
+            name,
+            expected_args,
+            args_span,
+            span,
+            monitor,
+            resizable,
+            close_on_unfocus,
+            widget,
+            stacking,
+            z_index,
+            geometry,
+            stylesheet,
+            keybinds,
+            locals,
+            open_transition,
+            close_transition,
+            transition_duration,
+            backend_options,
+        })
     }
 }
 
@@ -141,10 +276,46 @@ impl std::str::FromStr for WindowStacking {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         enum_parse! { "WindowStacking", s,
-            "foreground" | "fg" => WindowStacking::Foreground,
+            "foreground" | "fg" | "top" => WindowStacking::Foreground,
             "background" | "bg" => WindowStacking::Background,
             "bottom" | "bt" => WindowStacking::Bottom,
             "overlay" | "ov" => WindowStacking::Overlay,
         }
     }
 }
+
+/// Animation shown when a window is opened or closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, smart_default::SmartDefault, serde::Serialize)]
+pub enum WindowTransition {
+    #[default]
+    None,
+    Fade,
+    SlideUp,
+    SlideDown,
+    SlideLeft,
+    SlideRight,
+}
+
+impl WindowTransition {
+    pub fn is_slide(&self) -> bool {
+        matches!(
+            self,
+            WindowTransition::SlideUp | WindowTransition::SlideDown | WindowTransition::SlideLeft | WindowTransition::SlideRight
+        )
+    }
+}
+
+impl std::str::FromStr for WindowTransition {
+    type Err = EnumParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        enum_parse! { "WindowTransition", s,
+            "none" => WindowTransition::None,
+            "fade" | "crossfade" => WindowTransition::Fade,
+            "slide-up" | "slideup" => WindowTransition::SlideUp,
+            "slide-down" | "slidedown" => WindowTransition::SlideDown,
+            "slide-left" | "slideleft" => WindowTransition::SlideLeft,
+            "slide-right" | "slideright" => WindowTransition::SlideRight,
+        }
+    }
+}
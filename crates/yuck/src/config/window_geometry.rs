@@ -149,6 +149,8 @@ fn convert_to_num_with_unit(
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct WindowGeometryDef {
     pub anchor_point: Option<SimplExpr>,
+    /// Id of another window that this window is positioned relative to, instead of the monitor
+    pub anchor_to: Option<SimplExpr>,
     pub offset: CoordsDef,
     pub size: CoordsDef,
 }
@@ -163,6 +165,7 @@ impl FromAstElementContent for WindowGeometryDef {
 
         Ok(WindowGeometryDef {
             anchor_point: attrs.ast_optional("anchor")?,
+            anchor_to: attrs.ast_optional("anchor-to")?,
             size: CoordsDef { x: attrs.ast_optional("width")?, y: attrs.ast_optional("height")? },
             offset: CoordsDef { x: attrs.ast_optional("x")?, y: attrs.ast_optional("y")? },
         })
@@ -180,6 +183,13 @@ impl WindowGeometryDef {
             offset: self.offset.eval(local_variables)?,
         })
     }
+
+    pub fn eval_anchor_to(&self, local_variables: &HashMap<VarName, DynVal>) -> Result<Option<String>, Error> {
+        match &self.anchor_to {
+            Some(expr) => Ok(Some(expr.eval(local_variables)?.as_string().map_err(EvalError::from)?)),
+            None => Ok(None),
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Serialize)]
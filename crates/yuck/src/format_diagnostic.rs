@@ -142,6 +142,11 @@ impl ToDiagnostic for ValidationError {
 
                 diag.with_notes(extra_notes)
             }
+            ValidationError::UnusedLetBinding(span, _name) => gen_diagnostic! {
+                msg = self,
+                label = span => "Defined here",
+                note = "Hint: Remove the binding if you don't need it"
+            },
             ValidationError::AccidentalBuiltinOverride(span, _widget_name) => gen_diagnostic! {
                 msg = self,
                 label = span => "Defined here",
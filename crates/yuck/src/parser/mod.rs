@@ -86,4 +86,31 @@ mod test {
             "\"h\\\"i\""
         );
     }
+
+    #[test]
+    fn test_attr_spec_defaults() {
+        use crate::config::attributes::AttrSpec;
+
+        let parse_attr_specs = |code: &str| match parse_string(0, code).unwrap() {
+            Ast::Array(_, asts) => AttrSpec::from_ast_list(asts),
+            other => panic!("expected an array, got {:?}", other),
+        };
+        let specs = parse_attr_specs(r#"[a ?b c: "default" ?d: {1 + 1}]"#).unwrap();
+        let specs: Vec<_> = specs
+            .iter()
+            .map(|spec| (spec.name.0.as_str(), spec.optional, spec.default.as_ref().map(|x| x.eval_no_vars().unwrap().0)))
+            .collect();
+        assert_eq!(
+            vec![
+                ("a", false, None),
+                ("b", true, None),
+                ("c", true, Some("default".to_string())),
+                ("d", true, Some("2".to_string()))
+            ],
+            specs
+        );
+
+        let err = parse_attr_specs(r#"[a c:]"#).unwrap_err();
+        assert_eq!("Missing default value for argument `c`", err.0.message);
+    }
 }